@@ -16,7 +16,7 @@ extern crate thincollections;
 use std::rc::Rc;
 
 
-use criterion::{Bencher, black_box, Criterion, criterion_group, criterion_main};
+use criterion::{Bencher, BenchmarkId, black_box, Criterion, criterion_group, criterion_main};
 
 use thincollections::thin_v64::V64;
 use thincollections::thin_vec::ThinVec;
@@ -158,6 +158,36 @@ fn benchv_1m_insert(c: &mut Criterion) {
     group.finish();
 }
 
+// grows from empty, so most of the time goes into reallocating large blocks
+fn benchv_push_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Vec push from empty");
+    group.sample_size(10);
+    for &n in &[1_000_000u32, 10_000_000, 100_000_000] {
+        group.bench_with_input(BenchmarkId::new("V64", n), &n, |b, &n| b.iter(|| {
+            let mut vec: V64<u32> = V64::new();
+            for i in 0..n {
+                vec.push(i);
+            }
+            black_box(vec.len());
+        }));
+        group.bench_with_input(BenchmarkId::new("Vec", n), &n, |b, &n| b.iter(|| {
+            let mut vec: Vec<u32> = Vec::new();
+            for i in 0..n {
+                vec.push(i);
+            }
+            black_box(vec.len());
+        }));
+        group.bench_with_input(BenchmarkId::new("ThinVec", n), &n, |b, &n| b.iter(|| {
+            let mut vec: ThinVec<u32> = ThinVec::new();
+            for i in 0..n {
+                vec.push(i);
+            }
+            black_box(vec.len());
+        }));
+    }
+    group.finish();
+}
+
 fn benchv_type_insert(c: &mut Criterion) {
     let mut group = c.benchmark_group("Vec Type insert");
     group.bench_function("ThinThin", benchv_thinthin);
@@ -183,6 +213,6 @@ fn benchv_powerset_rc_insert(c: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = benchv_1m_insert, benchv_type_insert, benchv_powerset_insert, benchv_powerset_rc_insert
+    targets = benchv_1m_insert, benchv_push_heavy, benchv_type_insert, benchv_powerset_insert, benchv_powerset_rc_insert
 }
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,71 @@
+/*
+ * Copyright 2018 Mohammad Rezaei.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+/*
+ * C view of a thincollections ThinVec<T>, as returned by ThinVec::into_raw.
+ *
+ * The heap block starts with a thin_vec_header, and the elements start
+ * max(sizeof(thin_vec_header), _Alignof(T)) bytes into the block.
+ * A null pointer is an empty vector that hasn't allocated.
+ *
+ * C code may read the header and the elements, lower len, or overwrite
+ * elements of plain data types. It must never free or reallocate the block;
+ * hand it back to Rust (ThinVec::from_raw) for that.
+ * Vectors of zero sized Rust types have no block and must not be passed here.
+ */
+
+#ifndef THINCOLLECTIONS_H
+#define THINCOLLECTIONS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct thin_vec_header {
+    size_t len;
+    size_t cap;
+} thin_vec_header;
+
+static inline size_t thin_vec_len(const thin_vec_header *v) {
+    return v ? v->len : 0;
+}
+
+static inline size_t thin_vec_capacity(const thin_vec_header *v) {
+    return v ? v->cap : 0;
+}
+
+/* Elements of a vector whose element type has the given alignment. Null for a null vector. */
+static inline void *thin_vec_elements(thin_vec_header *v, size_t align) {
+    size_t offset = sizeof(thin_vec_header);
+    if (!v) {
+        return NULL;
+    }
+    if (align > offset) {
+        offset = align;
+    }
+    return (char *) v + offset;
+}
+
+#ifdef __cplusplus
+#define THIN_VEC_ALIGNOF(type) alignof(type)
+#else
+#define THIN_VEC_ALIGNOF(type) _Alignof(type)
+#endif
+
+/* Typed element pointer, e.g. THIN_VEC_ELEMENTS(v, uint32_t) */
+#define THIN_VEC_ELEMENTS(v, type) ((type *) thin_vec_elements((v), THIN_VEC_ALIGNOF(type)))
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* THINCOLLECTIONS_H */
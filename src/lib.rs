@@ -14,6 +14,29 @@
 //! `std::collections::Vec` uses 3. This makes `ThinVec` a much better choice when it's used
 //! inside another data structure, such as a vector of vectors or a map of vectors, etc.
 //!
+//! `ThinVec32` is a `ThinVec` that stores its length and capacity as `u32`s, which halves the
+//! heap header for vectors that will never hold more than 4G elements.
+//!
+//! `ThinHeaderVec` is a `ThinVec` that also keeps a header of your choosing, such as flags or a
+//! cached hash, in the same heap block as its elements.
+//!
+//! `ThinArcSlice` is a reference counted, immutable slice. The counts, length and elements share one
+//! allocation and the pointer is a single word, unlike `Arc<Vec<T>>` or `Arc<[T]>`.
+//!
+//! `ThinBoxSlice` is a fixed size slice for frozen data: its heap block only has the length in
+//! front of the elements, and it converts to and from `ThinVec` reusing the heap block.
+//!
+//! `ThinVecDeque` is a double ended queue on a ring buffer. The head, length and capacity are kept
+//! in the heap block, and a `ThinVec` converts into one without copying.
+//!
+//! `ThinBinaryHeap` and `V64BinaryHeap` are priority queues stored in a `ThinVec` and a `V64`.
+//!
+//! `ThinJagged` stores rows of different lengths in a compressed sparse row layout: one vector
+//! of values and one of row offsets, instead of an allocation per row.
+//!
+//! `SortedThinVec` and `SortedV64` keep their elements sorted and unique, which makes them compact
+//! sets with binary search lookups and linear time `merge`, `union` and `intersection`.
+//!
 //! `ThinMap` is a specialized map replacement for small key values. It uses less memory than `HashMap`
 //! if `mem::size_of::<(K, V)>() < 18`. It's also 2x to 5x faster (see the benchmarks). It's perfect
 //! for all the primitives, or your own keys, but for custom keys, you must implement the `ThinSentinel`
@@ -21,17 +44,34 @@
 //!
 //! `ThinSet` uses `ThinMap` underneath, so it's great for elements up to 18 bytes.
 //!
+//! `ThinBitSet` is a bitmap set of small integers, such as `u8` or `u16` keys, with word at a
+//! time set operations and `rank`/`select`.
+//!
+//! `ThinSparseSet` and `ThinSparseMap` hold dense `u32` ids, such as entity indexes, with O(1)
+//! insert, remove, contains and clear, and iterate in insertion order.
+//!
 //! `V64` is a specialized vector replacement that uses a single 64bit value to represent itself.
 //! It can store up to 7 bytes in that, and then uses heap memory. It's ideal for small vectors,
 //! especially if those vectors are used inside other data structures.
 //!
+//! `V128` is the 128bit version of `V64`. It can store up to 15 bytes inline, and its heap
+//! memory has no header, as the length and capacity are kept in the second 64 bits.
+//!
+//! `ThinSmallVec<T, N>` keeps up to `N` elements inline, then moves them into a `ThinVec`.
+//!
+//! `ShrinkingV64` is a `V64` that moves its elements back inline, freeing the heap block, once
+//! removals leave few enough of them.
+//!
+//! `V64Str` is a UTF-8 string built on `V64<u8>`. Strings of up to 7 bytes need no heap memory,
+//! and it implements `ThinSentinel`, so it makes a compact `ThinMap` key.
+//!
 //! ## Usage
 //!
 //! Add this to your `Cargo.toml`:
 //!
 //! ```toml
 //! [dependencies]
-//! thincollections = "0.5.0"
+//! thincollections = "0.6.0"
 //! ```
 //!
 //! and this to your crate root:
@@ -48,7 +88,21 @@ pub mod thin_sentinel;
 pub mod thin_map;
 pub mod thin_set;
 pub mod thin_v64;
+pub mod thin_v64str;
+pub mod thin_shrinking_v64;
+pub mod thin_v128;
+pub mod thin_small_vec;
 pub mod thin_vec;
+pub mod thin_vec32;
+pub mod thin_header_vec;
+pub mod thin_arc_slice;
+pub mod thin_box_slice;
+pub mod thin_sorted_vec;
+pub mod thin_vec_deque;
+pub mod thin_binary_heap;
+pub mod thin_jagged;
+pub mod thin_bit_set;
+pub mod thin_sparse_set;
 pub mod thin_hasher;
 #[doc(hidden)]
 pub mod util;
@@ -108,6 +162,59 @@ macro_rules! v64 {
     });
 }
 
+/// Creates a [`V128`] containing the arguments.
+///
+/// `v128!` allows `V128`s to be defined with the same syntax as array expressions.
+/// There are two forms of this macro:
+///
+/// - Create a [`V128`] containing a given list of elements:
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_v128::V128;
+/// # fn main() {
+/// let v: V128<i32> = v128![1, 2, 3];
+/// assert_eq!(v[0], 1);
+/// assert_eq!(v[1], 2);
+/// assert_eq!(v[2], 3);
+/// # }
+/// ```
+///
+/// - Create a [`V128`] from a given element and size:
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_v128::V128;
+/// # fn main() {
+/// let v: V128<u64> = v128![1; 3];
+/// assert_eq!(3, v.len());
+/// # }
+/// ```
+///
+/// Note that unlike array expressions this syntax supports all elements
+/// which implement [`Clone`] and the number of elements doesn't have to be
+/// a constant.
+///
+/// This will use `clone` to duplicate an expression, so one should be careful
+/// using this with types having a nonstandard `Clone` implementation. For
+/// example, `v128![Rc::new(1); 5]` will create a vector of five references
+/// to the same boxed integer value, not five references pointing to independently
+/// boxed integers.
+#[macro_export]
+macro_rules! v128 {
+    // count helper: transform any expression into 1
+    (@one $x:expr) => (1usize);
+    ($elem:expr; $n:expr) => ({
+        $crate::thin_v128::V128::from_elem($elem, $n)
+    });
+    ($($x:expr),*$(,)*) => ({
+        let count = 0usize $(+ v128!(@one $x))*;
+        let mut vec = $crate::thin_v128::V128::with_capacity(count);
+        $(vec.push($x);)*
+        vec
+    });
+}
+
 /// Creates a [`ThinVec`] containing the arguments.
 ///
 /// `thinvec!` allows `ThinVec`s to be defined with the same syntax as array expressions.
@@ -162,6 +269,60 @@ macro_rules! thinvec {
     });
 }
 
+/// Creates a [`ThinVec32`] containing the arguments.
+///
+/// `thinvec32!` allows `ThinVec32`s to be defined with the same syntax as array expressions.
+/// There are two forms of this macro:
+///
+/// - Create a [`ThinVec32`] containing a given list of elements:
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_vec32::ThinVec32;
+/// # fn main() {
+/// let v: ThinVec32<i32> = thinvec32![1, 2, 3];
+/// assert_eq!(v[0], 1);
+/// assert_eq!(v[1], 2);
+/// assert_eq!(v[2], 3);
+/// # }
+/// ```
+///
+/// - Create a [`ThinVec32`] from a given element and size:
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_vec32::ThinVec32;
+/// # fn main() {
+/// let v: ThinVec32<u64> = thinvec32![1; 3];
+/// assert_eq!(3, v.len());
+/// // assert_eq!(v, ThinVec32::from_buf([1, 1, 1]));
+/// # }
+/// ```
+///
+/// Note that unlike array expressions this syntax supports all elements
+/// which implement [`Clone`] and the number of elements doesn't have to be
+/// a constant.
+///
+/// This will use `clone` to duplicate an expression, so one should be careful
+/// using this with types having a nonstandard `Clone` implementation. For
+/// example, `thinvec32![Rc::new(1); 5]` will create a vector of five references
+/// to the same boxed integer value, not five references pointing to independently
+/// boxed integers.
+#[macro_export]
+macro_rules! thinvec32 {
+    // count helper: transform any expression into 1
+    (@one $x:expr) => (1usize);
+    ($elem:expr; $n:expr) => ({
+        $crate::thin_vec32::ThinVec32::from_elem($elem, $n)
+    });
+    ($($x:expr),*$(,)*) => ({
+        let count = 0usize $(+ thinvec32!(@one $x))*;
+        let mut vec = $crate::thin_vec32::ThinVec32::with_capacity(count);
+        $(vec.push($x);)*
+        vec
+    });
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,520 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Portions copyright The Rust Project Developers. Licensed under
+// the MIT License.
+
+//! # `ThinArcSlice` an immutable, reference counted slice in a single usize-sized pointer.
+use std::{
+    alloc::{self, Layout},
+    marker, mem, ptr,
+};
+use std::borrow::Borrow;
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::num::NonZeroUsize;
+use std::ops::Deref;
+use std::process;
+use std::slice;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+
+use crate::thin_vec::{DANGLE, ThinVec};
+
+/// Going over this many references is almost certainly a leak; we abort like `std::sync::Arc`.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// A thread-safe, reference counted, immutable slice. Guaranteed to be a usize-sized pointer.
+///
+/// `ThinArcSlice<T>` is to `Arc<[T]>` what `ThinVec<T>` is to `Vec<T>`: the strong count, weak count,
+/// length and elements all live in one heap block, so the handle itself is a single word.
+/// `Arc<Vec<T>>` on the other hand needs two allocations, and `Arc<[T]>` is a two word fat pointer.
+///
+/// Cloning only bumps the reference count. Use `make_mut` for clone-on-write access and `downgrade`
+/// to get a [`Weak`] reference.
+///
+/// [`Weak`]: struct.Weak.html
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_arc_slice::ThinArcSlice;
+/// # fn main() {
+/// let a: ThinArcSlice<u32> = ThinArcSlice::from(thinvec![1, 2, 3]);
+/// let b = a.clone();
+/// assert_eq!(2, ThinArcSlice::strong_count(&a));
+/// assert_eq!(&b[..], &[1, 2, 3]);
+/// assert!(ThinArcSlice::ptr_eq(&a, &b));
+/// # }
+/// ```
+pub struct ThinArcSlice<T> {
+    u: NonZeroUsize,
+    _marker: marker::PhantomData<T>,
+}
+
+/// A weak reference to a `ThinArcSlice`. Guaranteed to be a usize-sized pointer.
+///
+/// A `Weak` doesn't keep the elements alive, only the allocation. Use `upgrade` to get
+/// a `ThinArcSlice` back, if there still is one.
+pub struct Weak<T> {
+    u: NonZeroUsize,
+    _marker: marker::PhantomData<T>,
+}
+
+unsafe impl<T: Sync + Send> Send for ThinArcSlice<T> {}
+
+unsafe impl<T: Sync + Send> Sync for ThinArcSlice<T> {}
+
+unsafe impl<T: Sync + Send> Send for Weak<T> {}
+
+unsafe impl<T: Sync + Send> Sync for Weak<T> {}
+
+// the heap block is [strong, weak, len, elements...]
+#[inline(always)]
+fn elements_offset<T>() -> usize {
+    let align = mem::align_of::<T>();
+    (mem::size_of::<usize>() * 3 + align - 1) & !(align - 1)
+}
+
+#[inline(always)]
+fn layout<T>(len: usize) -> Layout {
+    let size = len.checked_mul(mem::size_of::<T>())
+        .and_then(|x| x.checked_add(elements_offset::<T>()))
+        .expect("ThinArcSlice capacity overflow");
+    Layout::from_size_align(size, cmp::max(mem::align_of::<usize>(), mem::align_of::<T>())).unwrap()
+}
+
+#[inline(always)]
+fn strong<'a>(u: NonZeroUsize) -> &'a AtomicUsize {
+    unsafe { &*(u.get() as *const AtomicUsize) }
+}
+
+#[inline(always)]
+fn weak<'a>(u: NonZeroUsize) -> &'a AtomicUsize {
+    unsafe { &*(u.get() as *const AtomicUsize).add(1) }
+}
+
+#[inline(always)]
+fn block_len(u: NonZeroUsize) -> usize {
+    unsafe { *(u.get() as *const usize).add(2) }
+}
+
+#[inline(always)]
+fn elements<T>(u: NonZeroUsize) -> *mut T {
+    (u.get() + elements_offset::<T>()) as *mut T
+}
+
+/// Frees the allocation; the elements must already have been dropped or moved out.
+unsafe fn dealloc_block<T>(u: NonZeroUsize) {
+    alloc::dealloc(u.get() as *mut u8, layout::<T>(block_len(u)));
+}
+
+/// Allocates a block for `len` elements with a strong and weak count of one.
+/// The elements are left uninitialized.
+unsafe fn allocate_block<T>(len: usize) -> NonZeroUsize {
+    let layout = layout::<T>(len);
+    let p = alloc::alloc(layout);
+    if p.is_null() {
+        alloc::handle_alloc_error(layout);
+    }
+    let p = p as *mut usize;
+    ptr::write(p as *mut AtomicUsize, AtomicUsize::new(1));
+    ptr::write((p as *mut AtomicUsize).add(1), AtomicUsize::new(1));
+    *p.add(2) = len;
+    NonZeroUsize::new_unchecked(p as usize)
+}
+
+/// Drops whatever was written into a block that is still being built, if a clone panics.
+struct PartialBlock<T> {
+    u: NonZeroUsize,
+    written: usize,
+    _marker: marker::PhantomData<T>,
+}
+
+impl<T> Drop for PartialBlock<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(elements::<T>(self.u), self.written));
+            dealloc_block::<T>(self.u);
+        }
+    }
+}
+
+impl<T> ThinArcSlice<T> {
+    /// Constructs a new, empty `ThinArcSlice<T>`.
+    ///
+    /// Unlike `ThinVec::new`, this allocates, as the reference counts need a home.
+    pub fn new() -> ThinArcSlice<T> {
+        unsafe { ThinArcSlice::allocate(0) }
+    }
+
+    /// Allocates a `ThinArcSlice` for `len` elements; see `allocate_block`.
+    unsafe fn allocate(len: usize) -> ThinArcSlice<T> {
+        ThinArcSlice { u: allocate_block::<T>(len), _marker: marker::PhantomData }
+    }
+
+    /// Moves `len` elements starting at `src` into a new block. The caller must
+    /// make sure they aren't dropped at the source.
+    unsafe fn from_raw_elements(src: *const T, len: usize) -> ThinArcSlice<T> {
+        let arc = ThinArcSlice::allocate(len);
+        ptr::copy_nonoverlapping(src, elements::<T>(arc.u), len);
+        arc
+    }
+
+    /// Gets the number of strong (`ThinArcSlice`) pointers to this allocation.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        strong(this.u).load(SeqCst)
+    }
+
+    /// Gets the number of `Weak` pointers to this allocation.
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        let cnt = weak(this.u).load(SeqCst);
+        // If the weak count is currently locked, the count was 1 just before the lock.
+        if cnt == usize::MAX { 0 } else { cnt - 1 }
+    }
+
+    /// Returns `true` if the two `ThinArcSlice`s point to the same allocation.
+    ///
+    /// ```
+    /// # use thincollections::thin_arc_slice::ThinArcSlice;
+    /// let a: ThinArcSlice<u8> = ThinArcSlice::from(&[1u8, 2][..]);
+    /// let b: ThinArcSlice<u8> = ThinArcSlice::from(&[1u8, 2][..]);
+    /// assert!(ThinArcSlice::ptr_eq(&a, &a.clone()));
+    /// assert!(!ThinArcSlice::ptr_eq(&a, &b));
+    /// ```
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.u == other.u
+    }
+
+    /// Creates a new `Weak` pointer to this allocation.
+    ///
+    /// ```
+    /// # use thincollections::thin_arc_slice::ThinArcSlice;
+    /// let a: ThinArcSlice<u8> = ThinArcSlice::from(vec![1u8, 2]);
+    /// let w = ThinArcSlice::downgrade(&a);
+    /// assert_eq!(&w.upgrade().unwrap()[..], &[1, 2]);
+    /// drop(a);
+    /// assert!(w.upgrade().is_none());
+    /// ```
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let weak = weak(this.u);
+        let mut cur = weak.load(Relaxed);
+        loop {
+            // usize::MAX means is_unique holds the weak count locked
+            if cur == usize::MAX {
+                cur = weak.load(Relaxed);
+                continue;
+            }
+            if cur > MAX_REFCOUNT {
+                process::abort();
+            }
+            match weak.compare_exchange_weak(cur, cur + 1, Acquire, Relaxed) {
+                Ok(_) => return Weak { u: this.u, _marker: marker::PhantomData },
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// Returns a mutable slice if there are no other `ThinArcSlice` or `Weak` pointers
+    /// to this allocation.
+    pub fn get_mut(this: &mut Self) -> Option<&mut [T]> {
+        if this.is_unique() {
+            unsafe { Some(slice::from_raw_parts_mut(elements::<T>(this.u), block_len(this.u))) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of elements in the slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        block_len(self.u)
+    }
+
+    /// Returns `true` if the slice has no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Extracts a slice containing all the elements.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(elements::<T>(self.u), block_len(self.u)) }
+    }
+
+    /// The number of bytes allocated for the counts, length and elements.
+    pub fn bytes_on_heap(&self) -> usize {
+        layout::<T>(self.len()).size()
+    }
+
+    fn is_unique(&mut self) -> bool {
+        // Lock the weak count so no Weak can be created between checking the two counts.
+        if weak(self.u).compare_exchange(1, usize::MAX, Acquire, Relaxed).is_ok() {
+            let unique = strong(self.u).load(Acquire) == 1;
+            weak(self.u).store(1, Release);
+            unique
+        } else {
+            false
+        }
+    }
+}
+
+impl<T: Clone> ThinArcSlice<T> {
+    /// Makes a mutable slice out of this `ThinArcSlice`.
+    ///
+    /// If there are other `ThinArcSlice`s pointing to the same allocation, the elements
+    /// are cloned into a new allocation first. If there are only `Weak` pointers, the elements
+    /// are moved instead, and the `Weak`s will no longer upgrade.
+    ///
+    /// ```
+    /// # use thincollections::thin_arc_slice::ThinArcSlice;
+    /// let mut a: ThinArcSlice<u32> = ThinArcSlice::from(vec![1, 2, 3]);
+    /// let b = a.clone();
+    /// ThinArcSlice::make_mut(&mut a)[0] = 10;
+    /// assert_eq!(&a[..], &[10, 2, 3]);
+    /// assert_eq!(&b[..], &[1, 2, 3]);
+    /// ```
+    pub fn make_mut(this: &mut Self) -> &mut [T] {
+        if strong(this.u).compare_exchange(1, 0, Acquire, Relaxed).is_err() {
+            // Other strong pointers exist, so clone the elements.
+            *this = ThinArcSlice::from(this.as_slice());
+        } else if weak(this.u).load(Relaxed) != 1 {
+            // We were the only strong pointer, but Weaks are still around.
+            // Move the elements out and let the Weaks keep the empty block.
+            let _weak: Weak<T> = Weak { u: this.u, _marker: marker::PhantomData };
+            unsafe {
+                let fresh = ThinArcSlice::from_raw_elements(elements::<T>(this.u), block_len(this.u));
+                ptr::write(this, fresh);
+            }
+        } else {
+            // Unique after all, undo the strong count change.
+            strong(this.u).store(1, Release);
+        }
+        unsafe { slice::from_raw_parts_mut(elements::<T>(this.u), block_len(this.u)) }
+    }
+}
+
+impl<T> Weak<T> {
+    /// Constructs a new `Weak<T>` without an allocation. `upgrade` always returns `None`.
+    pub fn new() -> Weak<T> {
+        unsafe { Weak { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData } }
+    }
+
+    #[inline(always)]
+    fn is_dangling(&self) -> bool {
+        self.u.get() == DANGLE
+    }
+
+    /// Attempts to get a `ThinArcSlice` for this allocation.
+    ///
+    /// Returns `None` if all the `ThinArcSlice`s have been dropped.
+    pub fn upgrade(&self) -> Option<ThinArcSlice<T>> {
+        if self.is_dangling() {
+            return None;
+        }
+        let strong = strong(self.u);
+        let mut n = strong.load(Relaxed);
+        loop {
+            if n == 0 {
+                return None;
+            }
+            if n > MAX_REFCOUNT {
+                process::abort();
+            }
+            match strong.compare_exchange_weak(n, n + 1, Acquire, Relaxed) {
+                Ok(_) => return Some(ThinArcSlice { u: self.u, _marker: marker::PhantomData }),
+                Err(old) => n = old,
+            }
+        }
+    }
+
+    /// Gets the number of strong (`ThinArcSlice`) pointers to this allocation.
+    pub fn strong_count(&self) -> usize {
+        if self.is_dangling() { 0 } else { strong(self.u).load(SeqCst) }
+    }
+}
+
+impl<T> Clone for ThinArcSlice<T> {
+    #[inline]
+    fn clone(&self) -> ThinArcSlice<T> {
+        if strong(self.u).fetch_add(1, Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+        ThinArcSlice { u: self.u, _marker: marker::PhantomData }
+    }
+}
+
+impl<T> Drop for ThinArcSlice<T> {
+    fn drop(&mut self) {
+        if strong(self.u).fetch_sub(1, Release) != 1 {
+            return;
+        }
+        atomic::fence(Acquire);
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(elements::<T>(self.u), block_len(self.u)));
+        }
+        // the strong pointers collectively hold one weak reference
+        drop(Weak::<T> { u: self.u, _marker: marker::PhantomData });
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    #[inline]
+    fn clone(&self) -> Weak<T> {
+        if !self.is_dangling() && weak(self.u).fetch_add(1, Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+        Weak { u: self.u, _marker: marker::PhantomData }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        if self.is_dangling() {
+            return;
+        }
+        if weak(self.u).fetch_sub(1, Release) == 1 {
+            atomic::fence(Acquire);
+            unsafe { dealloc_block::<T>(self.u); }
+        }
+    }
+}
+
+impl<T> Default for Weak<T> {
+    fn default() -> Weak<T> {
+        Weak::new()
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+impl<T> Deref for ThinArcSlice<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> AsRef<[T]> for ThinArcSlice<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> Borrow<[T]> for ThinArcSlice<T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> Default for ThinArcSlice<T> {
+    fn default() -> ThinArcSlice<T> {
+        ThinArcSlice::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ThinArcSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash> Hash for ThinArcSlice<T> {
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        Hash::hash(&**self, state)
+    }
+}
+
+impl<T: PartialEq> PartialEq for ThinArcSlice<T> {
+    #[inline]
+    fn eq(&self, other: &ThinArcSlice<T>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq> Eq for ThinArcSlice<T> {}
+
+impl<T: PartialOrd> PartialOrd for ThinArcSlice<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &ThinArcSlice<T>) -> Option<Ordering> {
+        PartialOrd::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<T: Ord> Ord for ThinArcSlice<T> {
+    #[inline]
+    fn cmp(&self, other: &ThinArcSlice<T>) -> Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+impl<'a, T: Clone> From<&'a [T]> for ThinArcSlice<T> {
+    fn from(s: &'a [T]) -> ThinArcSlice<T> {
+        unsafe {
+            // only the guard owns the block until every clone is in place
+            let u = allocate_block::<T>(s.len());
+            let mut guard: PartialBlock<T> = PartialBlock { u, written: 0, _marker: marker::PhantomData };
+            let dst = elements::<T>(u);
+            for x in s {
+                ptr::write(dst.add(guard.written), x.clone());
+                guard.written += 1;
+            }
+            mem::forget(guard);
+            ThinArcSlice { u, _marker: marker::PhantomData }
+        }
+    }
+}
+
+impl<T> From<ThinVec<T>> for ThinArcSlice<T> {
+    fn from(mut v: ThinVec<T>) -> ThinArcSlice<T> {
+        unsafe {
+            let len = v.len();
+            v.set_len(0);
+            ThinArcSlice::from_raw_elements(v.as_ptr(), len)
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for ThinArcSlice<T> {
+    fn from(mut v: Vec<T>) -> ThinArcSlice<T> {
+        unsafe {
+            let len = v.len();
+            v.set_len(0);
+            ThinArcSlice::from_raw_elements(v.as_ptr(), len)
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ThinArcSlice<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinArcSlice<T> {
+        ThinArcSlice::from(iter.into_iter().collect::<ThinVec<T>>())
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ThinArcSlice<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
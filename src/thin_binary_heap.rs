@@ -0,0 +1,480 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Portions copyright The Rust Project Developers. Licensed under
+// the MIT License.
+
+//! # Priority queues on thin vectors.
+//! `ThinBinaryHeap` is a max-heap stored in a `ThinVec`, and `V64BinaryHeap` is the same heap
+//! stored in a `V64`, so tiny heaps live inline with no allocation.
+use std::fmt;
+use std::iter::FromIterator;
+use std::mem::{self, ManuallyDrop};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+use crate::thin_v64::{self, V64};
+use crate::thin_vec::{self, ThinVec};
+
+/// A hole in a slice: an index whose value has been moved out and is held here, so the
+/// heap operations move each element once instead of swapping. Filling the hole on drop keeps
+/// the slice whole if a comparison panics.
+struct Hole<'a, T: 'a> {
+    data: &'a mut [T],
+    elt: ManuallyDrop<T>,
+    pos: usize,
+}
+
+impl<'a, T> Hole<'a, T> {
+    /// Unsafe because `pos` must be within the data slice.
+    #[inline]
+    unsafe fn new(data: &'a mut [T], pos: usize) -> Self {
+        debug_assert!(pos < data.len());
+        let elt = ptr::read(data.get_unchecked(pos));
+        Hole { data, elt: ManuallyDrop::new(elt), pos }
+    }
+
+    #[inline]
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    fn element(&self) -> &T {
+        &self.elt
+    }
+
+    /// Unsafe because `index` must be within the data slice and not equal to pos.
+    #[inline]
+    unsafe fn get(&self, index: usize) -> &T {
+        debug_assert!(index != self.pos);
+        debug_assert!(index < self.data.len());
+        self.data.get_unchecked(index)
+    }
+
+    /// Moves the hole to `index`. Unsafe because `index` must be within the data slice and not
+    /// equal to pos.
+    #[inline]
+    unsafe fn move_to(&mut self, index: usize) {
+        debug_assert!(index != self.pos);
+        debug_assert!(index < self.data.len());
+        let ptr = self.data.as_mut_ptr();
+        ptr::copy_nonoverlapping(ptr.add(index), ptr.add(self.pos), 1);
+        self.pos = index;
+    }
+}
+
+impl<'a, T> Drop for Hole<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // fill the hole again
+        unsafe {
+            let pos = self.pos;
+            ptr::copy_nonoverlapping(&*self.elt, self.data.get_unchecked_mut(pos), 1);
+        }
+    }
+}
+
+// Moves the element at `pos` up towards `start`; returns its new position.
+fn sift_up<T: Ord>(data: &mut [T], start: usize, pos: usize) -> usize {
+    unsafe {
+        let mut hole = Hole::new(data, pos);
+        while hole.pos() > start {
+            let parent = (hole.pos() - 1) / 2;
+            if hole.element() <= hole.get(parent) {
+                break;
+            }
+            hole.move_to(parent);
+        }
+        hole.pos()
+    }
+}
+
+// Moves the element at `pos` down, considering only the elements before `end`.
+fn sift_down_range<T: Ord>(data: &mut [T], pos: usize, end: usize) {
+    unsafe {
+        let mut hole = Hole::new(data, pos);
+        let mut child = 2 * pos + 1;
+        while child < end {
+            let right = child + 1;
+            // pick the greater of the two children
+            if right < end && hole.get(child) <= hole.get(right) {
+                child = right;
+            }
+            if hole.element() >= hole.get(child) {
+                break;
+            }
+            hole.move_to(child);
+            child = 2 * hole.pos() + 1;
+        }
+    }
+}
+
+// Moves the element at `pos` all the way down to a leaf, then sifts it back up. The element
+// came from the bottom of the heap, so it's likely to belong near the bottom: this does fewer
+// comparisons than `sift_down_range`.
+fn sift_down_to_bottom<T: Ord>(data: &mut [T], mut pos: usize) {
+    let end = data.len();
+    let start = pos;
+    unsafe {
+        let mut hole = Hole::new(data, pos);
+        let mut child = 2 * pos + 1;
+        while child < end {
+            let right = child + 1;
+            if right < end && hole.get(child) <= hole.get(right) {
+                child = right;
+            }
+            hole.move_to(child);
+            child = 2 * hole.pos() + 1;
+        }
+        pos = hole.pos;
+    }
+    sift_up(data, start, pos);
+}
+
+fn rebuild<T: Ord>(data: &mut [T]) {
+    let len = data.len();
+    let mut n = len / 2;
+    while n > 0 {
+        n -= 1;
+        sift_down_range(data, n, len);
+    }
+}
+
+// Restores the heap after the elements from `start` on were appended, by sifting each of them
+// up, or by rebuilding the whole heap when that's cheaper.
+fn rebuild_tail<T: Ord>(data: &mut [T], start: usize) {
+    let len = data.len();
+    if start == len {
+        return;
+    }
+    let tail_len = len - start;
+    let log2 = (usize::BITS - len.leading_zeros()) as usize;
+    // rebuilding takes about 2 * len comparisons, sifting up about tail_len * log2(start)
+    if tail_len.saturating_mul(log2) < 2 * len {
+        for i in start..len {
+            sift_up(data, 0, i);
+        }
+    } else {
+        rebuild(data);
+    }
+}
+
+/// A mutable reference to the greatest item of a heap, from `peek_mut`.
+///
+/// When it's dropped, the item is moved down to its place if it was changed.
+pub struct PeekMut<'a, T: 'a + Ord> {
+    data: &'a mut [T],
+    sift: bool,
+}
+
+impl<'a, T: Ord + fmt::Debug> fmt::Debug for PeekMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PeekMut").field(&self.data[0]).finish()
+    }
+}
+
+impl<'a, T: Ord> Drop for PeekMut<'a, T> {
+    fn drop(&mut self) {
+        if self.sift {
+            let len = self.data.len();
+            sift_down_range(self.data, 0, len);
+        }
+    }
+}
+
+impl<'a, T: Ord> Deref for PeekMut<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.data[0]
+    }
+}
+
+impl<'a, T: Ord> DerefMut for PeekMut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.sift = true;
+        &mut self.data[0]
+    }
+}
+
+macro_rules! binary_heap {
+    ($(#[$attr: meta])* $name: ident, $vec: ident, $vec_mod: ident) => {
+        $(#[$attr])*
+        pub struct $name<T> {
+            data: $vec<T>,
+        }
+
+        impl<T: Ord> $name<T> {
+            /// Constructs a new, empty heap. Does not allocate.
+            #[inline]
+            pub fn new() -> $name<T> {
+                $name { data: $vec::new() }
+            }
+
+            /// Constructs a new, empty heap with at least the specified capacity.
+            #[inline]
+            pub fn with_capacity(capacity: usize) -> $name<T> {
+                $name { data: $vec::with_capacity(capacity) }
+            }
+
+            /// Pushes an item onto the heap, in O(log n).
+            pub fn push(&mut self, item: T) {
+                let old_len = self.data.len();
+                self.data.push(item);
+                sift_up(&mut self.data, 0, old_len);
+            }
+
+            /// Removes the greatest item and returns it, or `None` if the heap is empty.
+            pub fn pop(&mut self) -> Option<T> {
+                self.data.pop().map(|mut item| {
+                    if !self.data.is_empty() {
+                        mem::swap(&mut item, &mut self.data[0]);
+                        sift_down_to_bottom(&mut self.data, 0);
+                    }
+                    item
+                })
+            }
+
+            /// Returns the greatest item, or `None` if the heap is empty.
+            #[inline]
+            pub fn peek(&self) -> Option<&T> {
+                self.data.first()
+            }
+
+            /// Returns a mutable reference to the greatest item, or `None` if the heap is empty.
+            /// If the item is changed, it's moved to its place when the `PeekMut` is dropped.
+            pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T>> {
+                if self.is_empty() {
+                    None
+                } else {
+                    Some(PeekMut { data: &mut self.data, sift: false })
+                }
+            }
+
+            /// Consumes the heap and returns its items sorted in ascending order. The sort is
+            /// done in place.
+            pub fn into_sorted_vec(mut self) -> $vec<T> {
+                let mut end = self.len();
+                while end > 1 {
+                    end -= 1;
+                    self.data.swap(0, end);
+                    sift_down_range(&mut self.data, 0, end);
+                }
+                self.into_vec()
+            }
+
+            /// Keeps only the items for which `f` returns `true`, then restores the heap.
+            pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+                let len = self.data.len();
+                self.data.retain(f);
+                if self.data.len() < len {
+                    rebuild(&mut self.data);
+                }
+            }
+
+            /// Moves all the items of `other` into this heap, leaving `other` empty.
+            pub fn append(&mut self, other: &mut $name<T>) {
+                if self.len() < other.len() {
+                    mem::swap(self, other);
+                }
+                let start = self.data.len();
+                self.data.append(&mut other.data);
+                rebuild_tail(&mut self.data, start);
+            }
+        }
+
+        impl<T> $name<T> {
+            /// Returns the number of items in the heap.
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.data.len()
+            }
+
+            /// Returns `true` if the heap is empty.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.data.is_empty()
+            }
+
+            /// Returns the number of items the heap can hold without reallocating.
+            #[inline]
+            pub fn capacity(&self) -> usize {
+                self.data.capacity()
+            }
+
+            /// Reserves capacity for at least `additional` more items.
+            #[inline]
+            pub fn reserve(&mut self, additional: usize) {
+                self.data.reserve(additional);
+            }
+
+            /// Shrinks the capacity as much as possible.
+            #[inline]
+            pub fn shrink_to_fit(&mut self) {
+                self.data.shrink_to_fit();
+            }
+
+            /// Returns an iterator over the items, in arbitrary order.
+            #[inline]
+            pub fn iter(&self) -> slice::Iter<'_, T> {
+                self.data.iter()
+            }
+
+            /// Returns the items in their heap order.
+            #[inline]
+            pub fn as_slice(&self) -> &[T] {
+                self.data.as_slice()
+            }
+
+            /// Consumes the heap and returns the underlying vector, in arbitrary order.
+            #[inline]
+            pub fn into_vec(self) -> $vec<T> {
+                self.data
+            }
+
+            /// Removes all the items and returns them as an iterator, in arbitrary order.
+            #[inline]
+            pub fn drain(&mut self) -> $vec_mod::Drain<'_, T> {
+                self.data.drain(..)
+            }
+
+            /// Drops all the items.
+            #[inline]
+            pub fn clear(&mut self) {
+                self.data.clear();
+            }
+        }
+
+        impl<T: Ord> From<$vec<T>> for $name<T> {
+            /// Turns the vector into a heap in place, in O(n).
+            fn from(vec: $vec<T>) -> $name<T> {
+                let mut heap = $name { data: vec };
+                rebuild(&mut heap.data);
+                heap
+            }
+        }
+
+        impl<T> From<$name<T>> for $vec<T> {
+            #[inline]
+            fn from(heap: $name<T>) -> $vec<T> {
+                heap.data
+            }
+        }
+
+        impl<T: Ord> FromIterator<T> for $name<T> {
+            fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> $name<T> {
+                $name::from(iter.into_iter().collect::<$vec<T>>())
+            }
+        }
+
+        impl<T: Ord> Extend<T> for $name<T> {
+            fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+                let start = self.data.len();
+                self.data.extend(iter);
+                rebuild_tail(&mut self.data, start);
+            }
+        }
+
+        impl<'a, T: 'a + Ord + Copy> Extend<&'a T> for $name<T> {
+            fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
+                self.extend(iter.into_iter().cloned());
+            }
+        }
+
+        impl<T> IntoIterator for $name<T> {
+            type Item = T;
+            type IntoIter = $vec_mod::IntoIter<T>;
+
+            /// Returns the items in arbitrary order.
+            #[inline]
+            fn into_iter(self) -> $vec_mod::IntoIter<T> {
+                self.data.into_iter()
+            }
+        }
+
+        impl<'a, T> IntoIterator for &'a $name<T> {
+            type Item = &'a T;
+            type IntoIter = slice::Iter<'a, T>;
+
+            #[inline]
+            fn into_iter(self) -> slice::Iter<'a, T> {
+                self.data.iter()
+            }
+        }
+
+        impl<T: Clone> Clone for $name<T> {
+            #[inline]
+            fn clone(&self) -> $name<T> {
+                $name { data: self.data.clone() }
+            }
+        }
+
+        impl<T: Ord> Default for $name<T> {
+            #[inline]
+            fn default() -> $name<T> {
+                $name::new()
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for $name<T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_list().entries(self.data.iter()).finish()
+            }
+        }
+    }
+}
+
+binary_heap! {
+    /// A priority queue implemented with a binary max-heap, stored in a `ThinVec`.
+    /// Guaranteed to be a usize-sized pointer.
+    ///
+    /// It works like `std::collections::BinaryHeap`: `push` and `pop` are O(log n) and `peek`
+    /// is O(1). Use `std::cmp::Reverse` for a min-heap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_binary_heap::ThinBinaryHeap;
+    ///
+    /// let mut heap = ThinBinaryHeap::new();
+    /// heap.push(3);
+    /// heap.push(7);
+    /// heap.push(5);
+    /// assert_eq!(Some(&7), heap.peek());
+    /// if let Some(mut top) = heap.peek_mut() {
+    ///     *top = 1;
+    /// }
+    /// assert_eq!(Some(5), heap.pop());
+    /// assert_eq!(heap.into_sorted_vec(), [1, 3]);
+    /// ```
+    ThinBinaryHeap, ThinVec, thin_vec
+}
+
+binary_heap! {
+    /// A priority queue implemented with a binary max-heap, stored in a `V64`.
+    /// Guaranteed to be a 64 bit pointer.
+    ///
+    /// It has the same methods as `ThinBinaryHeap`. Heaps that fit in 7 bytes, such as up to
+    /// 7 `u8` or 3 `u16` priorities, are kept inline and never allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_binary_heap::V64BinaryHeap;
+    ///
+    /// let mut heap: V64BinaryHeap<u16> = vec![2, 9, 4].into_iter().collect();
+    /// assert_eq!(Some(9), heap.pop());
+    /// heap.push(6);
+    /// assert_eq!(0, heap.into_vec().bytes_on_heap());
+    /// ```
+    V64BinaryHeap, V64, thin_v64
+}
@@ -0,0 +1,350 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ThinBitSet` a set of small integers as a bitmap in a single usize-sized pointer.
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::{self, FromIterator, FusedIterator};
+
+use crate::thin_set::ThinSet;
+use crate::thin_vec::ThinVec;
+
+const BITS: usize = 64;
+
+/// A set of `usize` values stored as a bitmap of `u64` words in a `ThinVec`.
+///
+/// It's the set to use for small, dense domains such as `u8` or `u16` keys: `insert`, `remove`
+/// and `contains` are a shift and a mask, with no hashing, and `union_with`, `intersect_with` and
+/// `difference_with` work a word, or 64 values, at a time. The bitmap takes `(max + 1) / 8`
+/// bytes, so a `ThinSet` is the better choice for large, sparse values.
+///
+/// `rank` and `select` convert between a value and its position in the set's sorted order.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_bit_set::ThinBitSet;
+///
+/// let mut a: ThinBitSet = vec![1, 5, 64, 200].into_iter().collect();
+/// assert!(a.contains(64));
+/// assert_eq!(4, a.len());
+/// assert_eq!(2, a.rank(64)); // 1 and 5 are smaller
+/// assert_eq!(Some(200), a.select(3));
+///
+/// let b: ThinBitSet = vec![5, 6, 200].into_iter().collect();
+/// a.intersect_with(&b);
+/// assert_eq!(a.iter().collect::<Vec<_>>(), [5, 200]);
+/// ```
+#[derive(Clone, Default)]
+pub struct ThinBitSet {
+    // never ends with a zero word, so equal sets have equal words
+    words: ThinVec<u64>,
+}
+
+impl ThinBitSet {
+    /// Constructs a new, empty `ThinBitSet`. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinBitSet {
+        ThinBitSet { words: ThinVec::new() }
+    }
+
+    /// Constructs a new, empty `ThinBitSet` that can hold the values `0..bits` without reallocating.
+    #[inline]
+    pub fn with_capacity(bits: usize) -> ThinBitSet {
+        ThinBitSet { words: ThinVec::with_capacity(bits.div_ceil(BITS)) }
+    }
+
+    /// Adds a value to the set. Returns `true` if it wasn't there already.
+    #[inline]
+    pub fn insert(&mut self, value: usize) -> bool {
+        let (word, mask) = (value / BITS, 1u64 << (value % BITS));
+        if word >= self.words.len() {
+            self.grow(word + 1);
+        }
+        let was_set = self.words[word] & mask != 0;
+        self.words[word] |= mask;
+        !was_set
+    }
+
+    /// Removes a value from the set. Returns `true` if it was there.
+    #[inline]
+    pub fn remove(&mut self, value: usize) -> bool {
+        let (word, mask) = (value / BITS, 1u64 << (value % BITS));
+        if word >= self.words.len() || self.words[word] & mask == 0 {
+            return false;
+        }
+        self.words[word] &= !mask;
+        self.trim();
+        true
+    }
+
+    /// Returns `true` if the set contains `value`.
+    #[inline]
+    pub fn contains(&self, value: usize) -> bool {
+        let word = value / BITS;
+        word < self.words.len() && self.words[word] & (1u64 << (value % BITS)) != 0
+    }
+
+    /// Returns the number of values in the set, by counting the set bits.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns `true` if the set is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Removes all the values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Returns the smallest value, or `None` if the set is empty.
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    /// Returns the largest value, or `None` if the set is empty.
+    pub fn last(&self) -> Option<usize> {
+        let last = self.words.len().checked_sub(1)?;
+        Some(last * BITS + (BITS - 1 - self.words[last].leading_zeros() as usize))
+    }
+
+    /// Returns the number of values in the set that are smaller than `value`.
+    pub fn rank(&self, value: usize) -> usize {
+        let (word, bit) = (value / BITS, value % BITS);
+        let full: usize = self.words.iter().take(word).map(|w| w.count_ones() as usize).sum();
+        match self.words.get(word) {
+            Some(w) => full + (w & ((1u64 << bit) - 1)).count_ones() as usize,
+            None => full,
+        }
+    }
+
+    /// Returns the value at position `n` in ascending order, counting from 0, or `None` if the
+    /// set has `n` values or fewer. `select(rank(x)) == Some(x)` for every `x` in the set.
+    pub fn select(&self, mut n: usize) -> Option<usize> {
+        for (i, w) in self.words.iter().enumerate() {
+            let ones = w.count_ones() as usize;
+            if n < ones {
+                let mut w = *w;
+                for _ in 0..n {
+                    w &= w - 1; // clears the lowest set bit
+                }
+                return Some(i * BITS + w.trailing_zeros() as usize);
+            }
+            n -= ones;
+        }
+        None
+    }
+
+    /// Adds all the values of `other` to this set.
+    pub fn union_with(&mut self, other: &ThinBitSet) {
+        self.grow(other.words.len());
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a |= *b;
+        }
+    }
+
+    /// Removes the values that aren't in `other`.
+    pub fn intersect_with(&mut self, other: &ThinBitSet) {
+        self.words.truncate(other.words.len());
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a &= *b;
+        }
+        self.trim();
+    }
+
+    /// Removes the values that are in `other`.
+    pub fn difference_with(&mut self, other: &ThinBitSet) {
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a &= !*b;
+        }
+        self.trim();
+    }
+
+    /// Keeps the values that are in exactly one of the two sets.
+    pub fn symmetric_difference_with(&mut self, other: &ThinBitSet) {
+        self.grow(other.words.len());
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a ^= *b;
+        }
+        self.trim();
+    }
+
+    /// Returns `true` if every value of this set is in `other`.
+    pub fn is_subset(&self, other: &ThinBitSet) -> bool {
+        self.words.len() <= other.words.len()
+            && self.words.iter().zip(other.words.iter()).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Returns `true` if the sets have no value in common.
+    pub fn is_disjoint(&self, other: &ThinBitSet) -> bool {
+        self.words.iter().zip(other.words.iter()).all(|(a, b)| a & b == 0)
+    }
+
+    /// Returns an iterator over the values, in ascending order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { words: &self.words, index: 0, word: self.words.first().cloned().unwrap_or(0) }
+    }
+
+    /// Returns the words of the bitmap; value `v` is bit `v % 64` of word `v / 64`.
+    #[inline]
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Shrinks the capacity to fit the largest value.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.words.shrink_to_fit();
+    }
+
+    // pads the bitmap with zero words up to `words` words
+    #[inline]
+    fn grow(&mut self, words: usize) {
+        let len = self.words.len();
+        if words > len {
+            self.words.extend(iter::repeat_n(0, words - len));
+        }
+    }
+
+    #[inline]
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+}
+
+impl FromIterator<usize> for ThinBitSet {
+    fn from_iter<I: IntoIterator<Item=usize>>(iter: I) -> ThinBitSet {
+        let mut set = ThinBitSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<usize> for ThinBitSet {
+    fn extend<I: IntoIterator<Item=usize>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a> Extend<&'a usize> for ThinBitSet {
+    fn extend<I: IntoIterator<Item=&'a usize>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl PartialEq for ThinBitSet {
+    #[inline]
+    fn eq(&self, other: &ThinBitSet) -> bool {
+        self.as_words() == other.as_words()
+    }
+}
+
+impl Eq for ThinBitSet {}
+
+impl Hash for ThinBitSet {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_words().hash(state)
+    }
+}
+
+impl fmt::Debug for ThinBitSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a ThinBitSet {
+    type Item = usize;
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+macro_rules! impl_thin_set_conversions {
+    ($($t: ty),*) => {
+        $(
+        impl<S: BuildHasher> From<ThinSet<$t, S>> for ThinBitSet {
+            fn from(set: ThinSet<$t, S>) -> ThinBitSet {
+                set.iter().map(|x| *x as usize).collect()
+            }
+        }
+
+        impl<S: BuildHasher + Default> From<ThinBitSet> for ThinSet<$t, S> {
+            /// # Panics
+            ///
+            /// Panics if the bit set holds a value that doesn't fit in the element type.
+            fn from(bits: ThinBitSet) -> ThinSet<$t, S> {
+                let mut set = ThinSet::with_capacity_and_hasher(bits.len(), S::default());
+                for x in bits.iter() {
+                    assert!(x <= <$t>::MAX as usize, "{} doesn't fit in a {}", x, stringify!($t));
+                    set.insert(x as $t);
+                }
+                set
+            }
+        }
+        )*
+    }
+}
+
+impl_thin_set_conversions!(u8, u16);
+
+/// An iterator over the values of a `ThinBitSet`, in ascending order.
+///
+/// This `struct` is created by the [`iter`] method on [`ThinBitSet`].
+///
+/// [`iter`]: struct.ThinBitSet.html#method.iter
+/// [`ThinBitSet`]: struct.ThinBitSet.html
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    words: &'a [u64],
+    index: usize,
+    // the bits of words[index] that haven't been returned yet
+    word: u64,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            self.index += 1;
+            if self.index >= self.words.len() {
+                return None;
+            }
+            self.word = self.words[self.index];
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some(self.index * BITS + bit)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self.words.len().saturating_sub(self.index + 1);
+        (self.word.count_ones() as usize, Some(self.word.count_ones() as usize + rest * BITS))
+    }
+}
+
+impl<'a> FusedIterator for Iter<'a> {}
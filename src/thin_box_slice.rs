@@ -0,0 +1,424 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ThinBoxSlice` a fixed size boxed slice in a single usize-sized pointer.
+use std::{
+    alloc::{self, Layout},
+    marker, mem, ptr,
+};
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::num::NonZeroUsize;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::thin_vec::{self, DANGLE, ThinVec, ThinVecHeader, ZST_MASK};
+
+/// A boxed slice that can't grow. Guaranteed to be a usize-sized pointer.
+///
+/// `ThinBoxSlice<T>` is the frozen counterpart of `ThinVec<T>`. Its heap block only holds the
+/// length followed by the elements, so it takes `len * size_of::<T>() + size_of::<usize>()` bytes
+/// (more if `T` needs a bigger alignment), while the handle is a single word instead of the two
+/// words of a `Box<[T]>`.
+///
+/// Empty slices and slices of zero sized types don't allocate.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_box_slice::ThinBoxSlice;
+/// # use std::mem::size_of;
+/// # fn main() {
+/// let mut b: ThinBoxSlice<u64> = ThinBoxSlice::from(thinvec![3, 1, 2]);
+/// b.sort();
+/// assert_eq!(&b[..], &[1, 2, 3]);
+/// assert_eq!(3 * 8 + size_of::<usize>(), b.bytes_on_heap());
+/// let mut v = b.into_thinvec();
+/// v.push(4);
+/// assert_eq!(v, [1, 2, 3, 4]);
+/// # }
+/// ```
+pub struct ThinBoxSlice<T> {
+    u: NonZeroUsize,
+    _marker: marker::PhantomData<T>,
+}
+
+/// Drops whatever was written into a block that is still being built, if a clone panics.
+struct PartialBlock<T> {
+    block: *mut usize,
+    written: usize,
+    len: usize,
+    _marker: marker::PhantomData<T>,
+}
+
+impl<T> Drop for PartialBlock<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let array = (self.block as *mut u8).add(<ThinBoxSlice<T>>::header_bytes()) as *mut T;
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(array, self.written));
+            alloc::dealloc(self.block as *mut u8, <ThinBoxSlice<T>>::layout(self.len));
+        }
+    }
+}
+
+impl<T> ThinBoxSlice<T> {
+    /// Constructs a new, empty `ThinBoxSlice<T>`. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinBoxSlice<T> {
+        unsafe {
+            if mem::size_of::<T>() == 0 {
+                return ThinBoxSlice { u: NonZeroUsize::new_unchecked(ZST_MASK), _marker: marker::PhantomData };
+            }
+            ThinBoxSlice { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData }
+        }
+    }
+
+    /// Returns the number of elements in the slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        if mem::size_of::<T>() == 0 { return self.u.get() & (ZST_MASK - 1); }
+        if self.u.get() == DANGLE { 0 } else { unsafe { *(self.u.get() as *const usize) } }
+    }
+
+    /// Returns `true` if the slice has no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes allocated for the length and elements.
+    pub fn bytes_on_heap(&self) -> usize {
+        if self.is_heap() { <ThinBoxSlice<T>>::layout(self.len()).size() } else { 0 }
+    }
+
+    /// Returns a raw pointer to the elements.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.array()
+    }
+
+    /// Returns an unsafe mutable pointer to the elements.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.array()
+    }
+
+    /// Extracts a slice containing all the elements.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.array(), self.len()) }
+    }
+
+    /// Extracts a mutable slice containing all the elements.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.array(), self.len()) }
+    }
+
+    /// Converts the slice back into a `ThinVec<T>` with a capacity equal to its length.
+    ///
+    /// The heap block is reused; it's grown by the capacity word with `realloc`.
+    ///
+    /// ```
+    /// # use thincollections::thin_box_slice::ThinBoxSlice;
+    /// let b: ThinBoxSlice<i32> = (0..5).collect();
+    /// let v = b.into_thinvec();
+    /// assert_eq!(5, v.capacity());
+    /// assert_eq!(v, [0, 1, 2, 3, 4]);
+    /// ```
+    pub fn into_thinvec(self) -> ThinVec<T> {
+        let len = self.len();
+        if mem::size_of::<T>() == 0 {
+            mem::forget(self);
+            let mut v = ThinVec::new();
+            unsafe { v.set_len(len); }
+            return v;
+        }
+        if !self.is_heap() {
+            return ThinVec::new();
+        }
+        unsafe {
+            let mut block = self.u.get() as *mut u8;
+            mem::forget(self);
+            let from = <ThinBoxSlice<T>>::header_bytes();
+            let to = <ThinVec<T>>::header_bytes();
+            if from != to {
+                let old_layout = <ThinBoxSlice<T>>::layout(len);
+                block = alloc::realloc(block, old_layout, old_layout.size() + to - from);
+                if block.is_null() {
+                    alloc::handle_alloc_error(Layout::from_size_align_unchecked(old_layout.size() + to - from, old_layout.align()));
+                }
+                ptr::copy(block.add(from), block.add(to), len * mem::size_of::<T>());
+            }
+            *(block as *mut usize).add(1) = len;
+            ThinVec::from_raw(block as *mut ThinVecHeader)
+        }
+    }
+
+    #[inline(always)]
+    fn is_heap(&self) -> bool {
+        mem::size_of::<T>() != 0 && self.u.get() != DANGLE
+    }
+
+    #[inline(always)]
+    fn array(&self) -> *mut T {
+        if self.is_heap() {
+            unsafe { (self.u.get() as *mut u8).add(<ThinBoxSlice<T>>::header_bytes()) as *mut T }
+        } else {
+            NonNull::dangling().as_ptr()
+        }
+    }
+
+    #[inline(always)]
+    fn header_bytes() -> usize {
+        cmp::max(mem::size_of::<usize>(), mem::align_of::<T>())
+    }
+
+    #[inline(always)]
+    fn layout(len: usize) -> Layout {
+        let size = len.checked_mul(mem::size_of::<T>())
+            .and_then(|x| x.checked_add(<ThinBoxSlice<T>>::header_bytes()))
+            .expect("ThinBoxSlice capacity overflow");
+        Layout::from_size_align(size, cmp::max(mem::align_of::<usize>(), mem::align_of::<T>())).unwrap()
+    }
+
+    /// Allocates a block for `len > 0` elements of a non zero sized type.
+    /// The elements are left uninitialized.
+    unsafe fn allocate(len: usize) -> *mut usize {
+        let layout = <ThinBoxSlice<T>>::layout(len);
+        let block = alloc::alloc(layout) as *mut usize;
+        if block.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        *block = len;
+        block
+    }
+
+    /// Moves `len` elements starting at `src` into a new slice. The caller must
+    /// make sure they aren't dropped at the source.
+    unsafe fn from_raw_elements(src: *const T, len: usize) -> ThinBoxSlice<T> {
+        if mem::size_of::<T>() == 0 {
+            return ThinBoxSlice { u: NonZeroUsize::new_unchecked(len | ZST_MASK), _marker: marker::PhantomData };
+        }
+        if len == 0 {
+            return ThinBoxSlice::new();
+        }
+        let block = <ThinBoxSlice<T>>::allocate(len);
+        ptr::copy_nonoverlapping(src, (block as *mut u8).add(<ThinBoxSlice<T>>::header_bytes()) as *mut T, len);
+        ThinBoxSlice { u: NonZeroUsize::new_unchecked(block as usize), _marker: marker::PhantomData }
+    }
+}
+
+impl<T> Drop for ThinBoxSlice<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.as_mut_slice() as *mut [T]);
+            if self.is_heap() {
+                alloc::dealloc(self.u.get() as *mut u8, <ThinBoxSlice<T>>::layout(self.len()));
+            }
+        }
+    }
+}
+
+impl<T: Clone> Clone for ThinBoxSlice<T> {
+    fn clone(&self) -> ThinBoxSlice<T> {
+        ThinBoxSlice::from(self.as_slice())
+    }
+}
+
+impl<T> Deref for ThinBoxSlice<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for ThinBoxSlice<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> AsRef<[T]> for ThinBoxSlice<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> AsMut<[T]> for ThinBoxSlice<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T> Borrow<[T]> for ThinBoxSlice<T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> BorrowMut<[T]> for ThinBoxSlice<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T> Default for ThinBoxSlice<T> {
+    fn default() -> ThinBoxSlice<T> {
+        ThinBoxSlice::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ThinBoxSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash> Hash for ThinBoxSlice<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&**self, state)
+    }
+}
+
+impl<T: PartialEq> PartialEq for ThinBoxSlice<T> {
+    #[inline]
+    fn eq(&self, other: &ThinBoxSlice<T>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq> Eq for ThinBoxSlice<T> {}
+
+impl<T: PartialOrd> PartialOrd for ThinBoxSlice<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &ThinBoxSlice<T>) -> Option<Ordering> {
+        PartialOrd::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<T: Ord> Ord for ThinBoxSlice<T> {
+    #[inline]
+    fn cmp(&self, other: &ThinBoxSlice<T>) -> Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+impl<T> From<ThinVec<T>> for ThinBoxSlice<T> {
+    /// Reuses the vector's block when its capacity equals its length: nothing new is allocated,
+    /// the elements are shifted over the capacity word and the block is shrunk by that word with
+    /// a shrink-only `realloc` (none at all when the alignment pads both headers to the same
+    /// size). Otherwise the elements are moved into a new, exactly sized block.
+    fn from(mut v: ThinVec<T>) -> ThinBoxSlice<T> {
+        let len = v.len();
+        if mem::size_of::<T>() == 0 || len == 0 || v.capacity() != len {
+            unsafe {
+                v.set_len(0);
+                return ThinBoxSlice::from_raw_elements(v.as_ptr(), len);
+            }
+        }
+        unsafe {
+            let mut block = v.into_raw() as *mut u8;
+            let from = <ThinVec<T>>::header_bytes();
+            let to = <ThinBoxSlice<T>>::header_bytes();
+            if from != to {
+                let new_layout = <ThinBoxSlice<T>>::layout(len);
+                ptr::copy(block.add(from), block.add(to), len * mem::size_of::<T>());
+                let old_layout = Layout::from_size_align_unchecked(new_layout.size() + from - to, new_layout.align());
+                block = alloc::realloc(block, old_layout, new_layout.size());
+                if block.is_null() {
+                    alloc::handle_alloc_error(new_layout);
+                }
+            }
+            // the length is already in the first word
+            ThinBoxSlice { u: NonZeroUsize::new_unchecked(block as usize), _marker: marker::PhantomData }
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for ThinBoxSlice<T> {
+    fn from(mut v: Vec<T>) -> ThinBoxSlice<T> {
+        unsafe {
+            let len = v.len();
+            v.set_len(0);
+            ThinBoxSlice::from_raw_elements(v.as_ptr(), len)
+        }
+    }
+}
+
+impl<T> From<Box<[T]>> for ThinBoxSlice<T> {
+    fn from(b: Box<[T]>) -> ThinBoxSlice<T> {
+        ThinBoxSlice::from(b.into_vec())
+    }
+}
+
+impl<'a, T: Clone> From<&'a [T]> for ThinBoxSlice<T> {
+    fn from(s: &'a [T]) -> ThinBoxSlice<T> {
+        unsafe {
+            if mem::size_of::<T>() == 0 || s.is_empty() {
+                // zero sized clones have nowhere to be written, but they are owned by the new slice
+                for x in s {
+                    mem::forget(x.clone());
+                }
+                return ThinBoxSlice::from_raw_elements(NonNull::dangling().as_ptr(), s.len());
+            }
+            let block = <ThinBoxSlice<T>>::allocate(s.len());
+            let mut guard: PartialBlock<T> = PartialBlock { block, written: 0, len: s.len(), _marker: marker::PhantomData };
+            let array = (block as *mut u8).add(<ThinBoxSlice<T>>::header_bytes()) as *mut T;
+            for x in s {
+                ptr::write(array.add(guard.written), x.clone());
+                guard.written += 1;
+            }
+            mem::forget(guard);
+            ThinBoxSlice { u: NonZeroUsize::new_unchecked(block as usize), _marker: marker::PhantomData }
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ThinBoxSlice<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinBoxSlice<T> {
+        ThinBoxSlice::from(iter.into_iter().collect::<ThinVec<T>>())
+    }
+}
+
+impl<T> IntoIterator for ThinBoxSlice<T> {
+    type Item = T;
+    type IntoIter = thin_vec::IntoIter<T>;
+
+    fn into_iter(self) -> thin_vec::IntoIter<T> {
+        self.into_thinvec().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ThinBoxSlice<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ThinBoxSlice<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
@@ -8,7 +8,9 @@
 //
 
 //! Implementations of `Hasher` that work well with `ThinMap`/`ThinSet`
+use std::collections::hash_map::DefaultHasher;
 use std::hash::BuildHasher;
+use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::atomic::*;
 
@@ -23,6 +25,28 @@ fn next_seed() -> u64 {
     y
 }
 
+/// Hashes `t` with SipHash under fixed keys. Two maps with differently seeded hashers can be
+/// equal, so their `Hash` impls can't use their own hashers, and the entry hashes get summed,
+/// so they have to be well mixed: the hashers below only xor fields together.
+#[inline]
+pub(crate) fn fixed_hash<T: Hash + ?Sized>(t: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    t.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Feeds `state` a hash of a collection that doesn't depend on the order of its entries:
+/// the entry hashes are spread and then added up.
+#[inline]
+pub(crate) fn hash_unordered<H, I>(len: usize, entry_hashes: I, state: &mut H)
+    where H: Hasher,
+          I: Iterator<Item=u64>
+{
+    let sum = entry_hashes.fold(0u64, |sum, h| sum.wrapping_add(spread_two(h)));
+    state.write_usize(len);
+    state.write_u64(sum);
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct OneFieldHasherBuilder {
     seed: u64,
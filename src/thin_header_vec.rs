@@ -0,0 +1,1014 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Portions copyright The Rust Project Developers. Licensed under
+// the MIT License.
+
+//! # `ThinHeaderVec` a `ThinVec` that also stores a user defined header in its heap block.
+use std::{
+    alloc::{self, Layout},
+    marker, mem, ptr,
+};
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::iter::FusedIterator;
+use std::num::NonZeroUsize;
+use std::ops::Bound::*;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ops::RangeBounds;
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::thin_vec::DANGLE;
+
+/// A header that can be stored in front of the elements of a `ThinHeaderVec`.
+///
+/// `DEFAULT` is the header of a vector that hasn't allocated. A `ThinHeaderVec` whose header
+/// equals `DEFAULT` doesn't need any heap memory while it's empty.
+///
+/// `ThinHeader` is already implemented for the primitives. Here is a custom implementation:
+///
+/// ```
+/// use thincollections::thin_header_vec::{ThinHeader, ThinHeaderVec};
+///
+/// #[derive(Clone, PartialEq, Debug)]
+/// struct Meta {
+///     version: u32,
+///     dirty: bool,
+/// }
+///
+/// impl ThinHeader for Meta {
+///     const DEFAULT: &'static Self = &Meta { version: 0, dirty: false };
+/// }
+///
+/// let mut v: ThinHeaderVec<Meta, u16> = ThinHeaderVec::with_header(Meta { version: 3, dirty: false });
+/// v.push(7);
+/// v.header_mut().dirty = true;
+/// assert_eq!(3, v.header().version);
+/// assert!(v.header().dirty);
+/// ```
+pub trait ThinHeader: Clone + PartialEq + 'static {
+    const DEFAULT: &'static Self;
+}
+
+macro_rules! impl_thin_header {
+    ($($t: ty = $v: expr),*) => {
+        $(impl ThinHeader for $t {
+            const DEFAULT: &'static Self = &$v;
+        })*
+    }
+}
+
+impl_thin_header!(u8 = 0, u16 = 0, u32 = 0, u64 = 0, u128 = 0, usize = 0,
+    i8 = 0, i16 = 0, i32 = 0, i64 = 0, i128 = 0, isize = 0,
+    bool = false, char = '\0', () = ());
+
+/// A thin (usize) vector with a user defined header. Guaranteed to be a usize-sized smart pointer.
+///
+/// `ThinHeaderVec<H, T>` works like `ThinVec<T>`, but its heap block also holds a header of type
+/// `H`, right after the length and capacity. That keeps data such as a version number, flags or a
+/// cached hash in the same allocation as the elements, instead of a `Box<(H, Vec<T>)>`
+/// which needs two.
+///
+/// An empty vector whose header is `H::DEFAULT` doesn't allocate.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_header_vec::ThinHeaderVec;
+///
+/// let mut v: ThinHeaderVec<u64, i32> = ThinHeaderVec::new();
+/// assert_eq!(0, v.bytes_on_heap());
+/// assert_eq!(0, *v.header());
+/// v.extend(0..4);
+/// *v.header_mut() = v.iter().map(|x| *x as u64).sum();
+/// assert_eq!(6, *v.header());
+/// assert_eq!(v, [0, 1, 2, 3]);
+/// ```
+pub struct ThinHeaderVec<H: ThinHeader, T> {
+    u: NonZeroUsize,
+    _marker: marker::PhantomData<(H, T)>,
+}
+
+impl<H: ThinHeader, T> ThinHeaderVec<H, T> {
+    /// Constructs a new, empty `ThinHeaderVec<H, T>` with the default header.
+    ///
+    /// The vector will not allocate until elements are pushed onto it, or the
+    /// header is changed.
+    #[inline]
+    pub fn new() -> ThinHeaderVec<H, T> {
+        unsafe { ThinHeaderVec { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData } }
+    }
+
+    /// Constructs a new, empty `ThinHeaderVec<H, T>` with the given header.
+    ///
+    /// The vector only allocates if the header isn't `H::DEFAULT`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_header_vec::ThinHeaderVec;
+    /// let v: ThinHeaderVec<u32, u8> = ThinHeaderVec::with_header(0);
+    /// assert_eq!(0, v.bytes_on_heap());
+    /// let v: ThinHeaderVec<u32, u8> = ThinHeaderVec::with_header(42);
+    /// assert_eq!(42, *v.header());
+    /// assert!(v.bytes_on_heap() > 0);
+    /// ```
+    #[inline]
+    pub fn with_header(header: H) -> ThinHeaderVec<H, T> {
+        if header == *H::DEFAULT {
+            return ThinHeaderVec::new();
+        }
+        ThinHeaderVec::allocate(0, header)
+    }
+
+    /// Constructs a new, empty `ThinHeaderVec<H, T>` with the default header and
+    /// the specified capacity.
+    ///
+    /// The vector will be able to hold exactly `capacity` elements without
+    /// reallocating. If `capacity` is 0, the vector will not allocate.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> ThinHeaderVec<H, T> {
+        ThinHeaderVec::with_header_and_capacity(H::DEFAULT.clone(), capacity)
+    }
+
+    /// Constructs a new, empty `ThinHeaderVec<H, T>` with the given header and capacity.
+    #[inline]
+    pub fn with_header_and_capacity(header: H, capacity: usize) -> ThinHeaderVec<H, T> {
+        if capacity == 0 {
+            return ThinHeaderVec::with_header(header);
+        }
+        ThinHeaderVec::allocate(capacity, header)
+    }
+
+    /// Returns a reference to the header.
+    #[inline]
+    pub fn header(&self) -> &H {
+        if self.is_dangling() {
+            H::DEFAULT
+        } else {
+            unsafe { &*self.header_ptr() }
+        }
+    }
+
+    /// Returns a mutable reference to the header.
+    ///
+    /// This allocates if the vector hasn't allocated yet.
+    #[inline]
+    pub fn header_mut(&mut self) -> &mut H {
+        if self.is_dangling() {
+            *self = ThinHeaderVec::allocate(0, H::DEFAULT.clone());
+        }
+        unsafe { &mut *self.header_ptr() }
+    }
+
+    /// Replaces the header.
+    ///
+    /// Setting the default header on a vector that hasn't allocated does nothing.
+    pub fn set_header(&mut self, header: H) {
+        if self.is_dangling() && header == *H::DEFAULT {
+            return;
+        }
+        *self.header_mut() = header;
+    }
+
+    /// Returns the number of elements in the vector, also referred to
+    /// as its 'length'.
+    #[inline]
+    pub fn len(&self) -> usize {
+        if self.is_dangling() { 0 } else { unsafe { *(self.u.get() as *const usize) } }
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        if self.is_dangling() { 0 } else { unsafe { *(self.u.get() as *const usize).add(1) } }
+    }
+
+    /// The number of bytes allocated for the length, capacity, header and elements.
+    pub fn bytes_on_heap(&self) -> usize {
+        if self.is_dangling() { 0 } else { <ThinHeaderVec<H, T>>::layout(self.capacity()).size() }
+    }
+
+    /// Returns a raw pointer to the vector's buffer.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        if self.is_dangling() {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.elements_ptr()
+        }
+    }
+
+    /// Returns an unsafe mutable pointer to the vector's buffer.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        if self.is_dangling() {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.elements_ptr()
+        }
+    }
+
+    /// Extracts a slice containing the entire vector.
+    ///
+    /// Equivalent to `&s[..]`.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    ///
+    /// Equivalent to `&mut s[..]`.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the given `ThinHeaderVec<H, T>`. The collection may reserve more space to avoid
+    /// frequent reallocations. Does nothing if capacity is already sufficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len().checked_add(additional).expect("capacity overflow");
+        let capacity = self.capacity();
+        if required > capacity || self.is_dangling() {
+            self.realloc_heap(cmp::max(required, capacity * 2));
+        }
+    }
+
+    /// Reserves the minimum capacity for exactly `additional` more elements to
+    /// be inserted in the given `ThinHeaderVec<H, T>`. Does nothing if the capacity is
+    /// already sufficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let required = self.len().checked_add(additional).expect("capacity overflow");
+        if required > self.capacity() || self.is_dangling() {
+            self.realloc_heap(required);
+        }
+    }
+
+    /// Shrinks the capacity of the vector as much as possible.
+    ///
+    /// An empty vector with the default header frees its heap memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_header_vec::ThinHeaderVec;
+    /// let mut v: ThinHeaderVec<u8, u32> = ThinHeaderVec::with_capacity(10);
+    /// v.push(1);
+    /// v.shrink_to_fit();
+    /// assert_eq!(1, v.capacity());
+    /// v.clear();
+    /// v.shrink_to_fit();
+    /// assert_eq!(0, v.bytes_on_heap());
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        if self.is_dangling() {
+            return;
+        }
+        let len = self.len();
+        if len == 0 && *self.header() == *H::DEFAULT {
+            *self = ThinHeaderVec::new();
+        } else if mem::size_of::<T>() != 0 && self.capacity() > len {
+            self.realloc_heap(len);
+        }
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping
+    /// the rest.
+    ///
+    /// If `len` is greater than the vector's current length, this has no
+    /// effect.
+    pub fn truncate(&mut self, len: usize) {
+        let old_len = self.len();
+        if len >= old_len {
+            return;
+        }
+        unsafe {
+            let tail = ptr::slice_from_raw_parts_mut(self.as_mut_ptr().add(len), old_len - len);
+            self.set_len(len);
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    /// Clears the vector, removing all values. The header is kept.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Appends an element to the back of a collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of elements in the vector overflows a `usize`.
+    #[inline]
+    pub fn push(&mut self, val: T) {
+        let len = self.len();
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            ptr::write(self.elements_ptr().add(len), val);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes the last element from a vector and returns it, or `None` if it
+    /// is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        unsafe {
+            self.set_len(len - 1);
+            Some(ptr::read(self.elements_ptr().add(len - 1)))
+        }
+    }
+
+    /// Inserts an element at position `index` within the vector, shifting all
+    /// elements after it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, element: T) {
+        let len = self.len();
+        assert!(index <= len, "index out of bounds! len: {}, index {}", len, index);
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            let p = self.elements_ptr().add(index);
+            ptr::copy(p, p.add(1), len - index);
+            ptr::write(p, element);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes and returns the element at position `index` within the vector,
+    /// shifting all elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "index out of bounds! len: {}, index {}", len, index);
+        unsafe {
+            let p = self.elements_ptr().add(index);
+            let ret = ptr::read(p);
+            ptr::copy(p.add(1), p, len - index - 1);
+            self.set_len(len - 1);
+            ret
+        }
+    }
+
+    /// Removes an element from the vector and returns it.
+    ///
+    /// The removed element is replaced by the last element of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "index out of bounds! len: {}, index {}", len, index);
+        self.swap(index, len - 1);
+        self.pop().unwrap()
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` such that `f(&e)` returns `false`.
+    /// This method operates in place and preserves the order of the retained
+    /// elements.
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&T) -> bool
+    {
+        let len = self.len();
+        let mut del = 0;
+        {
+            let v = &mut **self;
+            for i in 0..len {
+                if !f(&v[i]) {
+                    del += 1;
+                } else if del > 0 {
+                    v.swap(i - del, i);
+                }
+            }
+        }
+        if del > 0 {
+            self.truncate(len - del);
+        }
+    }
+
+    /// Removes all but the first of consecutive elements in the vector satisfying a given equality
+    /// relation.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F) where F: FnMut(&mut T, &mut T) -> bool {
+        let len = self.len();
+        if len <= 1 {
+            return;
+        }
+        let p = self.elements_ptr();
+        let mut w: usize = 1;
+        unsafe {
+            for r in 1..len {
+                let p_r = p.add(r);
+                let p_wm1 = p.add(w - 1);
+                if !same_bucket(&mut *p_r, &mut *p_wm1) {
+                    if r != w {
+                        ptr::swap(p_r, p.add(w));
+                    }
+                    w += 1;
+                }
+            }
+        }
+        self.truncate(w);
+    }
+
+    /// Removes all but the first of consecutive elements in the vector that resolve to the same
+    /// key.
+    #[inline]
+    pub fn dedup_by_key<F, K>(&mut self, mut key: F) where F: FnMut(&mut T) -> K, K: PartialEq {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Moves all the elements of `other` into `Self`, leaving `other` empty.
+    /// The headers are left as they are.
+    pub fn append(&mut self, other: &mut Self) {
+        let count = other.len();
+        if count == 0 {
+            return;
+        }
+        self.reserve(count);
+        unsafe {
+            let len = self.len();
+            ptr::copy_nonoverlapping(other.as_ptr(), self.elements_ptr().add(len), count);
+            self.set_len(len + count);
+            other.set_len(0);
+        }
+    }
+
+    /// Splits the collection into two at the given index.
+    ///
+    /// `self` keeps elements `[0, at)`. The returned vector has elements `[at, len)`
+    /// and a clone of the header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(at <= len, "`at` out of bounds");
+        let other_len = len - at;
+        let mut other = ThinHeaderVec::with_header_and_capacity(self.header().clone(), other_len);
+        if other_len > 0 {
+            unsafe {
+                self.set_len(at);
+                ptr::copy_nonoverlapping(self.as_ptr().add(at), other.elements_ptr(), other_len);
+                other.set_len(other_len);
+            }
+        }
+        other
+    }
+
+    /// Resizes the vector in-place so that `len` is equal to `new_len`, filling new
+    /// slots with the values returned by calling `f`.
+    pub fn resize_with<F>(&mut self, new_len: usize, mut f: F) where F: FnMut() -> T {
+        let len = self.len();
+        if new_len > len {
+            self.reserve(new_len - len);
+            for _ in len..new_len {
+                self.push(f());
+            }
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Creates a draining iterator that removes the specified range in the vector
+    /// and yields the removed items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point is greater than the end point or if
+    /// the end point is greater than the length of the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_header_vec::ThinHeaderVec;
+    /// let mut v: ThinHeaderVec<u8, i32> = ThinHeaderVec::with_header(1);
+    /// v.extend(vec![1, 2, 3]);
+    /// let u: Vec<_> = v.drain(1..).collect();
+    /// assert_eq!(v, [1]);
+    /// assert_eq!(u, [2, 3]);
+    /// assert_eq!(1, *v.header());
+    /// ```
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, H, T>
+        where R: RangeBounds<usize>
+    {
+        // Same approach as ThinVec::drain: shorten the vector first, so a leaked Drain
+        // can't expose moved-from elements, then move the tail back when the Drain drops.
+        let len = self.len();
+        let start = match range.start_bound() {
+            Included(&n) => n,
+            Excluded(&n) => n + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Included(&n) => n + 1,
+            Excluded(&n) => n,
+            Unbounded => len,
+        };
+        assert!(start <= end, "drain start greater than end");
+        assert!(end <= len, "drain end out of bounds");
+
+        unsafe {
+            if len > 0 {
+                self.set_len(start);
+            }
+            let range_slice = slice::from_raw_parts(self.as_ptr().add(start), end - start);
+            Drain {
+                tail_start: end,
+                tail_len: len - end,
+                iter: range_slice.iter(),
+                vec: NonNull::from(self),
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn is_dangling(&self) -> bool {
+        self.u.get() == DANGLE
+    }
+
+    unsafe fn set_len(&mut self, len: usize) {
+        if self.is_dangling() {
+            assert_eq!(0, len, "can't set the length of an unallocated ThinHeaderVec");
+            return;
+        }
+        *(self.u.get() as *mut usize) = len;
+    }
+
+    // the header follows the length and capacity, the elements follow the header
+    #[inline(always)]
+    fn header_offset() -> usize {
+        round_up(mem::size_of::<usize>() * 2, mem::align_of::<H>())
+    }
+
+    #[inline(always)]
+    fn elements_offset() -> usize {
+        round_up(<ThinHeaderVec<H, T>>::header_offset() + mem::size_of::<H>(), mem::align_of::<T>())
+    }
+
+    #[inline(always)]
+    fn header_ptr(&self) -> *mut H {
+        unsafe { (self.u.get() as *mut u8).add(<ThinHeaderVec<H, T>>::header_offset()) as *mut H }
+    }
+
+    #[inline(always)]
+    fn elements_ptr(&self) -> *mut T {
+        unsafe { (self.u.get() as *mut u8).add(<ThinHeaderVec<H, T>>::elements_offset()) as *mut T }
+    }
+
+    fn layout(capacity: usize) -> Layout {
+        let bytes = mem::size_of::<T>().checked_mul(capacity)
+            .and_then(|b| b.checked_add(<ThinHeaderVec<H, T>>::elements_offset()))
+            .expect("capacity overflow");
+        let align = cmp::max(mem::align_of::<usize>(), cmp::max(mem::align_of::<H>(), mem::align_of::<T>()));
+        Layout::from_size_align(bytes, align).unwrap()
+    }
+
+    #[cold]
+    fn allocate(capacity: usize, header: H) -> ThinHeaderVec<H, T> {
+        // zero sized elements never need more room
+        let capacity = if mem::size_of::<T>() == 0 { <usize>::MAX } else { capacity };
+        unsafe {
+            let layout = <ThinHeaderVec<H, T>>::layout(capacity);
+            let buffer = alloc::alloc(layout);
+            if buffer.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            ptr::write(buffer as *mut usize, 0); // current length
+            ptr::write((buffer as *mut usize).add(1), capacity);
+            ptr::write(buffer.add(<ThinHeaderVec<H, T>>::header_offset()) as *mut H, header);
+            ThinHeaderVec { u: NonZeroUsize::new_unchecked(buffer as usize), _marker: marker::PhantomData }
+        }
+    }
+
+    // moves the length, header and elements to a block with room for new_capacity elements
+    fn realloc_heap(&mut self, new_capacity: usize) {
+        if self.is_dangling() {
+            *self = ThinHeaderVec::allocate(new_capacity, H::DEFAULT.clone());
+            return;
+        }
+        if mem::size_of::<T>() == 0 {
+            return;
+        }
+        unsafe {
+            let len = self.len();
+            let old_layout = <ThinHeaderVec<H, T>>::layout(self.capacity());
+            let new_layout = <ThinHeaderVec<H, T>>::layout(new_capacity);
+            //realloc seems to bench slower...
+            let buffer = alloc::alloc(new_layout);
+            if buffer.is_null() {
+                alloc::handle_alloc_error(new_layout);
+            }
+            let old = self.u.get() as *mut u8;
+            ptr::copy_nonoverlapping(old, buffer, <ThinHeaderVec<H, T>>::elements_offset() + len * mem::size_of::<T>());
+            ptr::write((buffer as *mut usize).add(1), new_capacity);
+            self.u = NonZeroUsize::new_unchecked(buffer as usize);
+            alloc::dealloc(old, old_layout);
+        }
+    }
+}
+
+#[inline(always)]
+fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}
+
+impl<H: ThinHeader, T: Clone> ThinHeaderVec<H, T> {
+    /// Clones and appends all elements in a slice to the vector.
+    pub fn extend_from_slice(&mut self, slice: &[T]) {
+        self.reserve(slice.len());
+        for t in slice {
+            self.push(t.clone());
+        }
+    }
+
+    /// Resizes the vector in-place so that `len` is equal to `new_len`, filling new
+    /// slots with clones of `value`.
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        let len = self.len();
+        if new_len > len {
+            self.reserve(new_len - len);
+            for _ in len + 1..new_len {
+                self.push(value.clone());
+            }
+            self.push(value);
+        } else {
+            self.truncate(new_len);
+        }
+    }
+}
+
+impl<H: ThinHeader, T: PartialEq> ThinHeaderVec<H, T> {
+    /// Removes consecutive repeated elements in the vector.
+    ///
+    /// If the vector is sorted, this removes all duplicates.
+    #[inline]
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+}
+
+impl<H: ThinHeader, T> Drop for ThinHeaderVec<H, T> {
+    fn drop(&mut self) {
+        if self.is_dangling() {
+            return;
+        }
+        unsafe {
+            ptr::drop_in_place(&mut **self as *mut [T]);
+            ptr::drop_in_place(self.header_ptr());
+            alloc::dealloc(self.u.get() as *mut u8, <ThinHeaderVec<H, T>>::layout(self.capacity()));
+        }
+    }
+}
+
+impl<H: ThinHeader, T> Deref for ThinHeaderVec<H, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+}
+
+impl<H: ThinHeader, T> DerefMut for ThinHeaderVec<H, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe {
+            let len = self.len();
+            slice::from_raw_parts_mut(self.as_mut_ptr(), len)
+        }
+    }
+}
+
+impl<H: ThinHeader, T> Default for ThinHeaderVec<H, T> {
+    fn default() -> ThinHeaderVec<H, T> {
+        ThinHeaderVec::new()
+    }
+}
+
+impl<H: ThinHeader, T: Clone> Clone for ThinHeaderVec<H, T> {
+    fn clone(&self) -> ThinHeaderVec<H, T> {
+        let mut v = ThinHeaderVec::with_header_and_capacity(self.header().clone(), self.len());
+        v.extend_from_slice(self);
+        v
+    }
+}
+
+impl<H: ThinHeader + fmt::Debug, T: fmt::Debug> fmt::Debug for ThinHeaderVec<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThinHeaderVec")
+            .field("header", self.header())
+            .field("elements", &&**self)
+            .finish()
+    }
+}
+
+impl<H: ThinHeader + Hash, T: Hash> Hash for ThinHeaderVec<H, T> {
+    #[inline]
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.header().hash(state);
+        Hash::hash(&**self, state)
+    }
+}
+
+/// Two `ThinHeaderVec`s are equal if both their headers and their elements are equal.
+impl<H: ThinHeader, A: PartialEq<B>, B> PartialEq<ThinHeaderVec<H, B>> for ThinHeaderVec<H, A> {
+    #[inline]
+    fn eq(&self, other: &ThinHeaderVec<H, B>) -> bool {
+        self.header() == other.header() && self[..] == other[..]
+    }
+}
+
+impl<H: ThinHeader + Eq, T: Eq> Eq for ThinHeaderVec<H, T> {}
+
+// comparisons with plain sequences only look at the elements
+macro_rules! __impl_slice_eq1 {
+    ($Rhs: ty) => {
+        impl<'b, H: ThinHeader, A: PartialEq<B>, B> PartialEq<$Rhs> for ThinHeaderVec<H, A> {
+            #[inline]
+            fn eq(&self, other: &$Rhs) -> bool { self[..] == other[..] }
+        }
+    }
+}
+
+__impl_slice_eq1! { Vec<B> }
+__impl_slice_eq1! { &'b [B] }
+__impl_slice_eq1! { &'b mut [B] }
+
+impl<H: ThinHeader, A: PartialEq<B>, B, const M: usize> PartialEq<[B; M]> for ThinHeaderVec<H, A> {
+    #[inline]
+    fn eq(&self, other: &[B; M]) -> bool { self[..] == other[..] }
+}
+
+impl<H: ThinHeader, T> AsRef<[T]> for ThinHeaderVec<H, T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<H: ThinHeader, T> AsMut<[T]> for ThinHeaderVec<H, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<H: ThinHeader, T> Extend<T> for ThinHeaderVec<H, T> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        if lower > 0 {
+            self.reserve(lower);
+        }
+        for t in iter {
+            self.push(t);
+        }
+    }
+}
+
+impl<'a, H: ThinHeader, T: 'a + Copy> Extend<&'a T> for ThinHeaderVec<H, T> {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned())
+    }
+}
+
+impl<H: ThinHeader, T> FromIterator<T> for ThinHeaderVec<H, T> {
+    /// Collects into a vector with the default header.
+    #[inline]
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinHeaderVec<H, T> {
+        let mut vec = ThinHeaderVec::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+/// An iterator that moves out of a `ThinHeaderVec`. The header is dropped with the iterator.
+///
+/// This `struct` is created by the `into_iter` method on [`ThinHeaderVec`][`ThinHeaderVec`].
+///
+/// [`ThinHeaderVec`]: struct.ThinHeaderVec.html
+pub struct IntoIter<H: ThinHeader, T> {
+    // length is kept at 0, so dropping it only drops the header and frees the block
+    vec: ThinHeaderVec<H, T>,
+    start: usize,
+    end: usize,
+}
+
+impl<H: ThinHeader, T> IntoIterator for ThinHeaderVec<H, T> {
+    type Item = T;
+    type IntoIter = IntoIter<H, T>;
+
+    #[inline]
+    fn into_iter(mut self) -> IntoIter<H, T> {
+        let len = self.len();
+        unsafe { self.set_len(0); }
+        IntoIter { vec: self, start: 0, end: len }
+    }
+}
+
+impl<'a, H: ThinHeader, T> IntoIterator for &'a ThinHeaderVec<H, T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, H: ThinHeader, T> IntoIterator for &'a mut ThinHeaderVec<H, T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<H: ThinHeader, T> IntoIter<H, T> {
+    /// Returns the header of the vector being iterated.
+    pub fn header(&self) -> &H {
+        self.vec.header()
+    }
+
+    /// Returns the remaining items of this iterator as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.vec.as_ptr().add(self.start), self.end - self.start) }
+    }
+
+    /// Returns the remaining items of this iterator as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.vec.as_mut_ptr().add(self.start), self.end - self.start) }
+    }
+}
+
+impl<H: ThinHeader, T: fmt::Debug> fmt::Debug for IntoIter<H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IntoIter")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl<H: ThinHeader, T> Iterator for IntoIter<H, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            let old = self.start;
+            self.start += 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(old))) }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.end - self.start;
+        (exact, Some(exact))
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.len()
+    }
+}
+
+impl<H: ThinHeader, T> DoubleEndedIterator for IntoIter<H, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(self.end))) }
+        }
+    }
+}
+
+impl<H: ThinHeader, T> ExactSizeIterator for IntoIter<H, T> {}
+
+impl<H: ThinHeader, T> FusedIterator for IntoIter<H, T> {}
+
+impl<H: ThinHeader, T> Drop for IntoIter<H, T> {
+    fn drop(&mut self) {
+        // destroy the remaining elements
+        for _x in self.by_ref() {}
+    }
+}
+
+/// A draining iterator for `ThinHeaderVec<H, T>`.
+///
+/// This `struct` is created by the [`drain`] method on [`ThinHeaderVec`].
+///
+/// [`drain`]: struct.ThinHeaderVec.html#method.drain
+/// [`ThinHeaderVec`]: struct.ThinHeaderVec.html
+pub struct Drain<'a, H: ThinHeader, T: 'a> {
+    /// Index of tail to preserve
+    tail_start: usize,
+    /// Length of tail
+    tail_len: usize,
+    /// Current remaining range to remove
+    iter: slice::Iter<'a, T>,
+    vec: NonNull<ThinHeaderVec<H, T>>,
+}
+
+impl<'a, H: ThinHeader, T: 'a + fmt::Debug> fmt::Debug for Drain<'a, H, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Drain")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+unsafe impl<'a, H: ThinHeader + Sync, T: Sync> Sync for Drain<'a, H, T> {}
+
+unsafe impl<'a, H: ThinHeader + Send, T: Send> Send for Drain<'a, H, T> {}
+
+impl<'a, H: ThinHeader, T> Iterator for Drain<'a, H, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next().map(|elt| unsafe { ptr::read(elt as *const _) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, H: ThinHeader, T> DoubleEndedIterator for Drain<'a, H, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back().map(|elt| unsafe { ptr::read(elt as *const _) })
+    }
+}
+
+impl<'a, H: ThinHeader, T> Drop for Drain<'a, H, T> {
+    fn drop(&mut self) {
+        // exhaust self first
+        self.for_each(drop);
+
+        if self.tail_len > 0 {
+            unsafe {
+                let source_vec = self.vec.as_mut();
+                // memmove back untouched tail, update to new length
+                let start = source_vec.len();
+                let tail = self.tail_start;
+                if tail != start {
+                    let src = source_vec.as_ptr().add(tail);
+                    let dst = source_vec.as_mut_ptr().add(start);
+                    ptr::copy(src, dst, self.tail_len);
+                }
+                source_vec.set_len(start + self.tail_len);
+            }
+        }
+    }
+}
+
+impl<'a, H: ThinHeader, T> ExactSizeIterator for Drain<'a, H, T> {}
+
+impl<'a, H: ThinHeader, T> FusedIterator for Drain<'a, H, T> {}
@@ -0,0 +1,358 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ThinJagged` rows of different lengths in two allocations.
+//! A compressed sparse row (CSR) layout for read-mostly jagged arrays such as adjacency lists.
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::ops::{Index, IndexMut};
+
+use crate::thin_v64::V64;
+use crate::thin_vec::ThinVec;
+
+/// A jagged 2D array: a list of rows, each a slice of any length.
+///
+/// `ThinVec<ThinVec<T>>` allocates once per row. `ThinJagged<T>` keeps all the values back to
+/// back in one `ThinVec<T>`, and the end offset of each row in a `ThinVec<u32>`, so it uses two
+/// allocations in total and the rows are contiguous in memory. The offsets are `u32`s, which
+/// limits the total number of values to `u32::MAX`.
+///
+/// Rows can only be added at the end, and only the last row can grow. Build it once with
+/// `push_row`, or freeze an existing `ThinVec<ThinVec<T>>` or `Vec<V64<T>>` with `From`.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_jagged::ThinJagged;
+/// # fn main() {
+/// let mut adjacency = ThinJagged::new();
+/// adjacency.push_row(vec![1, 2]);
+/// adjacency.push_row(vec![]);
+/// adjacency.push_row(vec![0]);
+/// adjacency.extend_row_last(vec![1]);
+/// assert_eq!(3, adjacency.len());
+/// assert_eq!(adjacency[0], [1, 2]);
+/// assert!(adjacency[1].is_empty());
+/// assert_eq!(adjacency.row(2), [0, 1]);
+///
+/// let frozen = ThinJagged::from(thinvec![thinvec!['a'], thinvec!['b', 'c']]);
+/// assert_eq!(frozen.rows().map(|r| r.len()).collect::<Vec<_>>(), [1, 2]);
+/// # }
+/// ```
+pub struct ThinJagged<T> {
+    offsets: ThinVec<u32>,
+    values: ThinVec<T>,
+}
+
+impl<T> ThinJagged<T> {
+    /// Constructs a new `ThinJagged<T>` with no rows. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinJagged<T> {
+        ThinJagged { offsets: ThinVec::new(), values: ThinVec::new() }
+    }
+
+    /// Constructs a new `ThinJagged<T>` with room for `rows` rows holding `values` values in total.
+    #[inline]
+    pub fn with_capacity(rows: usize, values: usize) -> ThinJagged<T> {
+        ThinJagged { offsets: ThinVec::with_capacity(rows), values: ThinVec::with_capacity(values) }
+    }
+
+    /// Returns the number of rows.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns `true` if there are no rows.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Returns the row at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn row(&self, index: usize) -> &[T] {
+        let (start, end) = self.bounds(index);
+        &self.values[start..end]
+    }
+
+    /// Returns the row at `index` mutably. The row's length can't change.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn row_mut(&mut self, index: usize) -> &mut [T] {
+        let (start, end) = self.bounds(index);
+        &mut self.values[start..end]
+    }
+
+    /// Returns the row at `index`, or `None` if it's out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[T]> {
+        if index < self.len() { Some(self.row(index)) } else { None }
+    }
+
+    /// Returns all the values, row after row.
+    #[inline]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns all the values mutably, row after row.
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// Appends a row with the items of `row`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total number of values would exceed `u32::MAX`.
+    pub fn push_row<I: IntoIterator<Item=T>>(&mut self, row: I) {
+        let end = self.extend_values(row);
+        self.offsets.push(end);
+    }
+
+    /// Appends the items of `items` to the last row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no rows, or if the total number of values would exceed `u32::MAX`.
+    pub fn extend_row_last<I: IntoIterator<Item=T>>(&mut self, items: I) {
+        assert!(!self.offsets.is_empty(), "extend_row_last called without any rows");
+        let end = self.extend_values(items);
+        let last = self.offsets.len() - 1;
+        self.offsets[last] = end;
+    }
+
+    /// Removes the last row and returns its values, or `None` if there are no rows.
+    pub fn pop_row(&mut self) -> Option<ThinVec<T>> {
+        self.offsets.pop()?;
+        let start = self.offsets.last().map_or(0, |x| *x as usize);
+        Some(self.values.split_off(start))
+    }
+
+    /// Returns an iterator over the rows.
+    #[inline]
+    pub fn rows(&self) -> Rows<'_, T> {
+        Rows { jagged: self, front: 0, back: self.len() }
+    }
+
+    /// Removes all the rows.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.offsets.clear();
+        self.values.clear();
+    }
+
+    /// Shrinks both allocations to fit.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.offsets.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+
+    // extends the values and returns the new end offset; nothing changes if that overflows u32
+    fn extend_values<I: IntoIterator<Item=T>>(&mut self, items: I) -> u32 {
+        let len = self.values.len();
+        Extend::<T>::extend(&mut self.values, items);
+        if self.values.len() > u32::MAX as usize {
+            self.values.truncate(len);
+            panic!("ThinJagged can't hold more than u32::MAX values");
+        }
+        self.values.len() as u32
+    }
+
+    #[inline]
+    fn bounds(&self, index: usize) -> (usize, usize) {
+        let end = self.offsets[index] as usize;
+        let start = if index == 0 { 0 } else { self.offsets[index - 1] as usize };
+        (start, end)
+    }
+}
+
+impl<T> From<ThinVec<ThinVec<T>>> for ThinJagged<T> {
+    /// Moves the values of each row into the shared values vector.
+    fn from(rows: ThinVec<ThinVec<T>>) -> ThinJagged<T> {
+        let total = rows.iter().map(|r| r.len()).sum();
+        let mut jagged = ThinJagged::with_capacity(rows.len(), total);
+        for row in rows {
+            jagged.push_row(row);
+        }
+        jagged
+    }
+}
+
+impl<T> From<Vec<V64<T>>> for ThinJagged<T> {
+    /// Moves the values of each row into the shared values vector.
+    fn from(rows: Vec<V64<T>>) -> ThinJagged<T> {
+        let total = rows.iter().map(|r| r.len()).sum();
+        let mut jagged = ThinJagged::with_capacity(rows.len(), total);
+        for row in rows {
+            jagged.push_row(row);
+        }
+        jagged
+    }
+}
+
+impl<T> From<ThinJagged<T>> for ThinVec<ThinVec<T>> {
+    /// Splits the rows back into separate vectors.
+    fn from(mut jagged: ThinJagged<T>) -> ThinVec<ThinVec<T>> {
+        let mut rows = ThinVec::with_capacity(jagged.len());
+        while let Some(row) = jagged.pop_row() {
+            rows.push(row);
+        }
+        rows.reverse();
+        rows
+    }
+}
+
+impl<T, R: IntoIterator<Item=T>> FromIterator<R> for ThinJagged<T> {
+    fn from_iter<I: IntoIterator<Item=R>>(iter: I) -> ThinJagged<T> {
+        let mut jagged = ThinJagged::new();
+        jagged.extend(iter);
+        jagged
+    }
+}
+
+impl<T, R: IntoIterator<Item=T>> Extend<R> for ThinJagged<T> {
+    /// Appends each item of `iter` as a row.
+    fn extend<I: IntoIterator<Item=R>>(&mut self, iter: I) {
+        for row in iter {
+            self.push_row(row);
+        }
+    }
+}
+
+impl<T> Index<usize> for ThinJagged<T> {
+    type Output = [T];
+
+    #[inline]
+    fn index(&self, index: usize) -> &[T] {
+        self.row(index)
+    }
+}
+
+impl<T> IndexMut<usize> for ThinJagged<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut [T] {
+        self.row_mut(index)
+    }
+}
+
+impl<T> Default for ThinJagged<T> {
+    #[inline]
+    fn default() -> ThinJagged<T> {
+        ThinJagged::new()
+    }
+}
+
+impl<T: Clone> Clone for ThinJagged<T> {
+    fn clone(&self) -> ThinJagged<T> {
+        ThinJagged { offsets: self.offsets.clone(), values: self.values.clone() }
+    }
+}
+
+impl<T: PartialEq> PartialEq for ThinJagged<T> {
+    #[inline]
+    fn eq(&self, other: &ThinJagged<T>) -> bool {
+        self.offsets == other.offsets && self.values == other.values
+    }
+}
+
+impl<T: Eq> Eq for ThinJagged<T> {}
+
+impl<T: Hash> Hash for ThinJagged<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offsets.hash(state);
+        self.values.hash(state);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ThinJagged<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.rows()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ThinJagged<T> {
+    type Item = &'a [T];
+    type IntoIter = Rows<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Rows<'a, T> {
+        self.rows()
+    }
+}
+
+/// An iterator over the rows of a `ThinJagged`.
+///
+/// This `struct` is created by the [`rows`] method on [`ThinJagged`].
+///
+/// [`rows`]: struct.ThinJagged.html#method.rows
+/// [`ThinJagged`]: struct.ThinJagged.html
+pub struct Rows<'a, T: 'a> {
+    jagged: &'a ThinJagged<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Clone for Rows<'a, T> {
+    fn clone(&self) -> Rows<'a, T> {
+        Rows { jagged: self.jagged, front: self.front, back: self.back }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Rows<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> Iterator for Rows<'a, T> {
+    type Item = &'a [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a [T]> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.jagged.row(self.front - 1))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Rows<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a [T]> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.jagged.row(self.back))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Rows<'a, T> {}
+
+impl<'a, T> FusedIterator for Rows<'a, T> {}
@@ -27,6 +27,7 @@ use std::{
     marker, mem, ptr,
 };
 use std::cmp;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Debug};
 use std::hash::BuildHasher;
 use std::hash::Hash;
@@ -641,67 +642,85 @@ impl<K: ThinSentinel + Eq + Hash, V, H: BuildHasher> ThinMap<K, V, H> {
     /// assert_eq!(letters.get(&'y'), None);
     /// ```
     pub fn entry(&mut self, key: K) -> Entry<K, V> {
+        let (ptr, full) = self.entry_slot(&key);
+        if full {
+            return Entry::Occupied(self.occupied_at(ptr));
+        }
+        Entry::Vacant(self.vacant_at(ptr, key))
+    }
+
+    // Finds the bucket for key: the one holding it, or the one it would be inserted in.
+    // If the key is missing and one more would go over the load factor, the table grows
+    // before the bucket is picked, so a vacant entry can always be filled.
+    fn entry_slot(&mut self, key: &K) -> (*mut (K, V), bool) {
         if self.table_size == 0 {
             self.allocate_table();
         }
         unsafe {
-            if K::SENTINEL_ZERO == key {
+            if K::SENTINEL_ZERO == *key {
                 let ptr = self.table.offset(-2);
-                if (*ptr).0 == K::SENTINEL_ZERO {
-                    return Entry::Occupied(OccupiedEntry {
-                        ptr: ptr.as_mut().unwrap(),
-                        occupied: &mut self.occupied,
-                        sentinels: &mut self.sentinels,
-                        occupied_sentinels: &mut self.occupied_sentinels,
-                    });
-                }
-                return Entry::Vacant(VacantEntry {
-                    ptr: ptr.as_mut().unwrap(),
-                    occupied: &mut self.occupied,
-                    sentinels: &mut self.sentinels,
-                    occupied_sentinels: &mut self.occupied_sentinels,
-                    key,
-                });
+                return (ptr, (*ptr).0 == K::SENTINEL_ZERO);
             }
-            if K::SENTINEL_ONE == key {
+            if K::SENTINEL_ONE == *key {
                 let ptr = self.table.offset(-1);
-                if (*ptr).0 == K::SENTINEL_ONE {
-                    return Entry::Occupied(OccupiedEntry {
-                        ptr: ptr.as_mut().unwrap(),
-                        occupied: &mut self.occupied,
-                        sentinels: &mut self.sentinels,
-                        occupied_sentinels: &mut self.occupied_sentinels,
-                    });
-                }
-                return Entry::Vacant(VacantEntry {
-                    ptr: ptr.as_mut().unwrap(),
-                    occupied: &mut self.occupied,
-                    sentinels: &mut self.sentinels,
-                    occupied_sentinels: &mut self.occupied_sentinels,
-                    key,
-                });
+                return (ptr, (*ptr).0 == K::SENTINEL_ONE);
             }
         }
-        let (entry, bucket_state) = self.probe(&key);
-        unsafe {
-            if bucket_state.is_full() {
-                return Entry::Occupied(OccupiedEntry {
-                    ptr: entry.as_mut().unwrap(),
-                    occupied: &mut self.occupied,
-                    sentinels: &mut self.sentinels,
-                    occupied_sentinels: &mut self.occupied_sentinels,
-                });
-            }
-            return Entry::Vacant(VacantEntry {
-                ptr: entry.as_mut().unwrap(),
-                occupied: &mut self.occupied,
-                sentinels: &mut self.sentinels,
-                occupied_sentinels: &mut self.occupied_sentinels,
-                key,
-            });
+        let (entry, bucket_state) = self.probe(key);
+        if bucket_state.is_full() {
+            return (entry, true);
+        }
+        if self.occupied + self.sentinels >= self.max_occupied() {
+            self.rehash();
+            return (self.probe(key).0, false);
+        }
+        (entry, false)
+    }
+
+    #[inline]
+    fn occupied_at(&mut self, ptr: *mut (K, V)) -> OccupiedEntry<'_, K, V> {
+        OccupiedEntry {
+            ptr: unsafe { ptr.as_mut().unwrap() },
+            occupied: &mut self.occupied,
+            sentinels: &mut self.sentinels,
+            occupied_sentinels: &mut self.occupied_sentinels,
         }
     }
 
+    #[inline]
+    fn vacant_at(&mut self, ptr: *mut (K, V), key: K) -> VacantEntry<'_, K, V> {
+        VacantEntry {
+            ptr: unsafe { ptr.as_mut().unwrap() },
+            occupied: &mut self.occupied,
+            sentinels: &mut self.sentinels,
+            occupied_sentinels: &mut self.occupied_sentinels,
+            key,
+        }
+    }
+
+    // Puts `key` in place of the equal key and returns that one, or inserts `key` with `value`.
+    pub(crate) fn replace_key(&mut self, key: K, value: V) -> Option<K> {
+        let (ptr, full) = self.entry_slot(&key);
+        if full {
+            return Some(unsafe { mem::replace(&mut (*ptr).0, key) });
+        }
+        self.vacant_at(ptr, key).insert(value);
+        None
+    }
+
+    // Returns the key equal to `key`, inserting the key and value made by `f` if there isn't one.
+    pub(crate) fn get_key_or_insert_with<F>(&mut self, key: &K, f: F) -> &K
+        where F: FnOnce(&K) -> (K, V)
+    {
+        let (ptr, full) = self.entry_slot(key);
+        if full {
+            return unsafe { &(*ptr).0 };
+        }
+        let (new_key, value) = f(key);
+        assert!(new_key == *key, "the new key must be equal to the one looked up");
+        self.vacant_at(ptr, new_key).insert_key(value)
+    }
+
     /// Clears the map, returning all key-value pairs as an iterator. Keeps the
     /// allocated memory for reuse.
     ///
@@ -1423,6 +1442,7 @@ impl<K: ThinSentinel + Eq + Hash, V, H: BuildHasher> ThinMap<K, V, H> {
             let (entry, state) = self.probe(key);
             if state.is_full() {
                 unsafe {
+                    ptr::drop_in_place(entry as *mut K);
                     overwrite_k(entry, K::SENTINEL_ONE);
                     r = Some(ptr::read(&(*entry).1 as *const V));
                 }
@@ -1763,12 +1783,28 @@ impl<'a, K, V> OccupiedEntry<'a, K, V>
     pub fn key(&self) -> &K {
         &self.ptr.0
     }
+
+    pub(crate) fn into_key(self) -> &'a K {
+        &self.ptr.0
+    }
 }
 
 impl<'a, K, V> VacantEntry<'a, K, V>
     where K: Eq + Hash + ThinSentinel,
 {
     pub fn insert(self, value: V) -> &'a mut V {
+        &mut self.insert_pair(value).1
+    }
+
+    pub(crate) fn insert_key(self, value: V) -> &'a K {
+        &self.insert_pair(value).0
+    }
+
+    pub(crate) fn into_key(self) -> K {
+        self.key
+    }
+
+    fn insert_pair(self, value: V) -> &'a mut (K, V) {
         if self.is_sentry() {
             *self.occupied_sentinels += 1;
         } else {
@@ -1780,7 +1816,7 @@ impl<'a, K, V> VacantEntry<'a, K, V>
         unsafe {
             ptr::write(self.ptr, (self.key, value));
         }
-        &mut self.ptr.1
+        self.ptr
     }
 
     #[inline]
@@ -2044,6 +2080,49 @@ impl<K, V, S> Extend<(K, V)> for ThinMap<K, V, S>
     }
 }
 
+impl<K, V, S, S2> From<HashMap<K, V, S2>> for ThinMap<K, V, S>
+    where K: Eq + Hash + ThinSentinel,
+          S: BuildHasher + Default
+{
+    /// Moves the entries into a `ThinMap` sized for them up front.
+    fn from(map: HashMap<K, V, S2>) -> ThinMap<K, V, S> {
+        let mut thin = ThinMap::with_capacity_and_hasher(map.len(), S::default());
+        for (k, v) in map {
+            thin.insert(k, v);
+        }
+        thin
+    }
+}
+
+impl<K, V, S, S2> From<ThinMap<K, V, S>> for HashMap<K, V, S2>
+    where K: Eq + Hash + ThinSentinel,
+          S: BuildHasher,
+          S2: BuildHasher + Default
+{
+    /// Moves the entries into a `HashMap` sized for them up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use thincollections::thin_map::ThinMap;
+    ///
+    /// let mut thin = ThinMap::new();
+    /// thin.insert(0, "zero");
+    /// thin.insert(7, "seven");
+    /// let std: HashMap<_, _> = thin.into();
+    /// assert_eq!(std[&0], "zero");
+    ///
+    /// let back = ThinMap::<_, _>::from(std);
+    /// assert_eq!(back.get(&7), Some(&"seven"));
+    /// ```
+    fn from(thin: ThinMap<K, V, S>) -> HashMap<K, V, S2> {
+        let mut map = HashMap::with_capacity_and_hasher(thin.len(), S2::default());
+        map.extend(thin);
+        map
+    }
+}
+
 impl<K, V, S> FromIterator<(K, V)> for ThinMap<K, V, S>
     where K: Eq + Hash + ThinSentinel,
           S: BuildHasher + Default
@@ -2090,12 +2169,14 @@ impl<K, V, S> Clone for ThinMap<K, V, S>
     }
 }
 
-impl<K, V, S> PartialEq for ThinMap<K, V, S>
+impl<K, V, S, S2> PartialEq<ThinMap<K, V, S2>> for ThinMap<K, V, S>
     where K: Eq + Hash + ThinSentinel,
           V: PartialEq,
-          S: BuildHasher
+          S: BuildHasher,
+          S2: BuildHasher
 {
-    fn eq(&self, other: &ThinMap<K, V, S>) -> bool {
+    /// Maps with different hashers are equal if they have the same entries.
+    fn eq(&self, other: &ThinMap<K, V, S2>) -> bool {
         if self.len() != other.len() {
             return false;
         }
@@ -2104,6 +2185,57 @@ impl<K, V, S> PartialEq for ThinMap<K, V, S>
     }
 }
 
+impl<K, V, S, S2> PartialEq<HashMap<K, V, S2>> for ThinMap<K, V, S>
+    where K: Eq + Hash + ThinSentinel,
+          V: PartialEq,
+          S: BuildHasher,
+          S2: BuildHasher
+{
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use thincollections::thin_map::ThinMap;
+    ///
+    /// let thin: ThinMap<u32, char> = vec![(1, 'a'), (2, 'b')].into_iter().collect();
+    /// let std: HashMap<u32, char> = vec![(2, 'b'), (1, 'a')].into_iter().collect();
+    /// assert_eq!(thin, std);
+    /// ```
+    fn eq(&self, other: &HashMap<K, V, S2>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.iter().all(|(key, value)| other.get(key).is_some_and(|v| *value == *v))
+    }
+}
+
+impl<K, V, S> PartialEq<BTreeMap<K, V>> for ThinMap<K, V, S>
+    where K: Eq + Hash + Ord + ThinSentinel,
+          V: PartialEq,
+          S: BuildHasher
+{
+    fn eq(&self, other: &BTreeMap<K, V>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        self.iter().all(|(key, value)| other.get(key).is_some_and(|v| *value == *v))
+    }
+}
+
+impl<K, V, S> Hash for ThinMap<K, V, S>
+    where K: Eq + Hash + ThinSentinel,
+          V: Hash,
+          S: BuildHasher
+{
+    /// Equal maps hash the same whatever the order of their entries, or their hashers' seeds.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let entries = self.iter().map(|entry| fixed_hash(&entry));
+        hash_unordered(self.len(), entries, state);
+    }
+}
+
 impl<K, V, S> Eq for ThinMap<K, V, S>
     where K: Eq + Hash + ThinSentinel,
           V: Eq,
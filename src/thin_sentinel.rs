@@ -139,6 +139,21 @@ impl<T: ThinSentinel, U: ThinSentinel, V: ThinSentinel> ThinSentinel for (T, U,
 //    const ONE:  Self = (T::ZERO,U::ZERO,V::ZERO,W::ZERO,X::ZERO,Y::ZERO,Z::ONE);
 //}
 
+/// A wrapper that gives any type the two sentinels, as two extra variants, so it can be a
+/// `ThinMap` key or a `ThinSet` element. It's the way to nest collections that can't have
+/// sentinels of their own, such as a `ThinSet` of `ThinSet`s.
+///
+/// ```
+/// use thincollections::thin_sentinel::ThinSentinelEnum;
+/// use thincollections::thin_set::ThinSet;
+///
+/// let mut groups: ThinSet<ThinSentinelEnum<ThinSet<u32>>> = ThinSet::new();
+/// groups.insert(ThinSentinelEnum::from(vec![1, 2, 3].into_iter().collect::<ThinSet<_>>()));
+/// groups.insert(ThinSentinelEnum::from(vec![3, 2, 1].into_iter().collect::<ThinSet<_>>()));
+/// groups.insert(ThinSentinelEnum::from(ThinSet::new()));
+/// assert_eq!(2, groups.len());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ThinSentinelEnum<T> {
     ZERO,
     ONE,
@@ -150,6 +165,13 @@ impl<T> ThinSentinel for ThinSentinelEnum<T> {
     const SENTINEL_ONE: Self = ThinSentinelEnum::ONE;
 }
 
+impl<T> From<T> for ThinSentinelEnum<T> {
+    #[inline]
+    fn from(value: T) -> ThinSentinelEnum<T> {
+        ThinSentinelEnum::VALUE(value)
+    }
+}
+
 struct TypeIdZero {}
 
 struct TypeIdOne {}
@@ -13,19 +13,25 @@
 //! `ThinSet` uses `ThinMap` underneath, so it inherits all the properties of
 //! `ThinMap`.
 
+use std::collections::{BTreeSet, HashSet};
 use std::fmt::{self};
 use std::hash::BuildHasher;
 use std::hash::Hash;
+use std::hash::Hasher;
 use std::iter::Chain;
 use std::iter::FromIterator;
 use std::iter::FusedIterator;
 use std::ops::BitAnd;
+use std::ops::BitAndAssign;
 use std::ops::BitOr;
+use std::ops::BitOrAssign;
 use std::ops::BitXor;
+use std::ops::BitXorAssign;
 use std::ops::Sub;
+use std::ops::SubAssign;
 
 use crate::thin_hasher::*;
-use crate::thin_map::*;
+use crate::thin_map::{self, *};
 use crate::thin_sentinel::*;
 
 /// A hash set implemented as a `ThinMap` where the value is `()`.
@@ -326,13 +332,13 @@ impl<T, S> ThinSet<T, S>
     /// }
     ///
     /// let diff: ThinSet<i32> = a.difference(&b).cloned().collect();
-    /// assert_eq!(diff, [1].iter().cloned().collect());
+    /// assert_eq!(diff, [1].iter().cloned().collect::<ThinSet<_>>());
     ///
     /// // Note that difference is not symmetric,
     /// // and `b - a` means something else:
     /// let diff: ThinSet<_> = b.difference(&a).cloned().collect();
     /// let diff: ThinSet<_> = b.difference(&a).cloned().collect();
-    /// assert_eq!(diff, [4].iter().cloned().collect());
+    /// assert_eq!(diff, [4].iter().cloned().collect::<ThinSet<_>>());
     /// ```
     pub fn difference<'a>(&'a self, other: &'a ThinSet<T, S>) -> Difference<'a, T, S> {
         Difference {
@@ -360,7 +366,7 @@ impl<T, S> ThinSet<T, S>
     /// let diff2: ThinSet<_> = b.symmetric_difference(&a).cloned().collect();
     ///
     /// assert_eq!(diff1, diff2);
-    /// assert_eq!(diff1, [1, 4].iter().cloned().collect());
+    /// assert_eq!(diff1, [1, 4].iter().cloned().collect::<ThinSet<_>>());
     /// ```
     pub fn symmetric_difference<'a>(&'a self,
                                     other: &'a ThinSet<T, S>)
@@ -384,7 +390,7 @@ impl<T, S> ThinSet<T, S>
     /// }
     ///
     /// let intersection: ThinSet<_> = a.intersection(&b).cloned().collect();
-    /// assert_eq!(intersection, [2, 3].iter().cloned().collect());
+    /// assert_eq!(intersection, [2, 3].iter().cloned().collect::<ThinSet<_>>());
     /// ```
     pub fn intersection<'a>(&'a self, other: &'a ThinSet<T, S>) -> Intersection<'a, T, S> {
         Intersection {
@@ -409,7 +415,7 @@ impl<T, S> ThinSet<T, S>
     /// }
     ///
     /// let union: ThinSet<_> = a.union(&b).cloned().collect();
-    /// assert_eq!(union, [1, 2, 3, 4].iter().cloned().collect());
+    /// assert_eq!(union, [1, 2, 3, 4].iter().cloned().collect::<ThinSet<_>>());
     /// ```
     pub fn union<'a>(&'a self, other: &'a ThinSet<T, S>) -> Union<'a, T, S> {
         Union { iter: self.iter().chain(other.difference(self)) }
@@ -614,6 +620,104 @@ impl<T, S> ThinSet<T, S>
         self.map.insert(value, ()).is_none()
     }
 
+    /// Adds a value to the set if it isn't there, and returns a reference to the value in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut set: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+    /// assert_eq!(set.len(), 3);
+    /// assert_eq!(set.get_or_insert(2), &2);
+    /// assert_eq!(set.get_or_insert(100), &100);
+    /// assert_eq!(set.len(), 4); // 100 was inserted
+    /// ```
+    #[inline]
+    pub fn get_or_insert(&mut self, value: T) -> &T {
+        self.entry(value).or_insert()
+    }
+
+    /// Returns a reference to the value in the set that is equal to `value`. If there isn't one,
+    /// the value made by `f` is inserted first. This saves building an owned value, such as a
+    /// clone, when it's already in the set.
+    ///
+    /// Like the rest of `ThinSet`, the lookup takes a `&T` rather than a borrowed form of `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value made by `f` isn't equal to `value`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    /// use thincollections::thin_v64str::V64Str;
+    ///
+    /// let mut set: ThinSet<V64Str> = ThinSet::new();
+    /// let name = V64Str::from("a long name that lives on the heap");
+    /// let mut clones = 0;
+    /// for _ in 0..3 {
+    ///     set.get_or_insert_with(&name, |n| { clones += 1; n.clone() });
+    /// }
+    /// assert_eq!(1, clones);
+    /// assert_eq!(1, set.len());
+    /// ```
+    #[inline]
+    pub fn get_or_insert_with<F>(&mut self, value: &T, f: F) -> &T
+        where F: FnOnce(&T) -> T
+    {
+        self.map.get_key_or_insert_with(value, |v| (f(v), ()))
+    }
+
+    /// Adds a value to the set, replacing the existing value, if any, that is equal to the given
+    /// one. Returns the replaced value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut set = ThinSet::new();
+    /// assert_eq!(set.replace(7), None);
+    /// assert_eq!(set.replace(7), Some(7));
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    #[inline]
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        self.map.replace_key(value, ())
+    }
+
+    /// Gets the given value's entry in the set, for in-place inspection and insertion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::{Entry, ThinSet};
+    ///
+    /// let mut seen = ThinSet::new();
+    /// let mut firsts = Vec::new();
+    /// for ch in "a short treatise".chars() {
+    ///     if let Entry::Vacant(entry) = seen.entry(ch) {
+    ///         firsts.push(*entry.insert());
+    ///     }
+    /// }
+    /// assert_eq!(firsts.into_iter().collect::<String>(), "a shortei");
+    ///
+    /// match seen.entry('t') {
+    ///     Entry::Occupied(entry) => assert_eq!(entry.remove(), 't'),
+    ///     Entry::Vacant(_) => unreachable!(),
+    /// }
+    /// assert!(!seen.contains(&'t'));
+    /// ```
+    #[inline]
+    pub fn entry(&mut self, value: T) -> Entry<'_, T> {
+        match self.map.entry(value) {
+            thin_map::Entry::Occupied(inner) => Entry::Occupied(OccupiedEntry { inner }),
+            thin_map::Entry::Vacant(inner) => Entry::Vacant(VacantEntry { inner }),
+        }
+    }
+
     /// Removes a value from the set. Returns `true` if the value was
     /// present in the set.
     ///
@@ -672,13 +776,115 @@ impl<T, S> ThinSet<T, S>
     {
         self.map.retain(|k, _| f(k));
     }
+
+    /// Adds the values of `other` to `self`, in place.
+    ///
+    /// Room for all of `other` is reserved up front, so the table grows at most once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+    /// let b: ThinSet<_> = [3, 4].iter().cloned().collect();
+    /// a.union_with(&b);
+    /// assert_eq!(a, [1, 2, 3, 4].iter().cloned().collect::<ThinSet<_>>());
+    /// ```
+    pub fn union_with(&mut self, other: &ThinSet<T, S>)
+        where T: Clone
+    {
+        self.reserve(other.len());
+        for value in other {
+            if !self.contains(value) {
+                self.insert(value.clone());
+            }
+        }
+    }
+
+    /// Keeps only the values of `self` that are also in `other`.
+    ///
+    /// The smaller set drives the work, and `self` keeps its table, its hasher and its own copies
+    /// of equal values. If `self` is the smaller set it's filtered with `retain`. Otherwise the
+    /// values of `other` are taken out of `self`, and put back once the table has been cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+    /// let b: ThinSet<_> = [2, 3, 4].iter().cloned().collect();
+    /// a.intersect_with(&b);
+    /// assert_eq!(a, [2, 3].iter().cloned().collect::<ThinSet<_>>());
+    /// ```
+    pub fn intersect_with(&mut self, other: &ThinSet<T, S>) {
+        if self.len() <= other.len() {
+            self.retain(|v| other.contains(v));
+        } else {
+            let kept: Vec<T> = other.iter().filter_map(|v| self.take(v)).collect();
+            self.clear();
+            for value in kept {
+                self.insert(value);
+            }
+        }
+    }
+
+    /// Removes the values of `other` from `self`, in place.
+    ///
+    /// Walks whichever set is smaller: each value of `other` is removed from `self`, or `self`
+    /// is filtered with `retain`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+    /// let b: ThinSet<_> = [3, 4].iter().cloned().collect();
+    /// a.difference_with(&b);
+    /// assert_eq!(a, [1, 2].iter().cloned().collect::<ThinSet<_>>());
+    /// ```
+    pub fn difference_with(&mut self, other: &ThinSet<T, S>) {
+        if other.len() < self.len() {
+            for value in other {
+                self.remove(value);
+            }
+        } else {
+            self.retain(|v| !other.contains(v));
+        }
+    }
+
+    /// Keeps the values that are in exactly one of `self` and `other`, in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+    /// let b: ThinSet<_> = [3, 4].iter().cloned().collect();
+    /// a.symmetric_difference_with(&b);
+    /// assert_eq!(a, [1, 2, 4].iter().cloned().collect::<ThinSet<_>>());
+    /// ```
+    pub fn symmetric_difference_with(&mut self, other: &ThinSet<T, S>)
+        where T: Clone
+    {
+        for value in other {
+            if !self.remove(value) {
+                self.insert(value.clone());
+            }
+        }
+    }
 }
 
-impl<T, S> PartialEq for ThinSet<T, S>
+impl<T, S, S2> PartialEq<ThinSet<T, S2>> for ThinSet<T, S>
     where T: Eq + Hash + ThinSentinel,
-          S: BuildHasher
+          S: BuildHasher,
+          S2: BuildHasher
 {
-    fn eq(&self, other: &ThinSet<T, S>) -> bool {
+    /// Sets with different hashers are equal if they have the same values.
+    fn eq(&self, other: &ThinSet<T, S2>) -> bool {
         if self.len() != other.len() {
             return false;
         }
@@ -687,11 +893,100 @@ impl<T, S> PartialEq for ThinSet<T, S>
     }
 }
 
+impl<T, S, S2> PartialEq<HashSet<T, S2>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher,
+          S2: BuildHasher
+{
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let thin: ThinSet<u32> = (0..10).collect();
+    /// let std: HashSet<u32> = (0..10).rev().collect();
+    /// assert_eq!(thin, std);
+    /// ```
+    fn eq(&self, other: &HashSet<T, S2>) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<T, S> PartialEq<BTreeSet<T>> for ThinSet<T, S>
+    where T: Eq + Hash + Ord + ThinSentinel,
+          S: BuildHasher
+{
+    fn eq(&self, other: &BTreeSet<T>) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<T, S, S2> From<HashSet<T, S2>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher + Default
+{
+    /// Moves the values into a `ThinSet` sized for them up front.
+    fn from(set: HashSet<T, S2>) -> ThinSet<T, S> {
+        let mut thin = ThinSet::with_capacity_and_hasher(set.len(), S::default());
+        thin.extend(set);
+        thin
+    }
+}
+
+impl<T, S, S2> From<ThinSet<T, S>> for HashSet<T, S2>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher,
+          S2: BuildHasher + Default
+{
+    /// Moves the values into a `HashSet` sized for them up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let thin: ThinSet<u32> = (0..10).collect();
+    /// let std: HashSet<u32> = thin.into();
+    /// assert!(std.contains(&0));
+    /// assert_eq!(ThinSet::<u32>::from(std), (0..10).collect::<ThinSet<_>>());
+    /// ```
+    fn from(thin: ThinSet<T, S>) -> HashSet<T, S2> {
+        let mut set = HashSet::with_capacity_and_hasher(thin.len(), S2::default());
+        set.extend(thin);
+        set
+    }
+}
+
 impl<T, S> Eq for ThinSet<T, S>
     where T: Eq + Hash + ThinSentinel,
           S: BuildHasher
 {}
 
+impl<T, S> Hash for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Equal sets hash the same whatever the order of their values, or their hashers' seeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let a: ThinSet<u32> = (0..100).collect();
+    /// let b: ThinSet<u32> = (0..100).rev().collect();
+    /// let mut names = HashMap::new();
+    /// names.insert(a, "first hundred");
+    /// assert_eq!(names.get(&b), Some(&"first hundred"));
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_unordered(self.len(), self.iter().map(fixed_hash), state);
+    }
+}
+
 impl<T, S> fmt::Debug for ThinSet<T, S>
     where T: Eq + Hash + fmt::Debug + ThinSentinel,
           S: BuildHasher
@@ -864,6 +1159,325 @@ impl<'a, 'b, T, S> Sub<&'b ThinSet<T, S>> for &'a ThinSet<T, S>
     }
 }
 
+impl<T, S> BitOrAssign<&ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + Clone + ThinSentinel,
+          S: BuildHasher
+{
+    /// Adds the values of `rhs` to `self`. See [`union_with`](#method.union_with).
+    fn bitor_assign(&mut self, rhs: &ThinSet<T, S>) {
+        self.union_with(rhs);
+    }
+}
+
+impl<T, S> BitOrAssign<ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Moves the values of `rhs` that aren't in `self` into it. Like
+    /// [`union_with`](#method.union_with), `self` keeps its hasher and its own copies of equal values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = vec![1, 2].into_iter().collect();
+    /// a |= vec![2, 3, 4].into_iter().collect::<ThinSet<_>>();
+    /// assert_eq!(a, vec![1, 2, 3, 4].into_iter().collect::<ThinSet<_>>());
+    /// ```
+    fn bitor_assign(&mut self, rhs: ThinSet<T, S>) {
+        self.reserve(rhs.len());
+        for value in rhs {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T, S> BitAndAssign<&ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Keeps the values of `self` that are in `rhs`.
+    /// See [`intersect_with`](#method.intersect_with).
+    fn bitand_assign(&mut self, rhs: &ThinSet<T, S>) {
+        self.intersect_with(rhs);
+    }
+}
+
+impl<T, S> BitAndAssign<ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Keeps the values of `self` that are in `rhs`.
+    /// See [`intersect_with`](#method.intersect_with).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = vec![1, 2, 3, 4].into_iter().collect();
+    /// a &= vec![2, 4, 6].into_iter().collect::<ThinSet<_>>();
+    /// assert_eq!(a, vec![2, 4].into_iter().collect::<ThinSet<_>>());
+    /// ```
+    fn bitand_assign(&mut self, rhs: ThinSet<T, S>) {
+        self.intersect_with(&rhs);
+    }
+}
+
+impl<T, S> SubAssign<&ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Removes the values of `rhs` from `self`. See [`difference_with`](#method.difference_with).
+    fn sub_assign(&mut self, rhs: &ThinSet<T, S>) {
+        self.difference_with(rhs);
+    }
+}
+
+impl<T, S> SubAssign<ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Removes the values of `rhs` from `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = vec![1, 2, 3].into_iter().collect();
+    /// a -= vec![2, 5].into_iter().collect::<ThinSet<_>>();
+    /// assert_eq!(a, vec![1, 3].into_iter().collect::<ThinSet<_>>());
+    /// ```
+    fn sub_assign(&mut self, rhs: ThinSet<T, S>) {
+        self.difference_with(&rhs);
+    }
+}
+
+impl<T, S> BitXorAssign<&ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + Clone + ThinSentinel,
+          S: BuildHasher
+{
+    /// Keeps the values that are in exactly one of the sets.
+    /// See [`symmetric_difference_with`](#method.symmetric_difference_with).
+    fn bitxor_assign(&mut self, rhs: &ThinSet<T, S>) {
+        self.symmetric_difference_with(rhs);
+    }
+}
+
+impl<T, S> BitXorAssign<ThinSet<T, S>> for ThinSet<T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    /// Keeps the values that are in exactly one of the sets, moving the new ones out of `rhs`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_set::ThinSet;
+    ///
+    /// let mut a: ThinSet<_> = vec![1, 2, 3].into_iter().collect();
+    /// a ^= vec![3, 4].into_iter().collect::<ThinSet<_>>();
+    /// assert_eq!(a, vec![1, 2, 4].into_iter().collect::<ThinSet<_>>());
+    /// ```
+    fn bitxor_assign(&mut self, rhs: ThinSet<T, S>) {
+        for value in rhs {
+            if !self.remove(&value) {
+                self.insert(value);
+            }
+        }
+    }
+}
+
+/// Visits the values that are in every one of `sets`.
+///
+/// The sets are ordered by size: the smallest one is walked and each of its values is probed
+/// against the others, smallest first, so a value is dropped as early as possible. Nothing is
+/// visited if `sets` is empty. Collect the iterator to get a `ThinSet`.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_set::{self, ThinSet};
+///
+/// let a: ThinSet<_> = [1, 2, 3, 4].iter().cloned().collect();
+/// let b: ThinSet<_> = [2, 3, 4, 5].iter().cloned().collect();
+/// let c: ThinSet<_> = [3, 4].iter().cloned().collect();
+///
+/// let all: ThinSet<_> = thin_set::intersection_all(&[&a, &b, &c]).cloned().collect();
+/// assert_eq!(all, [3, 4].iter().cloned().collect::<ThinSet<_>>());
+/// ```
+pub fn intersection_all<'a, T, S>(sets: &[&'a ThinSet<T, S>]) -> IntersectionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    let mut others = sets.to_vec();
+    others.sort_by_key(|s| s.len());
+    if others.is_empty() {
+        return IntersectionAll { iter: None, others };
+    }
+    let smallest = others.remove(0);
+    IntersectionAll { iter: Some(smallest.iter()), others }
+}
+
+/// Visits the values that are in at least one of `sets`, each once.
+///
+/// The sets are ordered from the largest to the smallest, and a value of a set is only visited
+/// if none of the sets before it has it, so the big sets are probed the least.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_set::{self, ThinSet};
+///
+/// let a: ThinSet<_> = [1, 2].iter().cloned().collect();
+/// let b: ThinSet<_> = [2, 3, 4].iter().cloned().collect();
+/// let c: ThinSet<_> = [4, 5].iter().cloned().collect();
+///
+/// let all: ThinSet<_> = thin_set::union_all(&[&a, &b, &c]).cloned().collect();
+/// assert_eq!(all, [1, 2, 3, 4, 5].iter().cloned().collect::<ThinSet<_>>());
+/// ```
+pub fn union_all<'a, T, S>(sets: &[&'a ThinSet<T, S>]) -> UnionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    let mut sets = sets.to_vec();
+    sets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    let iter = sets.first().map(|s| s.iter());
+    UnionAll { sets, index: 0, iter }
+}
+
+/// Returns the number of values that are in both `a` and `b`, without building the intersection.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_set::{self, ThinSet};
+///
+/// let a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+/// let b: ThinSet<_> = [2, 3, 4, 5].iter().cloned().collect();
+/// assert_eq!(2, thin_set::count_intersection(&a, &b));
+/// ```
+pub fn count_intersection<T, S>(a: &ThinSet<T, S>, b: &ThinSet<T, S>) -> usize
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small.iter().filter(|v| large.contains(v)).count()
+}
+
+/// Returns the Jaccard index of `a` and `b`: the size of their intersection divided by the size
+/// of their union. It's 1.0 for two empty sets.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_set::{self, ThinSet};
+///
+/// let a: ThinSet<_> = [1, 2, 3].iter().cloned().collect();
+/// let b: ThinSet<_> = [2, 3, 4, 5].iter().cloned().collect();
+/// assert_eq!(0.4, thin_set::jaccard(&a, &b));
+/// ```
+pub fn jaccard<T, S>(a: &ThinSet<T, S>, b: &ThinSet<T, S>) -> f64
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    let common = count_intersection(a, b);
+    let union = a.len() + b.len() - common;
+    if union == 0 { 1.0 } else { common as f64 / union as f64 }
+}
+
+/// A view into a single value of a `ThinSet`, which may be there or not.
+///
+/// This `enum` is created by the [`entry`] method on [`ThinSet`].
+///
+/// [`ThinSet`]: struct.ThinSet.html
+/// [`entry`]: struct.ThinSet.html#method.entry
+pub enum Entry<'a, T: 'a> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+/// A view into a value that is in a `ThinSet`. It's part of the [`Entry`] enum.
+///
+/// [`Entry`]: enum.Entry.html
+pub struct OccupiedEntry<'a, T: 'a> {
+    inner: thin_map::OccupiedEntry<'a, T, ()>,
+}
+
+/// A view into a value that isn't in a `ThinSet`. It's part of the [`Entry`] enum.
+///
+/// [`Entry`]: enum.Entry.html
+pub struct VacantEntry<'a, T: 'a> {
+    inner: thin_map::VacantEntry<'a, T, ()>,
+}
+
+impl<'a, T> Entry<'a, T>
+    where T: Eq + Hash + ThinSentinel,
+{
+    /// Inserts the value if it isn't in the set, and returns a reference to the value in the set.
+    #[inline]
+    pub fn or_insert(self) -> &'a T {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(),
+        }
+    }
+
+    /// Returns a reference to the value in the set, or to the value that would be inserted.
+    #[inline]
+    pub fn get(&self) -> &T {
+        match *self {
+            Entry::Occupied(ref entry) => entry.get(),
+            Entry::Vacant(ref entry) => entry.get(),
+        }
+    }
+}
+
+impl<'a, T> OccupiedEntry<'a, T>
+    where T: Eq + Hash + ThinSentinel,
+{
+    /// Returns a reference to the value in the set.
+    #[inline]
+    pub fn get(&self) -> &T {
+        self.inner.key()
+    }
+
+    /// Removes the value from the set and returns it.
+    #[inline]
+    pub fn remove(self) -> T {
+        self.inner.remove_entry().0
+    }
+
+    #[inline]
+    fn into_ref(self) -> &'a T {
+        self.inner.into_key()
+    }
+}
+
+impl<'a, T> VacantEntry<'a, T>
+    where T: Eq + Hash + ThinSentinel,
+{
+    /// Returns a reference to the value that would be inserted.
+    #[inline]
+    pub fn get(&self) -> &T {
+        self.inner.key()
+    }
+
+    /// Takes back the value without inserting it.
+    #[inline]
+    pub fn into_value(self) -> T {
+        self.inner.into_key()
+    }
+
+    /// Inserts the value and returns a reference to it.
+    #[inline]
+    pub fn insert(self) -> &'a T {
+        self.inner.insert_key(())
+    }
+}
+
 /// An iterator over the items of a `ThinSet`.
 ///
 /// This `struct` is created by the [`iter`] method on [`ThinSet`].
@@ -952,6 +1566,35 @@ pub struct Union<'a, T: 'a + ThinSentinel + Eq + Hash, S: 'a + BuildHasher + Bui
     iter: Chain<Iter<'a, T>, Difference<'a, T, S>>,
 }
 
+/// A lazy iterator producing elements in the intersection of many `ThinSet`s.
+///
+/// This `struct` is created by the [`intersection_all`] function.
+/// See its documentation for more.
+///
+/// [`intersection_all`]: fn.intersection_all.html
+#[derive(Clone)]
+pub struct IntersectionAll<'a, T: 'a + ThinSentinel + Eq + Hash, S: 'a + BuildHasher> {
+    // iterator of the smallest set
+    iter: Option<Iter<'a, T>>,
+    // the other sets, smallest first
+    others: Vec<&'a ThinSet<T, S>>,
+}
+
+/// A lazy iterator producing elements in the union of many `ThinSet`s.
+///
+/// This `struct` is created by the [`union_all`] function.
+/// See its documentation for more.
+///
+/// [`union_all`]: fn.union_all.html
+#[derive(Clone)]
+pub struct UnionAll<'a, T: 'a + ThinSentinel + Eq + Hash, S: 'a + BuildHasher> {
+    // largest first
+    sets: Vec<&'a ThinSet<T, S>>,
+    // the set being walked
+    index: usize,
+    iter: Option<Iter<'a, T>>,
+}
+
 impl<'a, T, S> IntoIterator for &'a ThinSet<T, S>
     where T: Eq + Hash + ThinSentinel,
           S: BuildHasher
@@ -1227,6 +1870,70 @@ impl<'a, T, S> Iterator for Union<'a, T, S>
     }
 }
 
+impl<'a, T, S> Iterator for IntersectionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let iter = self.iter.as_mut()?;
+        loop {
+            let elt = iter.next()?;
+            if self.others.iter().all(|set| set.contains(elt)) {
+                return Some(elt);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.iter {
+            Some(ref iter) => (0, iter.size_hint().1),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<'a, T, S> FusedIterator for IntersectionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{}
+
+impl<'a, T, S> Iterator for UnionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.iter.as_mut()?.next() {
+                Some(elt) => {
+                    if !self.sets[..self.index].iter().any(|set| set.contains(elt)) {
+                        return Some(elt);
+                    }
+                }
+                None => {
+                    self.index += 1;
+                    self.iter = self.sets.get(self.index).map(|set| set.iter());
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let current = self.iter.as_ref().map_or(0, |iter| iter.len());
+        let rest: usize = self.sets.iter().skip(self.index + 1).map(|set| set.len()).sum();
+        let lower = if self.index == 0 { current } else { 0 };
+        (lower, Some(current + rest))
+    }
+}
+
+impl<'a, T, S> FusedIterator for UnionAll<'a, T, S>
+    where T: Eq + Hash + ThinSentinel,
+          S: BuildHasher
+{}
+
 #[cfg(test)]
 mod test_set {
     use super::super::thin_hasher::OneFieldHasherBuilder;
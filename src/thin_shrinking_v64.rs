@@ -0,0 +1,336 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ShrinkingV64` a `V64` that moves back into the pointer as it empties.
+//! Useful for long lived vectors that briefly grow, such as per key buffers in a map,
+//! where keeping a heap block for a couple of leftover elements wastes memory.
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::slice;
+
+use crate::thin_v64::{IntoIter, V64};
+
+/// A `V64` that gives up its heap block once few enough elements are left.
+///
+/// After `pop`, `truncate`, `clear`, `remove`, `swap_remove` and `retain`, the elements move
+/// back into the 64 bit value once they fill no more than half of its inline capacity.
+/// Waiting for half, instead of shrinking as soon as they fit, keeps a vector that goes
+/// back and forth over the inline capacity from allocating and freeing on every call.
+///
+/// Element types that don't fit inline (more than 7 bytes) only free the heap block when
+/// the vector becomes empty.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_shrinking_v64::ShrinkingV64;
+///
+/// let mut v: ShrinkingV64<u8> = (0..10).collect();
+/// assert!(v.bytes_on_heap() > 0);
+/// v.truncate(7); // fits inline, but would spill again on the next push
+/// assert!(v.bytes_on_heap() > 0);
+/// v.truncate(3);
+/// assert_eq!(0, v.bytes_on_heap());
+/// assert_eq!(v, [0, 1, 2]);
+/// ```
+#[repr(transparent)]
+pub struct ShrinkingV64<T> {
+    v: V64<T>,
+}
+
+impl<T> ShrinkingV64<T> {
+    /// Constructs a new, empty `ShrinkingV64<T>`. Does not allocate.
+    #[inline]
+    pub fn new() -> ShrinkingV64<T> {
+        ShrinkingV64 { v: V64::new() }
+    }
+
+    /// Constructs a new, empty `ShrinkingV64<T>` with at least the specified capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> ShrinkingV64<T> {
+        ShrinkingV64 { v: V64::with_capacity(capacity) }
+    }
+
+    /// Returns the underlying `V64`.
+    #[inline]
+    pub fn as_v64(&self) -> &V64<T> {
+        &self.v
+    }
+
+    /// Converts into the underlying `V64`, without copying.
+    #[inline]
+    pub fn into_v64(self) -> V64<T> {
+        self.v
+    }
+
+    /// Extracts a slice containing the entire vector.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self.v.as_slice()
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.v.as_mut_slice()
+    }
+
+    /// Returns the number of elements in the vector.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.v.capacity()
+    }
+
+    /// Returns the number of bytes allocated on the heap, 0 when the elements are inline.
+    #[inline]
+    pub fn bytes_on_heap(&self) -> usize {
+        self.v.bytes_on_heap()
+    }
+
+    /// Reserves capacity for at least `additional` more elements. See `V64::reserve`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.v.reserve(additional)
+    }
+
+    /// Shrinks the capacity as much as possible, moving the elements inline if they fit.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.v.shrink_to_fit()
+    }
+
+    /// Appends an element to the back of the vector.
+    #[inline]
+    pub fn push(&mut self, val: T) {
+        self.v.push(val)
+    }
+
+    /// Inserts an element at position `index`, shifting all elements after it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    #[inline]
+    pub fn insert(&mut self, index: usize, val: T) {
+        self.v.insert(index, val)
+    }
+
+    /// Removes the last element and returns it, or `None` if the vector is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let ret = self.v.pop();
+        self.shrink_if_small();
+        ret
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping the rest.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.v.truncate(len);
+        self.shrink_if_small();
+    }
+
+    /// Removes all elements and frees the heap block, if any.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.v.clear();
+        self.shrink_if_small();
+    }
+
+    /// Removes and returns the element at position `index`, shifting all elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn remove(&mut self, index: usize) -> T {
+        let ret = self.v.remove(index);
+        self.shrink_if_small();
+        ret
+    }
+
+    /// Removes an element and returns it, replacing it with the last element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let ret = self.v.swap_remove(index);
+        self.shrink_if_small();
+        ret
+    }
+
+    /// Retains only the elements specified by the predicate.
+    #[inline]
+    pub fn retain<F>(&mut self, f: F) where F: FnMut(&T) -> bool {
+        self.v.retain(f);
+        self.shrink_if_small();
+    }
+
+    #[inline]
+    fn shrink_if_small(&mut self) {
+        if self.v.bytes_on_heap() > 0 && self.v.len() <= <V64<T>>::stack_capacity() / 2 {
+            self.v.shrink_to_fit();
+        }
+    }
+}
+
+impl<T: Clone> ShrinkingV64<T> {
+    /// Clones and appends all elements in a slice to the vector.
+    #[inline]
+    pub fn extend_from_slice(&mut self, slice: &[T]) {
+        self.v.extend_from_slice(slice)
+    }
+}
+
+impl<T> Deref for ShrinkingV64<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.v.as_slice()
+    }
+}
+
+impl<T> DerefMut for ShrinkingV64<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.v.as_mut_slice()
+    }
+}
+
+impl<T: Clone> Clone for ShrinkingV64<T> {
+    fn clone(&self) -> ShrinkingV64<T> {
+        ShrinkingV64 { v: self.v.clone() }
+    }
+}
+
+impl<T> Default for ShrinkingV64<T> {
+    /// Creates an empty `ShrinkingV64<T>`.
+    #[inline]
+    fn default() -> ShrinkingV64<T> {
+        ShrinkingV64::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ShrinkingV64<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash> Hash for ShrinkingV64<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&**self, state)
+    }
+}
+
+impl<A: PartialEq<B>, B> PartialEq<ShrinkingV64<B>> for ShrinkingV64<A> {
+    #[inline]
+    fn eq(&self, other: &ShrinkingV64<B>) -> bool { self[..] == other[..] }
+}
+
+impl<T: Eq> Eq for ShrinkingV64<T> {}
+
+impl<'a, A: PartialEq<B>, B> PartialEq<&'a [B]> for ShrinkingV64<A> {
+    #[inline]
+    fn eq(&self, other: &&'a [B]) -> bool { self[..] == other[..] }
+}
+
+macro_rules! array_impls {
+    ($($N: expr)+) => {
+        $(
+            impl<A: PartialEq<B>, B> PartialEq<[B; $N]> for ShrinkingV64<A> {
+                #[inline]
+                fn eq(&self, other: &[B; $N]) -> bool { self[..] == other[..] }
+            }
+        )+
+    }
+}
+
+array_impls! { 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 }
+
+impl<T> From<V64<T>> for ShrinkingV64<T> {
+    #[inline]
+    fn from(v: V64<T>) -> ShrinkingV64<T> {
+        ShrinkingV64 { v }
+    }
+}
+
+impl<T> From<ShrinkingV64<T>> for V64<T> {
+    #[inline]
+    fn from(v: ShrinkingV64<T>) -> V64<T> {
+        v.v
+    }
+}
+
+impl<T> From<Vec<T>> for ShrinkingV64<T> {
+    #[inline]
+    fn from(v: Vec<T>) -> ShrinkingV64<T> {
+        ShrinkingV64 { v: V64::from(v) }
+    }
+}
+
+impl<'a, T: Clone> From<&'a [T]> for ShrinkingV64<T> {
+    #[inline]
+    fn from(s: &'a [T]) -> ShrinkingV64<T> {
+        ShrinkingV64 { v: V64::from(s) }
+    }
+}
+
+impl<T> Extend<T> for ShrinkingV64<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        self.v.extend(iter)
+    }
+}
+
+impl<T> FromIterator<T> for ShrinkingV64<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ShrinkingV64<T> {
+        ShrinkingV64 { v: V64::from_iter(iter) }
+    }
+}
+
+impl<T> IntoIterator for ShrinkingV64<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.v.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ShrinkingV64<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
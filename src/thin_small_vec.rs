@@ -0,0 +1,1239 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Portions copyright The Rust Project Developers. Licensed under
+// the MIT License.
+
+//! # `ThinSmallVec` a vector that keeps up to `N` elements inline.
+//! Once it outgrows its inline buffer, it moves its elements into a `ThinVec`, so the
+//! spilled form is a single pointer to a heap block that holds the length and capacity.
+use std::borrow::Borrow;
+use std::borrow::BorrowMut;
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::iter::FusedIterator;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::num::NonZeroUsize;
+use std::ops::Bound::*;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ops::RangeBounds;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+use crate::thin_vec::ThinVec;
+
+/// A vector that stores up to `N` elements inline, then spills into a `ThinVec`.
+///
+/// `V64` only has 7 bytes of inline storage, which isn't enough for, say, three `u32`s, and
+/// `ThinVec` has no inline storage at all. `ThinSmallVec<T, N>` picks the inline size at compile
+/// time: `ThinSmallVec<u32, 3>` keeps three `u32`s without allocating. When it needs more room,
+/// the elements move to a `ThinVec`, which keeps its length and capacity in the heap block, so
+/// the spilled form only uses one pointer worth of the inline buffer.
+///
+/// `ThinSmallVec` is null optimized: `Option<ThinSmallVec<T, N>>` is the same size as
+/// `ThinSmallVec<T, N>`.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_small_vec::ThinSmallVec;
+/// let mut vec: ThinSmallVec<u32, 3> = ThinSmallVec::new();
+/// vec.push(1);
+/// vec.push(2);
+/// vec.push(3);
+/// assert!(!vec.spilled());
+/// vec.push(4);
+/// assert!(vec.spilled());
+/// assert_eq!(vec, [1, 2, 3, 4]);
+/// ```
+pub struct ThinSmallVec<T, const N: usize> {
+    // inline length + 1, or SPILLED when the elements are in data.heap
+    tag: NonZeroUsize,
+    data: Data<T, N>,
+}
+
+union Data<T, const N: usize> {
+    inline: ManuallyDrop<MaybeUninit<[T; N]>>,
+    heap: ManuallyDrop<ThinVec<T>>,
+}
+
+const SPILLED: usize = usize::MAX;
+
+impl<T, const N: usize> ThinSmallVec<T, N> {
+    /// Constructs a new, empty `ThinSmallVec<T, N>`.
+    ///
+    /// The vector will not allocate until more than `N` elements are pushed onto it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::new();
+    /// vec.push(17);
+    /// vec.push(42); // still inline
+    /// assert!(!vec.spilled());
+    /// ```
+    #[inline]
+    pub const fn new() -> ThinSmallVec<T, N> {
+        ThinSmallVec { tag: unsafe { NonZeroUsize::new_unchecked(1) }, data: Data { inline: ManuallyDrop::new(MaybeUninit::uninit()) } }
+    }
+
+    /// Constructs a new, empty `ThinSmallVec<T, N>` with at least the specified capacity.
+    ///
+    /// If `capacity` is `N` or less, the vector will not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let vec: ThinSmallVec<i32, 4> = ThinSmallVec::with_capacity(2);
+    /// assert_eq!(4, vec.capacity());
+    /// assert!(!vec.spilled());
+    /// let vec: ThinSmallVec<i32, 4> = ThinSmallVec::with_capacity(10);
+    /// assert_eq!(10, vec.capacity());
+    /// assert!(vec.spilled());
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> ThinSmallVec<T, N> {
+        if capacity <= N || mem::size_of::<T>() == 0 {
+            return ThinSmallVec::new();
+        }
+        ThinSmallVec::from_thin_vec(ThinVec::with_capacity(capacity))
+    }
+
+    #[inline]
+    fn from_thin_vec(vec: ThinVec<T>) -> ThinSmallVec<T, N> {
+        unsafe {
+            ThinSmallVec { tag: NonZeroUsize::new_unchecked(SPILLED), data: Data { heap: ManuallyDrop::new(vec) } }
+        }
+    }
+
+    /// The number of elements that can be stored without a heap allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// assert_eq!(5, ThinSmallVec::<u8, 5>::inline_size());
+    /// ```
+    #[inline]
+    pub const fn inline_size() -> usize {
+        N
+    }
+
+    /// Returns `true` if the elements have moved to the heap.
+    #[inline]
+    pub fn spilled(&self) -> bool {
+        self.tag.get() == SPILLED
+    }
+
+    /// Returns the number of elements in the vector, also referred to
+    /// as its 'length'.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let vec: ThinSmallVec<i32, 2> = vec![1, 2, 3].into();
+    /// assert_eq!(3, vec.len());
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        if self.spilled() {
+            unsafe { self.data.heap.len() }
+        } else {
+            self.tag.get() - 1
+        }
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let vec: ThinSmallVec<i32, 3> = ThinSmallVec::new();
+    /// assert_eq!(3, vec.capacity());
+    /// ```
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        if mem::size_of::<T>() == 0 {
+            <usize>::MAX
+        } else if self.spilled() {
+            unsafe { self.data.heap.capacity() }
+        } else {
+            N
+        }
+    }
+
+    /// Returns a raw pointer to the vector's buffer.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        unsafe {
+            if self.spilled() {
+                self.data.heap.as_ptr()
+            } else {
+                self.data.inline.as_ptr() as *const T
+            }
+        }
+    }
+
+    /// Returns an unsafe mutable pointer to the vector's buffer.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        unsafe {
+            if self.spilled() {
+                (*self.data.heap).as_mut_ptr()
+            } else {
+                (*self.data.inline).as_mut_ptr() as *mut T
+            }
+        }
+    }
+
+    /// Extracts a slice containing the entire vector.
+    ///
+    /// Equivalent to `&s[..]`.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    ///
+    /// Equivalent to `&mut s[..]`.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    unsafe fn set_len(&mut self, len: usize) {
+        if self.spilled() {
+            (*self.data.heap).set_len(len);
+        } else {
+            debug_assert!(len <= N || mem::size_of::<T>() == 0);
+            self.tag = NonZeroUsize::new_unchecked(len + 1);
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements to be inserted
+    /// in the given `ThinSmallVec<T, N>`. The collection may reserve more space to avoid
+    /// frequent reallocations. Does nothing if capacity is already sufficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::new();
+    /// vec.push(1);
+    /// vec.reserve(10);
+    /// assert!(vec.capacity() >= 11);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        if self.spilled() {
+            unsafe { (*self.data.heap).reserve(additional) }
+        } else {
+            let required = self.len().checked_add(additional).expect("capacity overflow");
+            if required > self.capacity() {
+                self.spill(cmp::max(required, N * 2));
+            }
+        }
+    }
+
+    /// Reserves the minimum capacity for exactly `additional` more elements to
+    /// be inserted in the given `ThinSmallVec<T, N>`. Does nothing if the capacity is
+    /// already sufficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.spilled() {
+            unsafe { (*self.data.heap).reserve_exact(additional) }
+        } else {
+            let required = self.len().checked_add(additional).expect("capacity overflow");
+            if required > self.capacity() {
+                self.spill(required);
+            }
+        }
+    }
+
+    #[cold]
+    fn spill(&mut self, capacity: usize) {
+        unsafe {
+            let len = self.len();
+            let mut vec = ThinVec::with_capacity(capacity);
+            ptr::copy_nonoverlapping(self.data.inline.as_ptr() as *const T, vec.as_mut_ptr(), len);
+            vec.set_len(len);
+            self.data = Data { heap: ManuallyDrop::new(vec) };
+            self.tag = NonZeroUsize::new_unchecked(SPILLED);
+        }
+    }
+
+    /// Shrinks the capacity of the vector as much as possible.
+    ///
+    /// If the elements fit in the inline buffer, they are moved back there and the heap
+    /// memory is freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::with_capacity(10);
+    /// vec.push(1);
+    /// vec.push(2);
+    /// assert!(vec.spilled());
+    /// vec.shrink_to_fit();
+    /// assert!(!vec.spilled());
+    /// assert_eq!(vec, [1, 2]);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        if !self.spilled() {
+            return;
+        }
+        unsafe {
+            let len = self.len();
+            if len <= N {
+                let mut vec = ManuallyDrop::into_inner(ptr::read(&self.data.heap));
+                self.data = Data { inline: ManuallyDrop::new(MaybeUninit::uninit()) };
+                ptr::copy_nonoverlapping(vec.as_ptr(), (*self.data.inline).as_mut_ptr() as *mut T, len);
+                vec.set_len(0);
+                self.tag = NonZeroUsize::new_unchecked(len + 1);
+            } else {
+                (*self.data.heap).shrink_to_fit();
+            }
+        }
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping
+    /// the rest.
+    ///
+    /// If `len` is greater than the vector's current length, this has no
+    /// effect.
+    ///
+    /// Note that this method has no effect on the allocated capacity
+    /// of the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = vec![1, 2, 3, 4, 5].into();
+    /// vec.truncate(2);
+    /// assert_eq!(vec, [1, 2]);
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        let old_len = self.len();
+        if len >= old_len {
+            return;
+        }
+        unsafe {
+            // set_len reborrows self, which would invalidate a pointer into the inline buffer
+            // taken before it, so the tail pointer is taken afterwards
+            self.set_len(len);
+            let tail = ptr::slice_from_raw_parts_mut(self.as_mut_ptr().add(len), old_len - len);
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    /// Clears the vector, removing all values.
+    ///
+    /// Note that this method has no effect on the allocated capacity
+    /// of the vector.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Appends an element to the back of a collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of elements in the vector overflows a `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::new();
+    /// vec.push(1);
+    /// vec.push(2);
+    /// vec.push(3);
+    /// assert_eq!(vec, [1, 2, 3]);
+    /// ```
+    #[inline]
+    pub fn push(&mut self, val: T) {
+        if self.spilled() {
+            unsafe { (*self.data.heap).push(val) }
+            return;
+        }
+        let len = self.len();
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            ptr::write(self.as_mut_ptr().add(len), val);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes the last element from a vector and returns it, or `None` if it
+    /// is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 4> = vec![1, 2, 3].into();
+    /// assert_eq!(vec.pop(), Some(3));
+    /// assert_eq!(vec, [1, 2]);
+    /// ```
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        unsafe {
+            self.set_len(len - 1);
+            Some(ptr::read(self.as_ptr().add(len - 1)))
+        }
+    }
+
+    /// Inserts an element at position `index` within the vector, shifting all
+    /// elements after it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 3> = vec![1, 2, 3].into();
+    /// vec.insert(1, 4);
+    /// assert_eq!(vec, [1, 4, 2, 3]);
+    /// vec.insert(4, 5);
+    /// assert_eq!(vec, [1, 4, 2, 3, 5]);
+    /// ```
+    pub fn insert(&mut self, index: usize, element: T) {
+        let len = self.len();
+        assert!(index <= len, "index out of bounds! len: {}, index {}", len, index);
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), len - index);
+            ptr::write(p, element);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes and returns the element at position `index` within the vector,
+    /// shifting all elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut v: ThinSmallVec<i32, 3> = vec![1, 2, 3].into();
+    /// assert_eq!(v.remove(1), 2);
+    /// assert_eq!(v, [1, 3]);
+    /// ```
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "index out of bounds! len: {}, index {}", len, index);
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            let ret = ptr::read(p);
+            ptr::copy(p.add(1), p, len - index - 1);
+            self.set_len(len - 1);
+            ret
+        }
+    }
+
+    /// Removes an element from the vector and returns it.
+    ///
+    /// The removed element is replaced by the last element of the vector.
+    ///
+    /// This does not preserve ordering, but is O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut v: ThinSmallVec<&str, 4> = vec!["foo", "bar", "baz", "qux"].into();
+    /// assert_eq!(v.swap_remove(1), "bar");
+    /// assert_eq!(v, ["foo", "qux", "baz"]);
+    /// ```
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "index out of bounds! len: {}, index {}", len, index);
+        self.swap(index, len - 1);
+        self.pop().unwrap()
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` such that `f(&e)` returns `false`.
+    /// This method operates in place and preserves the order of the retained
+    /// elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 4> = vec![1, 2, 3, 4].into();
+    /// vec.retain(|&x| x % 2 == 0);
+    /// assert_eq!(vec, [2, 4]);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&T) -> bool
+    {
+        let len = self.len();
+        let mut del = 0;
+        {
+            let v = &mut **self;
+            for i in 0..len {
+                if !f(&v[i]) {
+                    del += 1;
+                } else if del > 0 {
+                    v.swap(i - del, i);
+                }
+            }
+        }
+        if del > 0 {
+            self.truncate(len - del);
+        }
+    }
+
+    /// Removes all but the first of consecutive elements in the vector satisfying a given equality
+    /// relation.
+    ///
+    /// The `same_bucket` function is passed references to two elements from the vector, and
+    /// returns `true` if the elements compare equal, or `false` if they do not. The elements are
+    /// passed in opposite order from their order in the vector, so if `same_bucket(a, b)` returns
+    /// `true`, `a` is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<&str, 2> = vec!["foo", "bar", "Bar", "baz", "bar"].into();
+    /// vec.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    /// assert_eq!(vec, ["foo", "bar", "baz", "bar"]);
+    /// ```
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F) where F: FnMut(&mut T, &mut T) -> bool {
+        let len = self.len();
+        if len <= 1 {
+            return;
+        }
+        let p = self.as_mut_ptr();
+        let mut w: usize = 1;
+        unsafe {
+            for r in 1..len {
+                let p_r = p.add(r);
+                let p_wm1 = p.add(w - 1);
+                if !same_bucket(&mut *p_r, &mut *p_wm1) {
+                    if r != w {
+                        ptr::swap(p_r, p.add(w));
+                    }
+                    w += 1;
+                }
+            }
+        }
+        self.truncate(w);
+    }
+
+    /// Removes all but the first of consecutive elements in the vector that resolve to the same
+    /// key.
+    ///
+    /// If the vector is sorted, this removes all duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 8> = vec![10, 20, 21, 30, 20].into();
+    /// vec.dedup_by_key(|i| *i / 10);
+    /// assert_eq!(vec, [10, 20, 30, 20]);
+    /// ```
+    #[inline]
+    pub fn dedup_by_key<F, K>(&mut self, mut key: F) where F: FnMut(&mut T) -> K, K: PartialEq {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Moves all the elements of `other` into `Self`, leaving `other` empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of elements in the vector overflows a `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = vec![1, 2].into();
+    /// let mut vec2: ThinSmallVec<i32, 2> = vec![3, 4].into();
+    /// vec.append(&mut vec2);
+    /// assert_eq!(vec, [1, 2, 3, 4]);
+    /// assert!(vec2.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut Self) {
+        let count = other.len();
+        self.reserve(count);
+        unsafe {
+            let len = self.len();
+            ptr::copy_nonoverlapping(other.as_ptr(), self.as_mut_ptr().add(len), count);
+            self.set_len(len + count);
+            other.set_len(0);
+        }
+    }
+
+    /// Splits the collection into two at the given index.
+    ///
+    /// Returns a newly allocated `Self`. `self` contains elements `[0, at)`,
+    /// and the returned `Self` contains elements `[at, len)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = vec![1, 2, 3].into();
+    /// let vec2 = vec.split_off(1);
+    /// assert_eq!(vec, [1]);
+    /// assert_eq!(vec2, [2, 3]);
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(at <= len, "`at` out of bounds");
+        let other_len = len - at;
+        let mut other = ThinSmallVec::with_capacity(other_len);
+        unsafe {
+            self.set_len(at);
+            ptr::copy_nonoverlapping(self.as_ptr().add(at), other.as_mut_ptr(), other_len);
+            other.set_len(other_len);
+        }
+        other
+    }
+
+    /// Resizes the vector in-place so that `len` is equal to `new_len`, filling new
+    /// slots with the values returned by calling `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::new();
+    /// let mut p = 1;
+    /// vec.resize_with(4, || { p *= 2; p });
+    /// assert_eq!(vec, [2, 4, 8, 16]);
+    /// ```
+    pub fn resize_with<F>(&mut self, new_len: usize, mut f: F) where F: FnMut() -> T {
+        let len = self.len();
+        if new_len > len {
+            self.reserve(new_len - len);
+            for _ in len..new_len {
+                self.push(f());
+            }
+        } else {
+            self.truncate(new_len);
+        }
+    }
+
+    /// Creates a draining iterator that removes the specified range in the vector
+    /// and yields the removed items.
+    ///
+    /// Note 1: The element range is removed even if the iterator is only
+    /// partially consumed or not consumed at all.
+    ///
+    /// Note 2: It is unspecified how many elements are removed from the vector
+    /// if the `Drain` value is leaked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point is greater than the end point or if
+    /// the end point is greater than the length of the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut v: ThinSmallVec<i32, 3> = vec![1, 2, 3].into();
+    /// let u: Vec<_> = v.drain(1..).collect();
+    /// assert_eq!(v, [1]);
+    /// assert_eq!(u, [2, 3]);
+    ///
+    /// // A full range clears the vector
+    /// v.drain(..);
+    /// assert!(v.is_empty());
+    /// ```
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T, N>
+        where R: RangeBounds<usize>
+    {
+        // Same approach as ThinVec::drain: shorten the vector first, so a leaked Drain
+        // can't expose moved-from elements, then move the tail back when the Drain drops.
+        let len = self.len();
+        let start = match range.start_bound() {
+            Included(&n) => n,
+            Excluded(&n) => n + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Included(&n) => n + 1,
+            Excluded(&n) => n,
+            Unbounded => len,
+        };
+        assert!(start <= end, "drain start greater than end");
+        assert!(end <= len, "drain end out of bounds");
+
+        unsafe {
+            self.set_len(start);
+            // the slice is derived from the NonNull, as moving `self` into it afterwards would
+            // invalidate a borrow of the inline buffer
+            let vec = NonNull::from(self);
+            let range_slice = slice::from_raw_parts(vec.as_ref().as_ptr().add(start), end - start);
+            Drain {
+                tail_start: end,
+                tail_len: len - end,
+                iter: range_slice.iter(),
+                vec,
+            }
+        }
+    }
+
+    /// Converts the vector into a `ThinVec<T>`, without reallocating if it has spilled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let vec: ThinSmallVec<i32, 2> = vec![1, 2, 3].into();
+    /// let thin = vec.into_thin_vec();
+    /// assert_eq!(thin, [1, 2, 3]);
+    /// ```
+    pub fn into_thin_vec(self) -> ThinVec<T> {
+        unsafe {
+            let me = ManuallyDrop::new(self);
+            if me.spilled() {
+                ManuallyDrop::into_inner(ptr::read(&me.data.heap))
+            } else {
+                let len = me.len();
+                let mut vec = ThinVec::with_capacity(len);
+                ptr::copy_nonoverlapping(me.as_ptr(), vec.as_mut_ptr(), len);
+                vec.set_len(len);
+                vec
+            }
+        }
+    }
+
+    /// Converts the vector into a `Vec<T>`.
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+}
+
+impl<T: Clone, const N: usize> ThinSmallVec<T, N> {
+    pub fn from_elem(elem: T, count: usize) -> ThinSmallVec<T, N> {
+        let mut vec = ThinSmallVec::with_capacity(count);
+        vec.resize(count, elem);
+        vec
+    }
+
+    /// Creates a `ThinSmallVec` by cloning the elements of a slice.
+    pub fn from_slice(slice: &[T]) -> ThinSmallVec<T, N> {
+        let mut vec = ThinSmallVec::with_capacity(slice.len());
+        vec.extend_from_slice(slice);
+        vec
+    }
+
+    /// Clones and appends all elements in a slice to the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 2> = ThinSmallVec::new();
+    /// vec.push(1);
+    /// vec.extend_from_slice(&[2, 3, 4]);
+    /// assert_eq!(vec, [1, 2, 3, 4]);
+    /// ```
+    pub fn extend_from_slice(&mut self, slice: &[T]) {
+        self.reserve(slice.len());
+        for t in slice {
+            self.push(t.clone());
+        }
+    }
+
+    /// Resizes the vector in-place so that `len` is equal to `new_len`, filling new
+    /// slots with clones of `value`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<&str, 2> = vec!["hello"].into();
+    /// vec.resize(3, "world");
+    /// assert_eq!(vec, ["hello", "world", "world"]);
+    /// vec.resize(1, "unused");
+    /// assert_eq!(vec, ["hello"]);
+    /// ```
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        let len = self.len();
+        if new_len > len {
+            self.reserve(new_len - len);
+            for _ in len + 1..new_len {
+                self.push(value.clone());
+            }
+            self.push(value);
+        } else {
+            self.truncate(new_len);
+        }
+    }
+}
+
+impl<T: PartialEq, const N: usize> ThinSmallVec<T, N> {
+    /// Removes consecutive repeated elements in the vector.
+    ///
+    /// If the vector is sorted, this removes all duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let mut vec: ThinSmallVec<i32, 4> = vec![1, 2, 2, 3, 2].into();
+    /// vec.dedup();
+    /// assert_eq!(vec, [1, 2, 3, 2]);
+    /// ```
+    #[inline]
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+}
+
+impl<T, const N: usize> Drop for ThinSmallVec<T, N> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.spilled() {
+                ManuallyDrop::drop(&mut self.data.heap);
+            } else {
+                ptr::drop_in_place(&mut **self as *mut [T]);
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Deref for ThinSmallVec<T, N> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for ThinSmallVec<T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe {
+            let len = self.len();
+            slice::from_raw_parts_mut(self.as_mut_ptr(), len)
+        }
+    }
+}
+
+impl<T, const N: usize> Default for ThinSmallVec<T, N> {
+    fn default() -> ThinSmallVec<T, N> {
+        ThinSmallVec::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ThinSmallVec<T, N> {
+    fn clone(&self) -> ThinSmallVec<T, N> {
+        ThinSmallVec::from_slice(self)
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ThinSmallVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash, const N: usize> Hash for ThinSmallVec<T, N> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&**self, state)
+    }
+}
+
+macro_rules! __impl_slice_eq1 {
+    ($Lhs: ty, $Rhs: ty) => {
+        __impl_slice_eq1! { $Lhs, $Rhs, Sized }
+    };
+    ($Lhs: ty, $Rhs: ty, $Bound: ident) => {
+        impl<'a, 'b, A: $Bound, B, const N: usize> PartialEq<$Rhs> for $Lhs where A: PartialEq<B> {
+            #[inline]
+            fn eq(&self, other: &$Rhs) -> bool { self[..] == other[..] }
+        }
+    }
+}
+
+__impl_slice_eq1! { ThinSmallVec<A, N>, Vec<B> }
+__impl_slice_eq1! { ThinSmallVec<A, N>, &'b [B] }
+__impl_slice_eq1! { ThinSmallVec<A, N>, &'b mut [B] }
+
+impl<A, B, const N: usize, const M: usize> PartialEq<ThinSmallVec<B, M>> for ThinSmallVec<A, N> where A: PartialEq<B> {
+    #[inline]
+    fn eq(&self, other: &ThinSmallVec<B, M>) -> bool { self[..] == other[..] }
+}
+
+impl<A, B, const N: usize, const M: usize> PartialEq<[B; M]> for ThinSmallVec<A, N> where A: PartialEq<B> {
+    #[inline]
+    fn eq(&self, other: &[B; M]) -> bool { self[..] == other[..] }
+}
+
+impl<'b, A, B, const N: usize, const M: usize> PartialEq<&'b [B; M]> for ThinSmallVec<A, N> where A: PartialEq<B> {
+    #[inline]
+    fn eq(&self, other: &&'b [B; M]) -> bool { self[..] == other[..] }
+}
+
+impl<T: Eq, const N: usize> Eq for ThinSmallVec<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for ThinSmallVec<T, N> {
+    #[inline]
+    fn partial_cmp(&self, other: &ThinSmallVec<T, N>) -> Option<cmp::Ordering> {
+        PartialOrd::partial_cmp(&**self, &**other)
+    }
+}
+
+impl<T: Ord, const N: usize> Ord for ThinSmallVec<T, N> {
+    #[inline]
+    fn cmp(&self, other: &ThinSmallVec<T, N>) -> cmp::Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for ThinSmallVec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for ThinSmallVec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> Borrow<[T]> for ThinSmallVec<T, N> {
+    fn borrow(&self) -> &[T] {
+        &self[..]
+    }
+}
+
+impl<T, const N: usize> BorrowMut<[T]> for ThinSmallVec<T, N> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        &mut self[..]
+    }
+}
+
+impl<T, const N: usize> Extend<T> for ThinSmallVec<T, N> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for t in iter {
+            self.push(t);
+        }
+    }
+}
+
+impl<'a, T: 'a + Copy, const N: usize> Extend<&'a T> for ThinSmallVec<T, N> {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned())
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for ThinSmallVec<T, N> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinSmallVec<T, N> {
+        let mut vec = ThinSmallVec::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<'a, T: Clone, const N: usize> From<&'a [T]> for ThinSmallVec<T, N> {
+    fn from(s: &'a [T]) -> ThinSmallVec<T, N> {
+        ThinSmallVec::from_slice(s)
+    }
+}
+
+impl<T, const N: usize> From<Vec<T>> for ThinSmallVec<T, N> {
+    fn from(v: Vec<T>) -> ThinSmallVec<T, N> {
+        v.into_iter().collect()
+    }
+}
+
+impl<T, const N: usize> From<ThinVec<T>> for ThinSmallVec<T, N> {
+    /// Takes over the `ThinVec`'s heap block, so the result is spilled even if it's small.
+    fn from(v: ThinVec<T>) -> ThinSmallVec<T, N> {
+        ThinSmallVec::from_thin_vec(v)
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for ThinSmallVec<T, N> {
+    /// Stores the whole array inline.
+    fn from(a: [T; N]) -> ThinSmallVec<T, N> {
+        unsafe {
+            ThinSmallVec { tag: NonZeroUsize::new_unchecked(N + 1), data: Data { inline: ManuallyDrop::new(MaybeUninit::new(a)) } }
+        }
+    }
+}
+
+impl<T, const N: usize> From<ThinSmallVec<T, N>> for ThinVec<T> {
+    fn from(v: ThinSmallVec<T, N>) -> ThinVec<T> {
+        v.into_thin_vec()
+    }
+}
+
+impl<T, const N: usize> From<ThinSmallVec<T, N>> for Vec<T> {
+    fn from(v: ThinSmallVec<T, N>) -> Vec<T> {
+        v.into_vec()
+    }
+}
+
+/// An iterator that moves out of a `ThinSmallVec`.
+///
+/// This `struct` is created by the `into_iter` method on [`ThinSmallVec`][`ThinSmallVec`].
+///
+/// [`ThinSmallVec`]: struct.ThinSmallVec.html
+pub struct IntoIter<T, const N: usize> {
+    // length is kept at 0, so dropping it only frees the heap block
+    vec: ThinSmallVec<T, N>,
+    start: usize,
+    end: usize,
+}
+
+impl<T, const N: usize> IntoIterator for ThinSmallVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    /// Creates a consuming iterator, that is, one that moves each value out of
+    /// the vector (from start to end). The vector cannot be used after calling
+    /// this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_small_vec::ThinSmallVec;
+    /// let v: ThinSmallVec<String, 2> = vec!["a".to_string(), "b".to_string()].into();
+    /// for s in v.into_iter() {
+    ///     // s has type String, not &String
+    ///     println!("{}", s);
+    /// }
+    /// ```
+    #[inline]
+    fn into_iter(mut self) -> IntoIter<T, N> {
+        let len = self.len();
+        unsafe { self.set_len(0); }
+        IntoIter { vec: self, start: 0, end: len }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ThinSmallVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ThinSmallVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIter<T, N> {
+    /// Returns the remaining items of this iterator as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.vec.as_ptr().add(self.start), self.end - self.start) }
+    }
+
+    /// Returns the remaining items of this iterator as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.vec.as_mut_ptr().add(self.start), self.end - self.start) }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for IntoIter<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IntoIter")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            let old = self.start;
+            self.start += 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(old))) }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.end - self.start;
+        (exact, Some(exact))
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.len()
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            unsafe { Some(ptr::read(self.vec.as_ptr().add(self.end))) }
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> FusedIterator for IntoIter<T, N> {}
+
+impl<T: Clone, const N: usize> Clone for IntoIter<T, N> {
+    fn clone(&self) -> IntoIter<T, N> {
+        ThinSmallVec::<T, N>::from_slice(self.as_slice()).into_iter()
+    }
+}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        // destroy the remaining elements
+        for _x in self.by_ref() {}
+    }
+}
+
+/// A draining iterator for `ThinSmallVec<T, N>`.
+///
+/// This `struct` is created by the [`drain`] method on [`ThinSmallVec`].
+///
+/// [`drain`]: struct.ThinSmallVec.html#method.drain
+/// [`ThinSmallVec`]: struct.ThinSmallVec.html
+pub struct Drain<'a, T: 'a, const N: usize> {
+    /// Index of tail to preserve
+    tail_start: usize,
+    /// Length of tail
+    tail_len: usize,
+    /// Current remaining range to remove
+    iter: slice::Iter<'a, T>,
+    vec: NonNull<ThinSmallVec<T, N>>,
+}
+
+impl<'a, T: 'a + fmt::Debug, const N: usize> fmt::Debug for Drain<'a, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Drain")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+unsafe impl<'a, T: Sync, const N: usize> Sync for Drain<'a, T, N> {}
+
+unsafe impl<'a, T: Send, const N: usize> Send for Drain<'a, T, N> {}
+
+impl<'a, T, const N: usize> Iterator for Drain<'a, T, N> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next().map(|elt| unsafe { ptr::read(elt as *const _) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Drain<'a, T, N> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back().map(|elt| unsafe { ptr::read(elt as *const _) })
+    }
+}
+
+impl<'a, T, const N: usize> Drop for Drain<'a, T, N> {
+    fn drop(&mut self) {
+        // exhaust self first
+        self.for_each(drop);
+
+        if self.tail_len > 0 {
+            unsafe {
+                let source_vec = self.vec.as_mut();
+                // memmove back untouched tail, update to new length
+                let start = source_vec.len();
+                let tail = self.tail_start;
+                if tail != start {
+                    let p = source_vec.as_mut_ptr();
+                    ptr::copy(p.add(tail), p.add(start), self.tail_len);
+                }
+                source_vec.set_len(start + self.tail_len);
+            }
+        }
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Drain<'a, T, N> {}
+
+impl<'a, T, const N: usize> FusedIterator for Drain<'a, T, N> {}
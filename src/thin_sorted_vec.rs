@@ -0,0 +1,398 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # Sorted vectors that work as compact sets.
+//! `SortedThinVec` and `SortedV64` keep their elements sorted and free of duplicates, so
+//! lookups are a binary search and set operations between two of them run in linear time.
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Bound::*;
+use std::ops::Deref;
+use std::ops::RangeBounds;
+use std::slice;
+
+use crate::thin_v64::{self, V64};
+use crate::thin_vec::{self, ThinVec};
+
+macro_rules! sorted_vec {
+    ($(#[$attr: meta])* $name: ident, $vec: ident, $into_iter: ident) => {
+        $(#[$attr])*
+        pub struct $name<T> {
+            v: $vec<T>,
+        }
+
+        impl<T> $name<T> {
+            /// Constructs a new, empty sorted vector. Does not allocate.
+            #[inline]
+            pub fn new() -> $name<T> {
+                $name { v: $vec::new() }
+            }
+
+            /// Constructs a new, empty sorted vector with at least the specified capacity.
+            #[inline]
+            pub fn with_capacity(capacity: usize) -> $name<T> {
+                $name { v: $vec::with_capacity(capacity) }
+            }
+
+            /// Returns the number of elements.
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.v.len()
+            }
+
+            /// Returns `true` if there are no elements.
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.v.is_empty()
+            }
+
+            /// Returns the number of elements the vector can hold without reallocating.
+            #[inline]
+            pub fn capacity(&self) -> usize {
+                self.v.capacity()
+            }
+
+            /// Reserves capacity for at least `additional` more elements.
+            #[inline]
+            pub fn reserve(&mut self, additional: usize) {
+                self.v.reserve(additional);
+            }
+
+            /// Shrinks the capacity as much as possible.
+            #[inline]
+            pub fn shrink_to_fit(&mut self) {
+                self.v.shrink_to_fit();
+            }
+
+            /// Removes all the elements.
+            #[inline]
+            pub fn clear(&mut self) {
+                self.v.clear();
+            }
+
+            /// Returns the elements, in ascending order, as a slice.
+            #[inline]
+            pub fn as_slice(&self) -> &[T] {
+                self.v.as_slice()
+            }
+
+            /// Returns the underlying vector, without copying.
+            #[inline]
+            pub fn into_inner(self) -> $vec<T> {
+                self.v
+            }
+
+            /// Keeps only the elements for which `f` returns `true`. Removing elements can't
+            /// break the ordering, so this is a single pass.
+            #[inline]
+            pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+                self.v.retain(f);
+            }
+        }
+
+        impl<T: Ord> $name<T> {
+            /// Inserts `value` at its sorted position, shifting the larger elements up.
+            ///
+            /// Returns `false`, and leaves the vector unchanged, if an equal element was already present.
+            pub fn insert(&mut self, value: T) -> bool {
+                match self.v.binary_search(&value) {
+                    Ok(_) => false,
+                    Err(index) => {
+                        self.v.insert(index, value);
+                        true
+                    }
+                }
+            }
+
+            /// Removes the element equal to `value`, if there is one, and returns it.
+            pub fn remove<Q: ?Sized + Ord>(&mut self, value: &Q) -> Option<T> where T: Borrow<Q> {
+                match self.v.binary_search_by(|e| e.borrow().cmp(value)) {
+                    Ok(index) => Some(self.v.remove(index)),
+                    Err(_) => None,
+                }
+            }
+
+            /// Returns `true` if an element equal to `value` is present.
+            pub fn contains<Q: ?Sized + Ord>(&self, value: &Q) -> bool where T: Borrow<Q> {
+                self.v.binary_search_by(|e| e.borrow().cmp(value)).is_ok()
+            }
+
+            /// Returns the elements that fall in `range` as a slice. An inverted range gives an
+            /// empty slice.
+            pub fn range<Q: ?Sized + Ord, R: RangeBounds<Q>>(&self, range: R) -> &[T] where T: Borrow<Q> {
+                let start = match range.start_bound() {
+                    Included(q) => self.v.partition_point(|e| e.borrow() < q),
+                    Excluded(q) => self.v.partition_point(|e| e.borrow() <= q),
+                    Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Included(q) => self.v.partition_point(|e| e.borrow() <= q),
+                    Excluded(q) => self.v.partition_point(|e| e.borrow() < q),
+                    Unbounded => self.v.len(),
+                };
+                if start < end { &self.v[start..end] } else { &[] }
+            }
+
+            /// Moves all the elements of `other` into this vector, in linear time.
+            /// Elements already present are kept and `other`'s duplicates are dropped.
+            pub fn merge(&mut self, mut other: $name<T>) {
+                if other.is_empty() {
+                    return;
+                }
+                if self.v.last().map_or(true, |last| *last < other.v[0]) {
+                    self.v.append(&mut other.v);
+                    return;
+                }
+                let mut out = $vec::with_capacity(self.len() + other.len());
+                let mut a = mem::take(&mut self.v).into_iter().peekable();
+                let mut b = other.v.into_iter().peekable();
+                loop {
+                    let order = match (a.peek(), b.peek()) {
+                        (Some(x), Some(y)) => x.cmp(y),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => break,
+                    };
+                    match order {
+                        Ordering::Less => out.push(a.next().unwrap()),
+                        Ordering::Greater => out.push(b.next().unwrap()),
+                        Ordering::Equal => {
+                            out.push(a.next().unwrap());
+                            b.next();
+                        }
+                    }
+                }
+                self.v = out;
+            }
+
+            /// Returns the elements that are in either vector, in linear time.
+            pub fn union(&self, other: &$name<T>) -> $name<T> where T: Clone {
+                let (a, b) = (self.as_slice(), other.as_slice());
+                let mut out = $vec::with_capacity(a.len() + b.len());
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() && j < b.len() {
+                    match a[i].cmp(&b[j]) {
+                        Ordering::Less => {
+                            out.push(a[i].clone());
+                            i += 1;
+                        }
+                        Ordering::Greater => {
+                            out.push(b[j].clone());
+                            j += 1;
+                        }
+                        Ordering::Equal => {
+                            out.push(a[i].clone());
+                            i += 1;
+                            j += 1;
+                        }
+                    }
+                }
+                out.extend_from_slice(&a[i..]);
+                out.extend_from_slice(&b[j..]);
+                $name { v: out }
+            }
+
+            /// Returns the elements that are in both vectors, in linear time.
+            pub fn intersection(&self, other: &$name<T>) -> $name<T> where T: Clone {
+                let (a, b) = (self.as_slice(), other.as_slice());
+                let mut out = $vec::new();
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() && j < b.len() {
+                    match a[i].cmp(&b[j]) {
+                        Ordering::Less => i += 1,
+                        Ordering::Greater => j += 1,
+                        Ordering::Equal => {
+                            out.push(a[i].clone());
+                            i += 1;
+                            j += 1;
+                        }
+                    }
+                }
+                $name { v: out }
+            }
+        }
+
+        impl<T: Ord> From<$vec<T>> for $name<T> {
+            /// Sorts the vector and removes its duplicates, keeping the first of each.
+            fn from(mut v: $vec<T>) -> $name<T> {
+                v.sort();
+                v.dedup();
+                $name { v }
+            }
+        }
+
+        impl<T> From<$name<T>> for $vec<T> {
+            #[inline]
+            fn from(s: $name<T>) -> $vec<T> {
+                s.v
+            }
+        }
+
+        impl<T: Ord> FromIterator<T> for $name<T> {
+            fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> $name<T> {
+                $name::from(iter.into_iter().collect::<$vec<T>>())
+            }
+        }
+
+        impl<T: Ord> Extend<T> for $name<T> {
+            fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+                let len = self.v.len();
+                self.v.extend(iter);
+                if self.v.len() > len {
+                    // the sorted prefix is a single run, which the merge sort picks up
+                    self.v.sort();
+                    self.v.dedup();
+                }
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = [T];
+
+            #[inline]
+            fn deref(&self) -> &[T] {
+                self.v.as_slice()
+            }
+        }
+
+        impl<T> AsRef<[T]> for $name<T> {
+            #[inline]
+            fn as_ref(&self) -> &[T] {
+                self.v.as_slice()
+            }
+        }
+
+        impl<T> IntoIterator for $name<T> {
+            type Item = T;
+            type IntoIter = $into_iter::IntoIter<T>;
+
+            #[inline]
+            fn into_iter(self) -> $into_iter::IntoIter<T> {
+                self.v.into_iter()
+            }
+        }
+
+        impl<'a, T> IntoIterator for &'a $name<T> {
+            type Item = &'a T;
+            type IntoIter = slice::Iter<'a, T>;
+
+            #[inline]
+            fn into_iter(self) -> slice::Iter<'a, T> {
+                self.v.iter()
+            }
+        }
+
+        impl<T: Clone> Clone for $name<T> {
+            #[inline]
+            fn clone(&self) -> $name<T> {
+                $name { v: self.v.clone() }
+            }
+        }
+
+        impl<T> Default for $name<T> {
+            #[inline]
+            fn default() -> $name<T> {
+                $name::new()
+            }
+        }
+
+        impl<T: PartialEq> PartialEq for $name<T> {
+            #[inline]
+            fn eq(&self, other: &$name<T>) -> bool {
+                self.as_slice() == other.as_slice()
+            }
+        }
+
+        impl<T: Eq> Eq for $name<T> {}
+
+        impl<T: PartialOrd> PartialOrd for $name<T> {
+            #[inline]
+            fn partial_cmp(&self, other: &$name<T>) -> Option<Ordering> {
+                self.as_slice().partial_cmp(other.as_slice())
+            }
+        }
+
+        impl<T: Ord> Ord for $name<T> {
+            #[inline]
+            fn cmp(&self, other: &$name<T>) -> Ordering {
+                self.as_slice().cmp(other.as_slice())
+            }
+        }
+
+        impl<T: Hash> Hash for $name<T> {
+            #[inline]
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.as_slice().hash(state)
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for $name<T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+    }
+}
+
+sorted_vec! {
+    /// A `ThinVec` that keeps its elements sorted and unique, for use as a compact set.
+    ///
+    /// `insert`, `remove` and `contains` binary search, then shift elements like `ThinVec::insert`
+    /// and `ThinVec::remove`, so they suit small to medium sets such as adjacency lists.
+    /// `merge`, `union` and `intersection` walk both vectors once.
+    ///
+    /// The vector derefs to a sorted slice; it can't be mutated in place, as that could break
+    /// the ordering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_sorted_vec::SortedThinVec;
+    ///
+    /// let mut a: SortedThinVec<u32> = vec![5, 1, 3, 1].into_iter().collect();
+    /// assert_eq!(*a, [1, 3, 5]);
+    /// assert!(a.insert(4));
+    /// assert!(!a.insert(3));
+    /// assert!(a.contains(&4));
+    /// assert_eq!(a.range(2..5), [3, 4]);
+    ///
+    /// let b: SortedThinVec<u32> = vec![2, 3, 6].into_iter().collect();
+    /// assert_eq!(*a.union(&b), [1, 2, 3, 4, 5, 6]);
+    /// assert_eq!(*a.intersection(&b), [3]);
+    /// a.merge(b);
+    /// assert_eq!(*a, [1, 2, 3, 4, 5, 6]);
+    /// assert_eq!(Some(4), a.remove(&4));
+    /// ```
+    SortedThinVec, ThinVec, thin_vec
+}
+
+sorted_vec! {
+    /// A `V64` that keeps its elements sorted and unique, for use as a compact set.
+    ///
+    /// It has the same methods as `SortedThinVec`. Small sets, such as up to 7 `u8`s or 3 `u16`s,
+    /// live inside the 64 bit value and don't allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_sorted_vec::SortedV64;
+    ///
+    /// let mut s: SortedV64<u16> = SortedV64::new();
+    /// s.insert(30);
+    /// s.insert(10);
+    /// s.insert(20);
+    /// assert_eq!(*s, [10, 20, 30]);
+    /// assert_eq!(0, s.into_inner().bytes_on_heap());
+    /// ```
+    SortedV64, V64, thin_v64
+}
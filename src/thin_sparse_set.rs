@@ -0,0 +1,597 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ThinSparseSet` and `ThinSparseMap` for dense `u32` ids with O(1) clear.
+//! The sparse/dense layout from Briggs and Torczon: a sparse array maps an id to its slot in a
+//! dense array, and a slot is only trusted if the dense array points back at the id.
+use std::fmt;
+use std::iter::{self, Chain, FromIterator, FusedIterator, Zip};
+use std::slice;
+
+use crate::thin_vec::ThinVec;
+
+/// A set of `u32` ids with O(1) `insert`, `remove`, `contains` and `clear`.
+///
+/// The ids are kept back to back in a dense `ThinVec<u32>`, and a sparse `ThinVec<u32>` indexed
+/// by id holds each id's position in the dense one. `contains` only trusts a sparse entry if the
+/// dense entry it points to is the id itself, so stale entries are harmless and `clear` only has
+/// to reset the dense length. The sparse vector grows to the largest id ever inserted, which makes
+/// this the set for small, dense ids such as entity or node indexes that are cleared and refilled
+/// often. Use a `ThinSet` for large, scattered values.
+///
+/// Iteration walks the dense vector, so it visits the ids in insertion order as long as nothing is
+/// removed. `remove` moves the last id into the hole it leaves.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_sparse_set::ThinSparseSet;
+///
+/// let mut a: ThinSparseSet = vec![7, 3, 12].into_iter().collect();
+/// assert!(a.insert(5));
+/// assert!(!a.insert(3));
+/// assert!(a.contains(12));
+/// assert_eq!(a.as_slice(), [7, 3, 12, 5]);
+///
+/// let b: ThinSparseSet = vec![3, 5, 8].into_iter().collect();
+/// assert_eq!(a.intersection(&b).cloned().collect::<Vec<_>>(), [3, 5]);
+///
+/// a.clear();
+/// assert!(a.is_empty());
+/// assert!(!a.contains(7));
+/// ```
+#[derive(Clone, Default)]
+pub struct ThinSparseSet {
+    // sparse[id] is the position of id in dense, if dense agrees
+    sparse: ThinVec<u32>,
+    dense: ThinVec<u32>,
+}
+
+impl ThinSparseSet {
+    /// Constructs a new, empty `ThinSparseSet`. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinSparseSet {
+        ThinSparseSet { sparse: ThinVec::new(), dense: ThinVec::new() }
+    }
+
+    /// Constructs a new, empty `ThinSparseSet` that can hold `len` ids below `universe` without
+    /// reallocating.
+    #[inline]
+    pub fn with_capacity(len: usize, universe: usize) -> ThinSparseSet {
+        ThinSparseSet {
+            sparse: ThinVec::with_capacity(universe),
+            dense: ThinVec::with_capacity(len),
+        }
+    }
+
+    /// Adds an id to the set. Returns `true` if it wasn't there already.
+    #[inline]
+    pub fn insert(&mut self, id: u32) -> bool {
+        if self.contains(id) {
+            return false;
+        }
+        let index = id as usize;
+        if index >= self.sparse.len() {
+            // the new entries are stale, contains() checks them against dense
+            let grow = index + 1 - self.sparse.len();
+            self.sparse.extend(iter::repeat_n(0, grow));
+        }
+        self.sparse[index] = self.dense.len() as u32;
+        self.dense.push(id);
+        true
+    }
+
+    /// Removes an id from the set. Returns `true` if it was there.
+    ///
+    /// The last id takes the place of the removed one, which changes the iteration order.
+    #[inline]
+    pub fn remove(&mut self, id: u32) -> bool {
+        self.remove_slot(id).is_some()
+    }
+
+    /// Returns `true` if the set contains `id`.
+    #[inline]
+    pub fn contains(&self, id: u32) -> bool {
+        self.slot(id).is_some()
+    }
+
+    /// Returns the number of ids in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Returns `true` if the set is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Removes all the ids in O(1). The memory is kept for the next round of inserts.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    /// Returns the ids in iteration order.
+    #[inline]
+    pub fn as_slice(&self) -> &[u32] {
+        &self.dense
+    }
+
+    /// Returns an iterator over the ids, in insertion order if nothing was removed.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, u32> {
+        self.dense.iter()
+    }
+
+    /// Visits the ids that are in `self` but not in `other`, in `self`'s order.
+    pub fn difference<'a>(&'a self, other: &'a ThinSparseSet) -> Difference<'a> {
+        Difference { iter: self.iter(), other }
+    }
+
+    /// Visits the ids that are in `self` or in `other` but not in both.
+    pub fn symmetric_difference<'a>(&'a self,
+                                    other: &'a ThinSparseSet)
+                                    -> SymmetricDifference<'a> {
+        SymmetricDifference { iter: self.difference(other).chain(other.difference(self)) }
+    }
+
+    /// Visits the ids that are both in `self` and `other`, in `self`'s order.
+    pub fn intersection<'a>(&'a self, other: &'a ThinSparseSet) -> Intersection<'a> {
+        Intersection { iter: self.iter(), other }
+    }
+
+    /// Visits the ids of `self`, then the ids of `other` that aren't in `self`.
+    pub fn union<'a>(&'a self, other: &'a ThinSparseSet) -> Union<'a> {
+        Union { iter: self.iter().chain(other.difference(self)) }
+    }
+
+    /// Returns `true` if every id of this set is in `other`.
+    pub fn is_subset(&self, other: &ThinSparseSet) -> bool {
+        self.len() <= other.len() && self.iter().all(|id| other.contains(*id))
+    }
+
+    /// Returns `true` if the sets have no id in common.
+    pub fn is_disjoint(&self, other: &ThinSparseSet) -> bool {
+        let (small, large) = if self.len() <= other.len() { (self, other) } else { (other, self) };
+        small.iter().all(|id| !large.contains(*id))
+    }
+
+    /// Shrinks the dense vector to the current length, and the sparse one to the largest id.
+    pub fn shrink_to_fit(&mut self) {
+        let universe = self.dense.iter().max().map_or(0, |id| *id as usize + 1);
+        self.sparse.truncate(universe);
+        self.sparse.shrink_to_fit();
+        self.dense.shrink_to_fit();
+    }
+
+    // the position of id in dense, if it's in the set
+    #[inline]
+    fn slot(&self, id: u32) -> Option<usize> {
+        let slot = *self.sparse.get(id as usize)? as usize;
+        if slot < self.dense.len() && self.dense[slot] == id { Some(slot) } else { None }
+    }
+
+    // swap removes id from dense and returns the position it had
+    #[inline]
+    fn remove_slot(&mut self, id: u32) -> Option<usize> {
+        let slot = self.slot(id)?;
+        let last = self.dense.pop().unwrap();
+        if slot < self.dense.len() {
+            self.dense[slot] = last;
+            self.sparse[last as usize] = slot as u32;
+        }
+        Some(slot)
+    }
+}
+
+impl FromIterator<u32> for ThinSparseSet {
+    fn from_iter<I: IntoIterator<Item=u32>>(iter: I) -> ThinSparseSet {
+        let mut set = ThinSparseSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<u32> for ThinSparseSet {
+    fn extend<I: IntoIterator<Item=u32>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+
+impl<'a> Extend<&'a u32> for ThinSparseSet {
+    fn extend<I: IntoIterator<Item=&'a u32>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl PartialEq for ThinSparseSet {
+    /// Two sets are equal if they hold the same ids, in any order.
+    fn eq(&self, other: &ThinSparseSet) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl Eq for ThinSparseSet {}
+
+impl fmt::Debug for ThinSparseSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a ThinSparseSet {
+    type Item = &'a u32;
+    type IntoIter = slice::Iter<'a, u32>;
+
+    #[inline]
+    fn into_iter(self) -> slice::Iter<'a, u32> {
+        self.iter()
+    }
+}
+
+/// A map from `u32` ids to values with O(1) `insert`, `remove` and lookups.
+///
+/// The keys are a [`ThinSparseSet`] and the values sit in a `ThinVec<V>` parallel to its dense
+/// vector, so iteration is a walk over two slices. `clear` is O(1) when `V` doesn't need to be
+/// dropped. The key set operations (`union`, `intersection`, ...) are the set's.
+///
+/// [`ThinSparseSet`]: struct.ThinSparseSet.html
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_sparse_set::ThinSparseMap;
+///
+/// let mut speed = ThinSparseMap::new();
+/// speed.insert(4, 1.5);
+/// speed.insert(9, 0.5);
+/// assert_eq!(Some(1.5), speed.insert(4, 2.0));
+/// *speed.get_mut(9).unwrap() += 1.0;
+/// assert_eq!(speed.iter().collect::<Vec<_>>(), [(&4, &2.0), (&9, &1.5)]);
+///
+/// let mut moved = ThinSparseMap::new();
+/// moved.insert(9, ());
+/// assert_eq!(speed.difference(&moved).collect::<Vec<_>>(), [&4]);
+/// ```
+#[derive(Clone)]
+pub struct ThinSparseMap<V> {
+    keys: ThinSparseSet,
+    // values[i] belongs to keys.dense[i]
+    values: ThinVec<V>,
+}
+
+impl<V> ThinSparseMap<V> {
+    /// Constructs a new, empty `ThinSparseMap`. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinSparseMap<V> {
+        ThinSparseMap { keys: ThinSparseSet::new(), values: ThinVec::new() }
+    }
+
+    /// Constructs a new, empty `ThinSparseMap` that can hold `len` entries with keys below
+    /// `universe` without reallocating.
+    #[inline]
+    pub fn with_capacity(len: usize, universe: usize) -> ThinSparseMap<V> {
+        ThinSparseMap {
+            keys: ThinSparseSet::with_capacity(len, universe),
+            values: ThinVec::with_capacity(len),
+        }
+    }
+
+    /// Inserts a key-value pair. Returns the old value if the key was already there.
+    #[inline]
+    pub fn insert(&mut self, key: u32, value: V) -> Option<V> {
+        match self.keys.slot(key) {
+            Some(slot) => Some(std::mem::replace(&mut self.values[slot], value)),
+            None => {
+                self.keys.insert(key);
+                self.values.push(value);
+                None
+            }
+        }
+    }
+
+    /// Removes a key and returns its value, or `None` if it wasn't there.
+    ///
+    /// The last entry takes the place of the removed one, which changes the iteration order.
+    #[inline]
+    pub fn remove(&mut self, key: u32) -> Option<V> {
+        let slot = self.keys.remove_slot(key)?;
+        Some(self.values.swap_remove(slot))
+    }
+
+    /// Returns a reference to the value for `key`.
+    #[inline]
+    pub fn get(&self, key: u32) -> Option<&V> {
+        self.keys.slot(key).map(|slot| &self.values[slot])
+    }
+
+    /// Returns a mutable reference to the value for `key`.
+    #[inline]
+    pub fn get_mut(&mut self, key: u32) -> Option<&mut V> {
+        let slot = self.keys.slot(key)?;
+        Some(&mut self.values[slot])
+    }
+
+    /// Returns `true` if the map has a value for `key`.
+    #[inline]
+    pub fn contains_key(&self, key: u32) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Returns the number of entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the map is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all the entries. The keys are cleared in O(1), the values are dropped.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Returns the set of keys.
+    #[inline]
+    pub fn key_set(&self) -> &ThinSparseSet {
+        &self.keys
+    }
+
+    /// Returns an iterator over the keys, in the map's order.
+    #[inline]
+    pub fn keys(&self) -> slice::Iter<'_, u32> {
+        self.keys.iter()
+    }
+
+    /// Returns an iterator over the values, in the map's order.
+    #[inline]
+    pub fn values(&self) -> slice::Iter<'_, V> {
+        self.values.iter()
+    }
+
+    /// Returns a mutable iterator over the values, in the map's order.
+    #[inline]
+    pub fn values_mut(&mut self) -> slice::IterMut<'_, V> {
+        self.values.iter_mut()
+    }
+
+    /// Returns an iterator over the entries, in insertion order if nothing was removed.
+    #[inline]
+    pub fn iter(&self) -> Zip<slice::Iter<'_, u32>, slice::Iter<'_, V>> {
+        self.keys.iter().zip(self.values.iter())
+    }
+
+    /// Returns an iterator over the entries, with mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> Zip<slice::Iter<'_, u32>, slice::IterMut<'_, V>> {
+        self.keys.iter().zip(self.values.iter_mut())
+    }
+
+    /// Visits the keys that are in `self` but not in `other`.
+    pub fn difference<'a, W>(&'a self, other: &'a ThinSparseMap<W>) -> Difference<'a> {
+        self.keys.difference(&other.keys)
+    }
+
+    /// Visits the keys that are in `self` or in `other` but not in both.
+    pub fn symmetric_difference<'a, W>(&'a self,
+                                       other: &'a ThinSparseMap<W>)
+                                       -> SymmetricDifference<'a> {
+        self.keys.symmetric_difference(&other.keys)
+    }
+
+    /// Visits the keys that are both in `self` and `other`.
+    pub fn intersection<'a, W>(&'a self, other: &'a ThinSparseMap<W>) -> Intersection<'a> {
+        self.keys.intersection(&other.keys)
+    }
+
+    /// Visits the keys of `self`, then the keys of `other` that aren't in `self`.
+    pub fn union<'a, W>(&'a self, other: &'a ThinSparseMap<W>) -> Union<'a> {
+        self.keys.union(&other.keys)
+    }
+
+    /// Shrinks both vectors to fit the entries.
+    pub fn shrink_to_fit(&mut self) {
+        self.keys.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+}
+
+impl<V> Default for ThinSparseMap<V> {
+    #[inline]
+    fn default() -> ThinSparseMap<V> {
+        ThinSparseMap::new()
+    }
+}
+
+impl<V> FromIterator<(u32, V)> for ThinSparseMap<V> {
+    fn from_iter<I: IntoIterator<Item=(u32, V)>>(iter: I) -> ThinSparseMap<V> {
+        let mut map = ThinSparseMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V> Extend<(u32, V)> for ThinSparseMap<V> {
+    fn extend<I: IntoIterator<Item=(u32, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<V: PartialEq> PartialEq for ThinSparseMap<V> {
+    /// Two maps are equal if they hold the same entries, in any order.
+    fn eq(&self, other: &ThinSparseMap<V>) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(*k) == Some(v))
+    }
+}
+
+impl<V: Eq> Eq for ThinSparseMap<V> {}
+
+impl<V: fmt::Debug> fmt::Debug for ThinSparseMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a ThinSparseMap<V> {
+    type Item = (&'a u32, &'a V);
+    type IntoIter = Zip<slice::Iter<'a, u32>, slice::Iter<'a, V>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A lazy iterator producing ids in the intersection of `ThinSparseSet`s.
+///
+/// This `struct` is created by the [`intersection`] method on [`ThinSparseSet`].
+/// See its documentation for more.
+///
+/// [`ThinSparseSet`]: struct.ThinSparseSet.html
+/// [`intersection`]: struct.ThinSparseSet.html#method.intersection
+#[derive(Clone)]
+pub struct Intersection<'a> {
+    // iterator of the first set
+    iter: slice::Iter<'a, u32>,
+    // the second set
+    other: &'a ThinSparseSet,
+}
+
+/// A lazy iterator producing ids in the difference of `ThinSparseSet`s.
+///
+/// This `struct` is created by the [`difference`] method on [`ThinSparseSet`].
+/// See its documentation for more.
+///
+/// [`ThinSparseSet`]: struct.ThinSparseSet.html
+/// [`difference`]: struct.ThinSparseSet.html#method.difference
+#[derive(Clone)]
+pub struct Difference<'a> {
+    // iterator of the first set
+    iter: slice::Iter<'a, u32>,
+    // the second set
+    other: &'a ThinSparseSet,
+}
+
+/// A lazy iterator producing ids in the symmetric difference of `ThinSparseSet`s.
+///
+/// This `struct` is created by the [`symmetric_difference`] method on [`ThinSparseSet`].
+/// See its documentation for more.
+///
+/// [`ThinSparseSet`]: struct.ThinSparseSet.html
+/// [`symmetric_difference`]: struct.ThinSparseSet.html#method.symmetric_difference
+#[derive(Clone)]
+pub struct SymmetricDifference<'a> {
+    iter: Chain<Difference<'a>, Difference<'a>>,
+}
+
+/// A lazy iterator producing ids in the union of `ThinSparseSet`s.
+///
+/// This `struct` is created by the [`union`] method on [`ThinSparseSet`].
+/// See its documentation for more.
+///
+/// [`ThinSparseSet`]: struct.ThinSparseSet.html
+/// [`union`]: struct.ThinSparseSet.html#method.union
+#[derive(Clone)]
+pub struct Union<'a> {
+    iter: Chain<slice::Iter<'a, u32>, Difference<'a>>,
+}
+
+impl<'a> Iterator for Intersection<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<&'a u32> {
+        loop {
+            let id = self.iter.next()?;
+            if self.other.contains(*id) {
+                return Some(id);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.iter.size_hint();
+        (0, upper)
+    }
+}
+
+impl<'a> FusedIterator for Intersection<'a> {}
+
+impl<'a> Iterator for Difference<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<&'a u32> {
+        loop {
+            let id = self.iter.next()?;
+            if !self.other.contains(*id) {
+                return Some(id);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.iter.size_hint();
+        (0, upper)
+    }
+}
+
+impl<'a> FusedIterator for Difference<'a> {}
+
+impl<'a> Iterator for SymmetricDifference<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<&'a u32> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> FusedIterator for SymmetricDifference<'a> {}
+
+impl<'a> Iterator for Union<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<&'a u32> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> FusedIterator for Union<'a> {}
+
+macro_rules! impl_set_debug {
+    ($($name: ident),*) => {
+        $(
+        impl<'a> fmt::Debug for $name<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_list().entries(self.clone()).finish()
+            }
+        }
+        )*
+    }
+}
+
+impl_set_debug!(Intersection, Difference, SymmetricDifference, Union);
@@ -31,8 +31,12 @@ use std::ops::Index;
 use std::ops::IndexMut;
 use std::ops::RangeBounds;
 use std::ptr::NonNull;
+use std::rc::Rc;
 use std::slice;
 use std::slice::SliceIndex;
+use std::sync::Arc;
+
+use crate::thin_vec::{self, ThinVec};
 
 /// A thin (64bit) vector. Guaranteed to be a 64 bit smart pointer.
 ///
@@ -66,8 +70,8 @@ use std::slice::SliceIndex;
 ///
 /// `V64` also uses its smart pointer for storage of small values. Data that can be
 /// stored within seven bytes and the required alignment will not allocate any heap memory.
+/// This holds on both little and big endian targets, and on 32 bit targets.
 ///
-#[cfg(target_endian = "little")]
 pub struct V64<T> {
     u: NonZeroU64,
     _marker: marker::PhantomData<T>,
@@ -75,6 +79,17 @@ pub struct V64<T> {
 
 const ZST_MASK: u64 = 0x8000_0000_0000_0000u64;
 
+// The control nibble is the least significant nibble of the 64 bit word: zero for a (16 byte
+// aligned) heap pointer, 8 | len for stack mode. On little endian targets that byte comes first in
+// memory, so the stack elements start at the first aligned offset after it. On big endian targets
+// it's the last byte, so the elements start at offset 0 and stop before it.
+#[inline(always)]
+const fn stack_offset(little_endian: bool, align: usize) -> usize {
+    if little_endian { align } else { 0 }
+}
+
+const LITTLE_ENDIAN: bool = cfg!(target_endian = "little");
+
 pub enum Control {
     Heap(*mut u8),
     Stack(usize),
@@ -191,7 +206,10 @@ impl<T> V64<T> {
     pub fn reserve(&mut self, additional: usize) {
         if mem::size_of::<T>() == 0 { return; }
         let len = self.len();
-        self.reserve_exact(cmp::max(len * 2, len + additional));
+        let capacity = self.capacity();
+        if capacity - len >= additional { return; }
+        let required = len.checked_add(additional).expect("capacity overflow");
+        self.reserve_exact(thin_vec::grow_capacity(capacity, required) - len);
     }
 
     /// Reserves the minimum capacity for exactly `additional` more elements to
@@ -238,6 +256,9 @@ impl<T> V64<T> {
     /// It will drop down as close as possible to the length but the allocator
     /// may still inform the vector that there is space for a few more elements.
     ///
+    /// If the remaining elements fit in the 64 bit pointer, they are moved back
+    /// there and the heap memory is freed.
+    ///
     /// # Examples
     ///
     /// ```
@@ -251,14 +272,29 @@ impl<T> V64<T> {
     /// assert!(vec.capacity() < 10);
     /// assert!(vec.capacity() == 3);
     /// ```
+    ///
+    /// Moves back to the stack when possible:
+    /// ```
+    /// use thincollections::thin_v64::V64;
+    /// let mut vec: V64<u16> = V64::with_capacity(10);
+    /// vec.push(1);
+    /// vec.push(2);
+    /// assert!(vec.bytes_on_heap() > 0);
+    /// vec.shrink_to_fit();
+    /// assert_eq!(0, vec.bytes_on_heap());
+    /// assert_eq!(vec, [1, 2]);
+    /// ```
     pub fn shrink_to_fit(&mut self) {
         if mem::size_of::<T>() == 0 { return; }
         match self.control() {
             Control::Heap(ptr) => {
-                //todo: implement shrinking back to stack mode.
                 unsafe {
                     let len_ptr = ptr as *mut usize;
-                    self.realloc_heap(len_ptr, *len_ptr);
+                    if *len_ptr <= <V64<T>>::stack_capacity() {
+                        self.move_to_stack(ptr);
+                    } else {
+                        self.realloc_heap(len_ptr, *len_ptr);
+                    }
                 }
             }
             Control::Stack(_) => {}
@@ -369,17 +405,14 @@ impl<T> V64<T> {
             }
             Control::Stack(stack_len) => {
                 unsafe {
-                    if stack_len > len {
-                        let mut cur = self.stack_ptr().add(len);
-                        let end = cur.add(stack_len - len);
-                        let mut cur_len = stack_len;
-                        while cur < end {
-                            cur_len -= 1;
-                            self.set_stack_len(cur_len);
-                            ptr::drop_in_place(cur);
-                            ptr::write(cur, mem::zeroed::<T>()); // we have to do this even if T doesn't require drop
-                            cur = cur.add(1);
-                        }
+                    let mut cur_len = stack_len;
+                    for i in len..stack_len {
+                        cur_len -= 1;
+                        self.set_stack_len(cur_len);
+                        // set_stack_len reborrows self, so the pointer is taken after it
+                        let cur = self.stack_ptr().add(i);
+                        ptr::drop_in_place(cur);
+                        ptr::write(cur, mem::zeroed::<T>()); // we have to do this even if T doesn't require drop
                     }
                 }
             }
@@ -481,6 +514,44 @@ impl<T> V64<T> {
         self
     }
 
+    /// Returns a raw pointer to the vector's elements, either in the heap block or in the pointer.
+    ///
+    /// Unlike `as_slice().as_ptr()`, the pointer is valid for the whole capacity.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        unsafe {
+            if mem::size_of::<T>() == 0 { return NonNull::dangling().as_ptr(); }
+            match self.control() {
+                Control::Heap(ptr) => {
+                    let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                    ptr.add(header_bytes) as *const T
+                }
+                Control::Stack(_) if mem::size_of::<T>() > 7 => NonNull::dangling().as_ptr(),
+                Control::Stack(_) => {
+                    ((&self.u) as *const _ as *const u8).add(stack_offset(LITTLE_ENDIAN, mem::align_of::<T>())) as *const T
+                }
+            }
+        }
+    }
+
+    /// Returns an unsafe mutable pointer to the vector's elements, either in the heap block or
+    /// in the pointer.
+    ///
+    /// Unlike `as_mut_slice().as_mut_ptr()`, the pointer is valid for the whole capacity.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        unsafe {
+            if mem::size_of::<T>() == 0 { return NonNull::dangling().as_ptr(); }
+            match self.control() {
+                Control::Heap(ptr) => {
+                    let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                    ptr.add(header_bytes) as *mut T
+                }
+                Control::Stack(_) => self.stack_ptr(),
+            }
+        }
+    }
+
     /// Removes an element from the vector and returns it.
     ///
     /// The removed element is replaced by the last element of the vector.
@@ -715,8 +786,8 @@ impl<T> V64<T> {
                 }
                 Control::Stack(stack_len) => {
                     len = stack_len;
-                    array = self.stack_ptr();
                     self.set_stack_len(stack_len - 1);
+                    array = self.stack_ptr();
                 }
             }
         }
@@ -1147,8 +1218,8 @@ impl<T> V64<T> {
                 Control::Stack(stack_len) => {
                     if stack_len == 0 { return None; }
                     len = stack_len;
-                    array = self.stack_ptr();
                     self.set_stack_len(stack_len - 1);
+                    array = self.stack_ptr();
                 }
             }
         }
@@ -1239,12 +1310,12 @@ impl<T> V64<T> {
         if count > 0 {
             self.reserve(count);
             let len = self.len();
-            ptr::copy_nonoverlapping(other as *const T, self.get_unchecked_mut(len), count);
+            ptr::copy_nonoverlapping(other as *const T, self.array_ptr().add(len), count);
             self.set_len(len + count);
         }
     }
 
-    unsafe fn set_len(&mut self, len: usize) {
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
         if mem::size_of::<T>() == 0 {
             self.u = NonZeroU64::new_unchecked(len as u64 | ZST_MASK);
             return;
@@ -1376,38 +1447,8 @@ impl<T> V64<T> {
     /// assert_eq!(slice.into_v64().capacity(), 3);
     /// # }
     /// ```
-    pub fn into_boxed_slice(mut self) -> Box<[T]> {
-        if mem::size_of::<T>() == 0 {
-            unsafe {
-                let slice = slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), self.len());
-                let output: Box<[T]> = Box::from_raw(slice);
-                return output;
-            }
-        }
-        unsafe {
-            let array: *mut T;
-            let len: usize;
-            match self.control() {
-                Control::Heap(ptr) => {
-                    let len_ptr = ptr as *mut usize;
-                    let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
-                    array = ptr.add(header_bytes) as *mut T;
-                    len = *len_ptr;
-                    *len_ptr = 0; // prevents dropping of contents
-                }
-                Control::Stack(stack_len) => {
-                    array = self.stack_ptr();
-                    len = stack_len;
-                    self.set_stack_len(0); //prevents dropping of contents
-                }
-            }
-            let layout = Layout::from_size_align(mem::size_of::<T>() * len, mem::align_of::<T>()).unwrap();
-            let buffer = alloc::alloc(layout) as *mut T;
-            ptr::copy_nonoverlapping(array, buffer, len);
-            let slice = slice::from_raw_parts_mut(buffer, len);
-            let output: Box<[T]> = Box::from_raw(slice);
-            output
-        }
+    pub fn into_boxed_slice(self) -> Box<[T]> {
+        Vec::from(self).into_boxed_slice()
     }
 
     /// Creates a draining iterator that removes the specified range in the vector
@@ -1488,15 +1529,15 @@ impl<T> V64<T> {
         unsafe {
             // set self.vec length's to start, to be safe in case Drain is leaked
             self.set_len(start);
-            // Use the borrow in the IterMut to indicate borrowing behavior of the
-            // whole Drain iterator (like &mut T).
-            let range_slice = slice::from_raw_parts_mut(self.as_mut_ptr().add(start),
-                                                        end - start);
+            // the slice is derived from the NonNull, as moving `self` into it afterwards would
+            // invalidate a borrow of the inline buffer
+            let vec = NonNull::from(self);
+            let range_slice = slice::from_raw_parts(vec.as_ref().as_ptr().add(start), end - start);
             Drain {
                 tail_start: end,
                 tail_len: len - end,
                 iter: range_slice.iter(),
-                vec: NonNull::from(self),
+                vec,
             }
         }
     }
@@ -1648,6 +1689,24 @@ impl<T> V64<T> {
             }
             return;
         }
+        // Reserve for the lower size hint up front (exact for slices, vectors and most adapters),
+        // then fill that capacity without checking it on every element.
+        let (lower, _) = iterator.size_hint();
+        self.reserve(lower);
+        {
+            let capacity = self.capacity();
+            let mut guard = SetLenOnDrop { local_len: self.len(), vec: self };
+            let dst = guard.vec.array_ptr();
+            while guard.local_len < capacity {
+                match iterator.next() {
+                    Some(element) => unsafe {
+                        ptr::write(dst.add(guard.local_len), element);
+                        guard.local_len += 1;
+                    },
+                    None => return,
+                }
+            }
+        }
         while let Some(element) = iterator.next() {
             let len = self.len();
             if len == self.capacity() {
@@ -1655,17 +1714,34 @@ impl<T> V64<T> {
                 self.reserve(lower.saturating_add(1));
             }
             unsafe {
-                ptr::write(self.get_unchecked_mut(len), element);
+                ptr::write(self.array_ptr().add(len), element);
                 // NB can't overflow since we would have had to alloc the address space
                 self.set_len(len + 1);
             }
         }
     }
 
+    // the first element slot; unlike going through a slice, this can be used to write past len
+    #[inline(always)]
+    fn array_ptr(&mut self) -> *mut T {
+        if mem::size_of::<T>() == 0 {
+            return NonNull::dangling().as_ptr();
+        }
+        match self.control() {
+            Control::Heap(ptr) => unsafe {
+                let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                ptr.add(header_bytes) as *mut T
+            },
+            Control::Stack(_) => self.stack_ptr(),
+        }
+    }
+
     #[inline(always)]
     fn stack_ptr(&mut self) -> *mut T {
+        // types of 8 bytes or more never live in the pointer, and their offset would be past it
+        if mem::size_of::<T>() > 7 { return NonNull::dangling().as_ptr(); }
         unsafe {
-            ((&mut self.u) as *mut _ as *mut u8).add(mem::align_of::<T>()) as *mut T
+            ((&mut self.u) as *mut _ as *mut u8).add(stack_offset(LITTLE_ENDIAN, mem::align_of::<T>())) as *mut T
         }
     }
     #[inline(always)]
@@ -1685,13 +1761,11 @@ impl<T> V64<T> {
     #[cold]
     fn allocate_array(capacity: usize) -> *mut u8 {
         unsafe {
-            // align_of is a power of 2. 2 * size_of::<usize> is a power of 2.
-            // if align_of is smaller than 2*size_of::<usize>, we'll have no padding between the header and array
-            // if align_of is bigger than 2*size_of::<usize>, we'll use the first align for the header
-            let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
-            let align = cmp::max(16, mem::align_of::<T>());
-            let layout = Layout::from_size_align(mem::size_of::<T>() * capacity + header_bytes, align).unwrap();
+            let layout = <V64<T>>::block_layout(capacity);
             let buffer = alloc::alloc(layout);
+            if buffer.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
             assert_eq!(buffer as usize & 15, 0); // check the allocator respects our assumptions
             ptr::write(buffer as *mut usize, 0); // current length
             ptr::write((buffer as *mut usize).add(1), capacity);
@@ -1705,26 +1779,44 @@ impl<T> V64<T> {
             let len_ptr = arr as *mut usize;
             let cap_ptr = len_ptr.add(1);
             if *len_ptr == *cap_ptr {
-                self.realloc_heap(len_ptr, *len_ptr * 2);
+                self.realloc_heap(len_ptr, thin_vec::grow_capacity(*cap_ptr, *cap_ptr + 1));
             }
         }
     }
 
+    #[inline(always)]
+    fn block_layout(capacity: usize) -> Layout {
+        // align_of is a power of 2. 2 * size_of::<usize> is a power of 2.
+        // if align_of is smaller than 2*size_of::<usize>, we'll have no padding between the header and array
+        // if align_of is bigger than 2*size_of::<usize>, we'll use the first align for the header
+        let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+        let align = cmp::max(16, mem::align_of::<T>());
+        let size = capacity.checked_mul(mem::size_of::<T>())
+            .and_then(|x| x.checked_add(header_bytes))
+            .expect("capacity overflow");
+        Layout::from_size_align(size, align).unwrap()
+    }
+
     #[inline(always)]
     fn realloc_heap(&mut self, len_ptr: *mut usize, new_capacity: usize) {
         unsafe {
-            //realloc seems to bench slower...
-            let head_ptr = <V64<T>>::allocate_array(new_capacity);
-            let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
-            let to_move = cmp::min(new_capacity, *(len_ptr.add(1)));
-            ptr::copy_nonoverlapping(len_ptr as *mut u8, head_ptr, to_move * mem::size_of::<T>() + header_bytes);
-            let new_cap_ptr = (head_ptr as *mut usize).add(1);
-            *new_cap_ptr = new_capacity;
+            let old_layout = <V64<T>>::block_layout(*len_ptr.add(1));
+            let new_layout = <V64<T>>::block_layout(new_capacity);
+            let head_ptr: *mut u8;
+            if cmp::max(old_layout.size(), new_layout.size()) >= thin_vec::REALLOC_THRESHOLD {
+                head_ptr = alloc::realloc(len_ptr as *mut u8, old_layout, new_layout.size());
+                if head_ptr.is_null() {
+                    alloc::handle_alloc_error(new_layout);
+                }
+            } else {
+                head_ptr = <V64<T>>::allocate_array(new_capacity);
+                let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                let to_move = cmp::min(new_capacity, *len_ptr);
+                ptr::copy_nonoverlapping(len_ptr as *mut u8, head_ptr, to_move * mem::size_of::<T>() + header_bytes);
+                alloc::dealloc(len_ptr as *mut u8, old_layout);
+            }
+            *(head_ptr as *mut usize).add(1) = new_capacity;
             self.u = NonZeroU64::new_unchecked(head_ptr as u64);
-            let old = len_ptr as *mut u8;
-            let align = cmp::max(16, mem::align_of::<T>());
-            let layout = Layout::from_size_align(mem::size_of::<T>() * (*(len_ptr.add(1))) + header_bytes, align).unwrap();
-            alloc::dealloc(old, layout);
         }
     }
 
@@ -1741,6 +1833,32 @@ impl<T> V64<T> {
         }
     }
 
+    // the heap length must fit in stack_capacity
+    #[cold]
+    fn move_to_stack(&mut self, ptr: *mut u8) {
+        unsafe {
+            let len_ptr = ptr as *mut usize;
+            let len = *len_ptr;
+            let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+            let arr = ptr.add(header_bytes) as *mut T;
+            let heap_capacity = *len_ptr.add(1);
+            // unused stack bytes must stay zero
+            self.u = NonZeroU64::new_unchecked(8);
+            if len > 0 {
+                ptr::copy_nonoverlapping(arr, self.stack_ptr(), len);
+                self.set_stack_len(len);
+            }
+            let align = cmp::max(16, mem::align_of::<T>());
+            let layout = Layout::from_size_align(mem::size_of::<T>() * heap_capacity + header_bytes, align).unwrap();
+            alloc::dealloc(ptr, layout);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn stack_capacity() -> usize {
+        7 / mem::size_of::<T>()
+    }
+
     #[inline]
     fn heap_push(&mut self, val: T) {
         unsafe {
@@ -1771,17 +1889,69 @@ impl<T: Clone> V64<T> {
         v64
     }
 
+    /// Clones and appends all the elements of a slice to the vector.
+    ///
+    /// The space is reserved once. The length is only written back at the end (or if a clone
+    /// panics), which lets the loop compile down to a `memcpy` for `Copy` types.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_v64::V64;
+    /// # fn main() {
+    /// let mut vec = v64![1];
+    /// vec.extend_from_slice(&[2, 3, 4]);
+    /// assert_eq!(vec, [1, 2, 3, 4]);
+    /// # }
+    /// ```
     pub fn extend_from_slice(&mut self, slice: &[T]) {
         self.reserve(slice.len());
-        unsafe {
-            let mut len = self.len();
-            let mut dst = self.get_unchecked_mut(len) as *mut T;
-            for t in slice.iter() {
-                ptr::write(dst, t.clone());
-                dst = dst.add(1);
-                len += 1;
-                self.set_len(len); // we set len here in the loop in case clone breaks.
-            }
+        let mut guard = SetLenOnDrop { local_len: self.len(), vec: self };
+        let dst = guard.vec.array_ptr();
+        for t in slice.iter() {
+            unsafe { ptr::write(dst.add(guard.local_len), t.clone()); }
+            guard.local_len += 1;
+        }
+    }
+
+    /// Clones the elements in the `src` range and appends them to the end of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, or if the end is
+    /// greater than the length of the vector.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_v64::V64;
+    /// # fn main() {
+    /// let mut vec = v64![0u8, 1, 2];
+    /// vec.extend_from_within(1..);
+    /// assert_eq!(vec, [0, 1, 2, 1, 2]);
+    /// vec.extend_from_within(..=3);
+    /// assert_eq!(vec, [0, 1, 2, 1, 2, 0, 1, 2, 1]);
+    /// # }
+    /// ```
+    pub fn extend_from_within<R>(&mut self, src: R) where R: RangeBounds<usize> {
+        let len = self.len();
+        let start = match src.start_bound() {
+            Included(&n) => n,
+            Excluded(&n) => n + 1,
+            Unbounded => 0,
+        };
+        let end = match src.end_bound() {
+            Included(&n) => n + 1,
+            Excluded(&n) => n,
+            Unbounded => len,
+        };
+        assert!(start <= end);
+        assert!(end <= len);
+
+        self.reserve(end - start);
+        let mut guard = SetLenOnDrop { local_len: len, vec: self };
+        let array = guard.vec.array_ptr();
+        for i in start..end {
+            unsafe { ptr::write(array.add(guard.local_len), (*array.add(i)).clone()); }
+            guard.local_len += 1;
         }
     }
 }
@@ -1890,6 +2060,22 @@ impl<T> V64<T> {
     }
 }
 
+impl V64<u8> {
+    /// Builds a stack mode `V64<u8>` in a `const` context, such as the `V64Str` sentinels.
+    pub(crate) const fn from_stack_bytes(bytes: &[u8]) -> V64<u8> {
+        assert!(bytes.len() < 8);
+        let mut word = [0u8; 8];
+        let offset = stack_offset(LITTLE_ENDIAN, 1);
+        let mut i = 0;
+        while i < bytes.len() {
+            word[offset + i] = bytes[i];
+            i += 1;
+        }
+        let u = u64::from_ne_bytes(word) | 8 | bytes.len() as u64;
+        unsafe { V64 { u: NonZeroU64::new_unchecked(u), _marker: marker::PhantomData } }
+    }
+}
+
 impl<T, I> Index<I> for V64<T>
     where
         I: SliceIndex<[T]>,
@@ -1925,8 +2111,9 @@ impl<T> Deref for V64<T> {
                     let arr = (len_ptr as *mut u8).add(header_bytes) as *mut T;
                     slice::from_raw_parts(arr, *len_ptr)
                 }
+                Control::Stack(len) if mem::size_of::<T>() > 7 => slice::from_raw_parts(NonNull::dangling().as_ptr(), len),
                 Control::Stack(len) => {
-                    let arr = ((&self.u) as *const _ as *const u8).add(mem::align_of::<T>()) as *const T;
+                    let arr = ((&self.u) as *const _ as *const u8).add(stack_offset(LITTLE_ENDIAN, mem::align_of::<T>())) as *const T;
                     slice::from_raw_parts(arr, len)
                 }
             }
@@ -2006,12 +2193,14 @@ impl<T: fmt::Debug> fmt::Debug for V64<T> {
 ///
 /// [`V64`]: struct.V64.html
 /// [`IntoIterator`]: ../../std/iter/trait.IntoIterator.html
+///
+/// Iterating an inline vector doesn't allocate: the iterator keeps a copy of the 64 bit word.
 pub struct IntoIter<T> {
     buf: *mut u8,
+    word: u64,
+    start: usize,
+    end: usize,
     _marker: marker::PhantomData<T>,
-    ptr: *const T,
-    end: *const T,
-    is_heap: bool,
 }
 
 impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
@@ -2057,48 +2246,22 @@ impl<T> IntoIterator for V64<T> {
     /// # }
     /// ```
     #[inline]
-    fn into_iter(mut self) -> IntoIter<T> {
-        unsafe {
-            if mem::size_of::<T>() == 0 {
-                let ptr = self.as_mut_ptr();
-                let end = (ptr as *const u8).add(self.len()) as *const T;
-                mem::forget(self);
-                return IntoIter {
-                    buf: ptr::null_mut(),
-                    _marker: marker::PhantomData,
-                    ptr,
-                    end,
-                    is_heap: false,
-                };
-            }
-            if self.u.get() & 8 == 8 {
-                let len = (self.u.get() & 7) as usize;
-                if len == 0 {
-                    return IntoIter {
-                        buf: ptr::null_mut(),
-                        _marker: marker::PhantomData,
-                        ptr: mem::align_of::<T>() as *mut T,
-                        end: mem::align_of::<T>() as *mut T,
-                        is_heap: false,
-                    };
-                }
-                self.move_to_heap(len, 0);
-            }
-            let len_ptr = self.u.get() as usize as *mut u8 as *mut usize;
-            let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
-            let begin = (len_ptr as *mut u8).add(header_bytes) as *mut T;
-            let end = begin.add(*len_ptr) as *const T;
-
-            let buf = len_ptr as *mut u8;
-            mem::forget(self);
-            IntoIter {
-                buf,
-                _marker: marker::PhantomData,
-                ptr: begin,
-                end,
-                is_heap: true,
+    fn into_iter(self) -> IntoIter<T> {
+        let mut iter = IntoIter {
+            buf: ptr::null_mut(),
+            word: 0,
+            start: 0,
+            end: self.len(),
+            _marker: marker::PhantomData,
+        };
+        if mem::size_of::<T>() != 0 {
+            match self.control() {
+                Control::Heap(ptr) => iter.buf = ptr,
+                Control::Stack(_) => iter.word = self.u.get(),
             }
         }
+        mem::forget(self);
+        iter
     }
 }
 
@@ -2138,7 +2301,8 @@ impl<T> IntoIter<T> {
     /// ```
     pub fn as_slice(&self) -> &[T] {
         unsafe {
-            slice::from_raw_parts(self.ptr, self.len())
+            let array = <IntoIter<T>>::array(self.buf, &self.word as *const u64 as *mut u8);
+            slice::from_raw_parts(array.add(self.start), self.len())
         }
     }
 
@@ -2161,7 +2325,23 @@ impl<T> IntoIter<T> {
     /// ```
     pub fn as_mut_slice(&mut self) -> &mut [T] {
         unsafe {
-            slice::from_raw_parts_mut(self.ptr as *mut T, self.len())
+            let array = <IntoIter<T>>::array(self.buf, &mut self.word as *mut u64 as *mut u8);
+            slice::from_raw_parts_mut(array.add(self.start), self.len())
+        }
+    }
+
+    // the elements are either in the heap block or in the copied word, at the same offset as in V64
+    #[inline(always)]
+    fn array(buf: *mut u8, word: *mut u8) -> *mut T {
+        unsafe {
+            if !buf.is_null() {
+                let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                buf.add(header_bytes) as *mut T
+            } else if mem::size_of::<T>() == 0 || mem::size_of::<T>() > 7 {
+                NonNull::dangling().as_ptr()
+            } else {
+                word.add(stack_offset(LITTLE_ENDIAN, mem::align_of::<T>())) as *mut T
+            }
         }
     }
 }
@@ -2175,32 +2355,21 @@ impl<T> Iterator for IntoIter<T> {
 
     #[inline]
     fn next(&mut self) -> Option<T> {
-        unsafe {
-            if self.ptr as *const _ == self.end {
-                None
-            } else if mem::size_of::<T>() == 0 {
-                // cast to u8, so we add 1, not zero
-                self.ptr = (self.ptr as *mut u8).add(1) as *mut T;
-
-                // Use a non-null pointer value
-                // (self.ptr might be null because of wrapping)
-                Some(ptr::read(NonNull::dangling().as_ptr()))
-            } else {
-                let old = self.ptr;
-                self.ptr = self.ptr.offset(1);
-
-                Some(ptr::read(old))
+        if self.start == self.end {
+            None
+        } else {
+            unsafe {
+                let array = <IntoIter<T>>::array(self.buf, &mut self.word as *mut u64 as *mut u8);
+                let old = self.start;
+                self.start += 1;
+                Some(ptr::read(array.add(old)))
             }
         }
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let exact = if mem::size_of::<T>() == 0 {
-            (self.end as usize).wrapping_sub(self.ptr as usize)
-        } else {
-            (self.end as usize).wrapping_sub(self.ptr as usize) / mem::size_of::<T>()
-        };
+        let exact = self.end - self.start;
         (exact, Some(exact))
     }
 
@@ -2213,19 +2382,13 @@ impl<T> Iterator for IntoIter<T> {
 impl<T> DoubleEndedIterator for IntoIter<T> {
     #[inline]
     fn next_back(&mut self) -> Option<T> {
-        unsafe {
-            if self.end == self.ptr {
-                None
-            } else if mem::size_of::<T>() == 0 {
-                self.end = (self.end as *const i8).sub(1) as *mut T;
-
-                // Use a non-null pointer value
-                // (self.end might be null because of wrapping)
-                Some(ptr::read(NonNull::dangling().as_ptr()))
-            } else {
-                self.end = self.end.offset(-1);
-
-                Some(ptr::read(self.end))
+        if self.start == self.end {
+            None
+        } else {
+            unsafe {
+                let array = <IntoIter<T>>::array(self.buf, &mut self.word as *mut u64 as *mut u8);
+                self.end -= 1;
+                Some(ptr::read(array.add(self.end)))
             }
         }
     }
@@ -2259,15 +2422,7 @@ impl<T: Clone> ToV64<T> for [T] {
 
 impl<T> IntoV64<T> for Box<[T]> {
     fn into_v64(self) -> V64<T> {
-        unsafe {
-            let len = self.len();
-            let ptr = self.as_ptr();
-            let mut vec = V64::with_capacity(len);
-            ptr::copy(ptr, vec.as_mut_ptr(), len);
-            vec.set_len(len);
-            mem::forget(self); // prevent it from dropping the contents
-            vec
-        }
+        V64::from(self.into_vec())
     }
 }
 
@@ -2278,13 +2433,13 @@ impl<T> Drop for IntoIter<T> {
         for _x in self.by_ref() {}
 
         unsafe {
-            if self.is_heap {
+            if !self.buf.is_null() {
                 let len_ptr = self.buf as *mut usize;
                 let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
                 let align = cmp::max(16, mem::align_of::<T>());
                 let layout = Layout::from_size_align(mem::size_of::<T>() * (*(len_ptr.add(1))) + header_bytes, align).unwrap();
                 alloc::dealloc(self.buf, layout);
-                self.is_heap = false;
+                self.buf = ptr::null_mut();
             }
         }
     }
@@ -2304,6 +2459,53 @@ impl<'a, T: 'a + Copy> Extend<&'a T> for V64<T> {
     }
 }
 
+impl<T> Extend<V64<T>> for V64<T> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=V64<T>>>(&mut self, iter: I) {
+        for mut v in iter {
+            self.append(&mut v);
+        }
+    }
+}
+
+impl<T> Extend<ThinVec<T>> for V64<T> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=ThinVec<T>>>(&mut self, iter: I) {
+        for mut v in iter {
+            unsafe {
+                self.append_elements(v.as_slice() as _);
+                v.set_len(0);
+            }
+        }
+    }
+}
+
+impl<T> Extend<Vec<T>> for V64<T> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=Vec<T>>>(&mut self, iter: I) {
+        for mut v in iter {
+            unsafe {
+                self.append_elements(v.as_slice() as _);
+                v.set_len(0);
+            }
+        }
+    }
+}
+
+// Writes the length back when it goes out of scope, so a panic part way through
+// filling the vector leaves it holding exactly the elements written so far.
+struct SetLenOnDrop<'a, T> {
+    vec: &'a mut V64<T>,
+    local_len: usize,
+}
+
+impl<'a, T> Drop for SetLenOnDrop<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { self.vec.set_len(self.local_len); }
+    }
+}
+
 impl<T> Default for V64<T> {
     /// Creates an empty `V64<T>`.
     fn default() -> V64<T> {
@@ -2329,6 +2531,95 @@ impl<T> From<Box<[T]>> for V64<T> {
     }
 }
 
+impl<T> From<Vec<T>> for V64<T> {
+    /// Moves the elements without cloning them. A `Vec` that fits is stored inline.
+    /// When `T` is aligned to 16 bytes or more, the `Vec`'s allocation is reused.
+    fn from(mut v: Vec<T>) -> V64<T> {
+        unsafe {
+            let len = v.len();
+            if mem::size_of::<T>() == 0 {
+                v.set_len(0);
+                let mut out = V64::new();
+                out.set_len(len);
+                return out;
+            }
+            if len == 0 {
+                return V64::new();
+            }
+            if mem::align_of::<T>() >= 16 {
+                let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                let block = thin_vec::vec_into_block::<T, usize>(v, header_bytes);
+                return V64 { u: NonZeroU64::new_unchecked(block as u64), _marker: marker::PhantomData };
+            }
+            let mut out = V64::with_capacity(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), out.array_ptr(), len);
+            v.set_len(0);
+            out.set_len(len);
+            out
+        }
+    }
+}
+
+impl<T> From<V64<T>> for Vec<T> {
+    /// Moves the elements without cloning them. When `T` is aligned to 16 bytes or more,
+    /// the heap block is reused.
+    fn from(mut v: V64<T>) -> Vec<T> {
+        unsafe {
+            let len = v.len();
+            if mem::size_of::<T>() == 0 {
+                // zero sized values have no bytes, so reading them from a dangling pointer
+                // hands the len values over to the Vec without allocating
+                v.set_len(0);
+                return (0..len).map(|_| ptr::read(NonNull::dangling().as_ptr())).collect();
+            }
+            if mem::align_of::<T>() >= 16 {
+                if let Control::Heap(ptr) = v.control() {
+                    mem::forget(v);
+                    let header_bytes = cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>());
+                    return thin_vec::block_into_vec::<T, usize>(ptr, header_bytes);
+                }
+            }
+            let mut out = Vec::with_capacity(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), out.as_mut_ptr(), len);
+            v.set_len(0);
+            out.set_len(len);
+            out
+        }
+    }
+}
+
+impl<T> From<V64<T>> for Box<[T]> {
+    fn from(v: V64<T>) -> Box<[T]> {
+        v.into_boxed_slice()
+    }
+}
+
+impl<T> From<V64<T>> for Rc<[T]> {
+    /// Moves the elements into the new `Rc` with a single copy.
+    fn from(mut v: V64<T>) -> Rc<[T]> {
+        unsafe {
+            let len = v.len();
+            let mut rc = Rc::<[T]>::new_uninit_slice(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), Rc::get_mut(&mut rc).unwrap().as_mut_ptr() as *mut T, len);
+            v.set_len(0);
+            rc.assume_init()
+        }
+    }
+}
+
+impl<T> From<V64<T>> for Arc<[T]> {
+    /// Moves the elements into the new `Arc` with a single copy.
+    fn from(mut v: V64<T>) -> Arc<[T]> {
+        unsafe {
+            let len = v.len();
+            let mut arc = Arc::<[T]>::new_uninit_slice(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), Arc::get_mut(&mut arc).unwrap().as_mut_ptr() as *mut T, len);
+            v.set_len(0);
+            arc.assume_init()
+        }
+    }
+}
+
 impl<'a, T> From<Cow<'a, [T]>> for V64<T> where [T]: ToOwned<Owned=V64<T>> {
     fn from(s: Cow<'a, [T]>) -> V64<T> {
         s.into_owned()
@@ -2412,9 +2703,8 @@ impl<'a, T> Drop for Drain<'a, T> {
                 let start = source_vec.len();
                 let tail = self.tail_start;
                 if tail != start {
-                    let src = source_vec.as_ptr().add(tail);
-                    let dst = source_vec.as_mut_ptr().add(start);
-                    ptr::copy(src, dst, self.tail_len);
+                    let p = source_vec.as_mut_ptr();
+                    ptr::copy(p.add(tail), p.add(start), self.tail_len);
                 }
                 source_vec.set_len(start + self.tail_len);
             }
@@ -2511,16 +2801,11 @@ impl<'a, T> Drain<'a, T> {
         let vec = self.vec.as_mut();
         let range_start = vec.len();
         let range_end = self.tail_start;
-        let range_slice = slice::from_raw_parts_mut(
-            vec.as_mut_ptr().add(range_start),
-            range_end - range_start);
-
-        let mut count = range_start;
-        for place in range_slice {
+        for i in range_start..range_end {
             if let Some(new_item) = replace_with.next() {
-                ptr::write(place, new_item);
-                count += 1;
-                vec.set_len(count);
+                // take the pointer after each set_len, which reborrows the vector
+                ptr::write(vec.as_mut_ptr().add(i), new_item);
+                vec.set_len(i + 1);
             } else {
                 return false;
             }
@@ -2531,12 +2816,18 @@ impl<'a, T> Drain<'a, T> {
     /// Make room for inserting more elements before the tail.
     unsafe fn move_tail(&mut self, extra_capacity: usize) {
         let vec = self.vec.as_mut();
-        vec.reserve(extra_capacity);
+        // reserve only moves the first len() elements, so count the tail in while it runs
+        let len = vec.len();
+        vec.set_len(self.tail_start + self.tail_len);
+        {
+            let guard = SetLenOnDrop { vec, local_len: len };
+            guard.vec.reserve(extra_capacity);
+        }
+        let vec = self.vec.as_mut();
 
         let new_tail_start = self.tail_start + extra_capacity;
-        let src = vec.as_ptr().add(self.tail_start);
-        let dst = vec.as_mut_ptr().add(new_tail_start);
-        ptr::copy(src, dst, self.tail_len);
+        let p = vec.as_mut_ptr();
+        ptr::copy(p.add(self.tail_start), p.add(new_tail_start), self.tail_len);
         self.tail_start = new_tail_start;
     }
 }
@@ -2680,4 +2971,146 @@ impl<T> BorrowMut<[T]> for V64<T> {
     fn borrow_mut(&mut self) -> &mut [T] {
         &mut self[..]
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test_v64 {
+    use std::fmt::Debug;
+    use std::mem;
+    use std::slice;
+
+    use super::{stack_offset, LITTLE_ENDIAN};
+    use super::V64;
+
+    // Lays out `len` elements of `size`/`align` the way V64 does on a target with the given byte
+    // order, and checks that the control nibble, read back as a number, isn't touched by the data.
+    fn check_layout(little_endian: bool, size: usize, align: usize) {
+        let capacity = 7 / size;
+        let offset = stack_offset(little_endian, align);
+        let control_byte = if little_endian { 0 } else { 7 };
+        assert_eq!(0, offset % align);
+        assert!(offset + capacity * size <= 8);
+        for len in 0..=capacity {
+            let mut bytes = [0u8; 8];
+            for b in offset..offset + len * size {
+                assert_ne!(control_byte, b);
+                bytes[b] = 0xFF;
+            }
+            bytes[control_byte] = 8 | len as u8;
+            let word = if little_endian { u64::from_le_bytes(bytes) } else { u64::from_be_bytes(bytes) };
+            assert_eq!(8 | len as u64, word & 15);
+        }
+    }
+
+    #[test]
+    fn test_stack_layout_both_byte_orders() {
+        for &little_endian in &[true, false] {
+            for &(size, align) in &[(1, 1), (2, 1), (2, 2), (3, 1), (4, 2), (4, 4), (6, 2), (7, 1)] {
+                check_layout(little_endian, size, align);
+            }
+        }
+    }
+
+    // The tests below check the layout of the target they run on. To run them on a big endian
+    // target: `cargo miri test --target s390x-unknown-linux-gnu --lib test_v64`.
+
+    // Checks a stack mode vector through the bytes of its word as they are in memory: the control
+    // nibble is in the low nibble of the first byte on little endian targets and of the last byte on
+    // big endian ones, and the elements keep their native bytes from `stack_offset` on.
+    fn check_stack<T: Copy + PartialEq + Debug>(v: &V64<T>, expected: &[T]) {
+        assert_eq!(expected, &v[..]);
+        assert_eq!(0, v.bytes_on_heap());
+        let bytes = v.u.get().to_ne_bytes();
+        let control_byte = if LITTLE_ENDIAN { 0 } else { 7 };
+        assert_eq!(8 | expected.len() as u8, bytes[control_byte] & 15);
+        let size = mem::size_of::<T>();
+        let offset = stack_offset(LITTLE_ENDIAN, mem::align_of::<T>());
+        assert_eq!(offset, v.as_ptr() as usize - v as *const V64<T> as usize);
+        for (i, x) in expected.iter().enumerate() {
+            let elem = unsafe { slice::from_raw_parts(x as *const T as *const u8, size) };
+            assert_eq!(elem, &bytes[offset + i * size..offset + (i + 1) * size]);
+        }
+    }
+
+    // Runs the stack mode operations over `vals`, which has one more element than fits inline,
+    // checking the word after each one against a Vec.
+    fn exercise_stack<T: Copy + PartialEq + Debug>(vals: &[T]) {
+        let capacity = 7 / mem::size_of::<T>();
+        assert_eq!(capacity + 1, vals.len());
+        let mut v: V64<T> = V64::new();
+        let mut model = Vec::new();
+        check_stack(&v, &model);
+        for &x in &vals[..capacity] {
+            v.push(x);
+            model.push(x);
+            check_stack(&v, &model);
+        }
+        let first = v.remove(0);
+        assert_eq!(model.remove(0), first);
+        check_stack(&v, &model);
+        v.insert(0, first);
+        model.insert(0, first);
+        check_stack(&v, &model);
+        let last = v.pop().unwrap();
+        assert_eq!(model.pop(), Some(last));
+        check_stack(&v, &model);
+        v.push(last);
+        model.push(last);
+        check_stack(&v, &model);
+        v.truncate(1);
+        model.truncate(1);
+        check_stack(&v, &model);
+        v.extend_from_slice(&vals[1..capacity]);
+        model.extend_from_slice(&vals[1..capacity]);
+        check_stack(&v, &model);
+
+        // spill to the heap and come back
+        v.push(vals[capacity]);
+        model.push(vals[capacity]);
+        assert_eq!(model, &v[..]);
+        assert!(v.bytes_on_heap() > 0);
+        v.pop();
+        model.pop();
+        v.shrink_to_fit();
+        check_stack(&v, &model);
+
+        let from_vec = V64::from(model.clone());
+        check_stack(&from_vec, &model);
+        assert_eq!(model, Vec::from(from_vec));
+        assert_eq!(model, &*v.clone().into_boxed_slice());
+        assert_eq!(model, v.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stack_operations_on_target_layout() {
+        exercise_stack(&[1u8, 2, 3, 4, 5, 6, 7, 8]);
+        exercise_stack(&[0x0102u16, 0x0304, 0x0506, 0x0708]);
+        exercise_stack(&[[1u8, 2, 3], [4, 5, 6], [7, 8, 9]]);
+        exercise_stack(&[0x0102_0304u32, 0x0506_0708]);
+        exercise_stack(&[[0x0102u16, 0x0304, 0x0506], [0x0708, 0x090A, 0x0B0C]]);
+        exercise_stack(&[[1u8, 2, 3, 4, 5, 6, 7], [8, 9, 10, 11, 12, 13, 14]]);
+    }
+
+    #[test]
+    fn test_control_nibble() {
+        let mut v: V64<u16> = V64::new();
+        for i in 0..3 {
+            v.push(0xFFFF);
+            assert_eq!(8 | (i + 1), v.u.get() & 15);
+        }
+        v.push(0xFFFF);
+        assert_eq!(0, v.u.get() & 15);
+    }
+
+    #[test]
+    fn test_from_stack_bytes() {
+        let v = V64::from_stack_bytes(&[1, 2, 3]);
+        check_stack(&v, &[1, 2, 3]);
+        let mut v = V64::from_stack_bytes(&[]);
+        assert!(v.is_empty());
+        v.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(0, v.bytes_on_heap());
+        v.push(8);
+        assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], v[..]);
+    }
+}
@@ -0,0 +1,488 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `V64Str` a UTF-8 string in a single 64 bit pointer.
+//! Strings of up to 7 bytes (ticker symbols, country codes, short tags) are stored
+//! inline with no heap allocation.
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::str::{self, FromStr, Utf8Error};
+
+use crate::thin_sentinel::ThinSentinel;
+use crate::thin_v64::V64;
+
+/// A thin (64bit) UTF-8 string. Guaranteed to be a 64 bit smart pointer.
+///
+/// `V64Str` is to `V64<u8>` what `String` is to `Vec<u8>`. Strings of 7 bytes or fewer
+/// live entirely inside the 64 bit value; longer strings spill to the heap, just like `V64`.
+///
+/// `V64Str` implements `ThinSentinel`, so it can be used as a `ThinMap` key or a `ThinSet`
+/// element. The sentinels are inline values that hold invalid UTF-8, so they can never be
+/// equal to a real string.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_v64str::V64Str;
+/// use thincollections::thin_map::ThinMap;
+///
+/// let mut s = V64Str::from("AAPL");
+/// assert_eq!(0, s.bytes_on_heap());
+/// s.push_str(".NASDAQ");
+/// assert!(s.bytes_on_heap() > 0);
+/// assert_eq!("AAPL.NASDAQ", &*s);
+///
+/// let mut prices = ThinMap::new();
+/// prices.insert(V64Str::from("MSFT"), 107);
+/// assert_eq!(Some(&107), prices.get(&V64Str::from("MSFT")));
+/// ```
+pub struct V64Str {
+    v: V64<u8>,
+}
+
+impl V64Str {
+    /// Constructs a new, empty `V64Str`. Does not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_v64str::V64Str;
+    /// let s = V64Str::new();
+    /// assert!(s.is_empty());
+    /// ```
+    #[inline]
+    pub fn new() -> V64Str {
+        V64Str { v: V64::new() }
+    }
+
+    /// Constructs a new, empty `V64Str` with at least the specified capacity in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_v64str::V64Str;
+    /// let s = V64Str::with_capacity(10);
+    /// assert_eq!(0, s.len());
+    /// assert!(s.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> V64Str {
+        V64Str { v: V64::with_capacity(capacity) }
+    }
+
+    /// Converts a vector of bytes to a `V64Str`, checking that the bytes are valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_v64str::V64Str;
+    /// # fn main() {
+    /// let s = V64Str::from_utf8(v64![b'h', b'i']).unwrap();
+    /// assert_eq!("hi", s.as_str());
+    /// assert!(V64Str::from_utf8(v64![0xFF]).is_err());
+    /// # }
+    /// ```
+    pub fn from_utf8(v: V64<u8>) -> Result<V64Str, Utf8Error> {
+        str::from_utf8(&v)?;
+        Ok(V64Str { v })
+    }
+
+    /// Converts a vector of bytes to a `V64Str` without checking the contents.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must be valid UTF-8.
+    #[inline]
+    pub unsafe fn from_utf8_unchecked(v: V64<u8>) -> V64Str {
+        V64Str { v }
+    }
+
+    /// Extracts a string slice containing the entire `V64Str`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(self.v.as_slice()) }
+    }
+
+    /// Extracts a mutable string slice containing the entire `V64Str`.
+    #[inline]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { str::from_utf8_unchecked_mut(self.v.as_mut_slice()) }
+    }
+
+    /// Returns the bytes of this string.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.v.as_slice()
+    }
+
+    /// Converts the `V64Str` into its underlying byte vector, without copying.
+    #[inline]
+    pub fn into_bytes(self) -> V64<u8> {
+        self.v
+    }
+
+    /// Returns the length of this string in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    /// Returns `true` if this string has a length of zero.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+
+    /// Returns the capacity of this string in bytes.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.v.capacity()
+    }
+
+    /// Returns the number of bytes allocated on the heap. Strings of 7 bytes
+    /// or fewer use no heap memory.
+    #[inline]
+    pub fn bytes_on_heap(&self) -> usize {
+        self.v.bytes_on_heap()
+    }
+
+    /// Reserves capacity for at least `additional` more bytes.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.v.reserve(additional)
+    }
+
+    /// Shrinks the capacity of this string to match its length.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.v.shrink_to_fit()
+    }
+
+    /// Appends the given string slice onto the end of this `V64Str`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_v64str::V64Str;
+    /// let mut s = V64Str::from("foo");
+    /// s.push_str("bar");
+    /// assert_eq!("foobar", s);
+    /// ```
+    #[inline]
+    pub fn push_str(&mut self, string: &str) {
+        self.v.extend_from_slice(string.as_bytes())
+    }
+
+    /// Appends the given `char` to the end of this `V64Str`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_v64str::V64Str;
+    /// let mut s = V64Str::from("ab");
+    /// s.push('c');
+    /// s.push('é');
+    /// assert_eq!("abcé", s);
+    /// ```
+    #[inline]
+    pub fn push(&mut self, ch: char) {
+        match ch.len_utf8() {
+            1 => self.v.push(ch as u8),
+            _ => self.v.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    /// Removes the last character from the string and returns it, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use thincollections::thin_v64str::V64Str;
+    /// let mut s = V64Str::from("hé");
+    /// assert_eq!(Some('é'), s.pop());
+    /// assert_eq!(Some('h'), s.pop());
+    /// assert_eq!(None, s.pop());
+    /// ```
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.chars().next_back()?;
+        let new_len = self.len() - ch.len_utf8();
+        self.v.truncate(new_len);
+        Some(ch)
+    }
+
+    /// Shortens this `V64Str` to the specified length in bytes.
+    ///
+    /// If `new_len` is greater than the string's current length, this has no effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_len` does not lie on a `char` boundary.
+    #[inline]
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len <= self.len() {
+            assert!(self.is_char_boundary(new_len));
+            self.v.truncate(new_len)
+        }
+    }
+
+    /// Truncates this `V64Str`, removing all contents.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.v.clear()
+    }
+}
+
+impl Deref for V64Str {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl DerefMut for V64Str {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl AsRef<str> for V64Str {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for V64Str {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<str> for V64Str {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Eq/Ord/Hash work on the raw bytes so they stay well defined for the (non UTF-8) sentinels.
+impl PartialEq for V64Str {
+    #[inline]
+    fn eq(&self, other: &V64Str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for V64Str {}
+
+impl PartialOrd for V64Str {
+    #[inline]
+    fn partial_cmp(&self, other: &V64Str) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for V64Str {
+    #[inline]
+    fn cmp(&self, other: &V64Str) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Hash for V64Str {
+    /// Hashes the same way as `str`, as required by `Borrow<str>`.
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.as_bytes());
+        state.write_u8(0xff)
+    }
+}
+
+macro_rules! impl_str_eq {
+    ($lhs:ty, $rhs: ty) => {
+        impl<'a> PartialEq<$rhs> for $lhs {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool { self[..] == other[..] }
+        }
+
+        impl<'a> PartialEq<$lhs> for $rhs {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool { self[..] == other[..] }
+        }
+    }
+}
+
+impl_str_eq! { V64Str, str }
+impl_str_eq! { V64Str, &'a str }
+impl_str_eq! { V64Str, String }
+
+impl fmt::Display for V64Str {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for V64Str {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Write for V64Str {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+
+impl Clone for V64Str {
+    fn clone(&self) -> V64Str {
+        V64Str { v: self.v.clone() }
+    }
+
+    fn clone_from(&mut self, source: &V64Str) {
+        self.v.clone_from(&source.v);
+    }
+}
+
+impl Default for V64Str {
+    /// Creates an empty `V64Str`.
+    #[inline]
+    fn default() -> V64Str {
+        V64Str::new()
+    }
+}
+
+impl<'a> From<&'a str> for V64Str {
+    #[inline]
+    fn from(s: &'a str) -> V64Str {
+        V64Str { v: V64::from(s) }
+    }
+}
+
+impl<'a> From<&'a String> for V64Str {
+    #[inline]
+    fn from(s: &'a String) -> V64Str {
+        V64Str::from(s.as_str())
+    }
+}
+
+impl From<String> for V64Str {
+    #[inline]
+    fn from(s: String) -> V64Str {
+        V64Str::from(s.as_str())
+    }
+}
+
+impl From<char> for V64Str {
+    #[inline]
+    fn from(c: char) -> V64Str {
+        let mut s = V64Str::new();
+        s.push(c);
+        s
+    }
+}
+
+impl<'a> From<&'a V64Str> for String {
+    #[inline]
+    fn from(s: &'a V64Str) -> String {
+        String::from(s.as_str())
+    }
+}
+
+impl From<V64Str> for String {
+    #[inline]
+    fn from(s: V64Str) -> String {
+        String::from(s.as_str())
+    }
+}
+
+impl FromStr for V64Str {
+    type Err = ::std::convert::Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<V64Str, Self::Err> {
+        Ok(V64Str::from(s))
+    }
+}
+
+impl Extend<char> for V64Str {
+    fn extend<I: IntoIterator<Item=char>>(&mut self, iter: I) {
+        let iterator = iter.into_iter();
+        let (lower_bound, _) = iterator.size_hint();
+        self.reserve(lower_bound);
+        for ch in iterator {
+            self.push(ch)
+        }
+    }
+}
+
+impl<'a> Extend<&'a char> for V64Str {
+    fn extend<I: IntoIterator<Item=&'a char>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<'a> Extend<&'a str> for V64Str {
+    fn extend<I: IntoIterator<Item=&'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s)
+        }
+    }
+}
+
+impl FromIterator<char> for V64Str {
+    fn from_iter<I: IntoIterator<Item=char>>(iter: I) -> V64Str {
+        let mut s = V64Str::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<'a> FromIterator<&'a char> for V64Str {
+    fn from_iter<I: IntoIterator<Item=&'a char>>(iter: I) -> V64Str {
+        let mut s = V64Str::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<'a> FromIterator<&'a str> for V64Str {
+    fn from_iter<I: IntoIterator<Item=&'a str>>(iter: I) -> V64Str {
+        let mut s = V64Str::new();
+        s.extend(iter);
+        s
+    }
+}
+
+// A single inline byte of 0xFF or 0xFE. Neither byte can appear in UTF-8, so no real string is
+// equal to a sentinel.
+impl ThinSentinel for V64Str {
+    const SENTINEL_ZERO: Self = V64Str { v: V64::from_stack_bytes(&[0xFF]) };
+    const SENTINEL_ONE: Self = V64Str { v: V64::from_stack_bytes(&[0xFE]) };
+}
@@ -29,8 +29,12 @@ use std::ops::Index;
 use std::ops::IndexMut;
 use std::ops::RangeBounds;
 use std::ptr::NonNull;
+use std::rc::Rc;
 use std::slice;
 use std::slice::SliceIndex;
+use std::sync::Arc;
+
+use crate::thin_v64::V64;
 
 /// A thin (usize) vector. Guaranteed to be a usize-sized smart pointer.
 ///
@@ -283,21 +287,136 @@ use std::slice::SliceIndex;
 /// # }
 /// ```
 ///
+/// # Memory layout
+///
+/// The layout of a `ThinVec<T>` is stable, so it can be handed to C or across a dylib boundary
+/// with [`ThinVec::into_raw`] and taken back with [`ThinVec::from_raw`]:
+///
+/// - the vector is a single pointer to a heap block, aligned to
+///   `max(align_of::<usize>(), align_of::<T>())`
+/// - the block starts with a [`ThinVecHeader`]: the length, then the capacity, both `usize`
+/// - the elements start `max(2 * size_of::<usize>(), align_of::<T>())` bytes into the block
+///
+/// A vector that hasn't allocated, and any vector of a zero sized type, has no block.
+/// `include/thincollections.h` has the matching C declarations.
 ///
-pub struct ThinVec<T> {
+pub type ThinVec<T> = ThinVecBase<T, usize>;
+
+/// The vector behind [`ThinVec`] and [`ThinVec32`], generic over the integer type of the length and
+/// capacity in its heap header. Use one of the aliases rather than naming this type directly.
+///
+/// [`ThinVec`]: type.ThinVec.html
+/// [`ThinVec32`]: ../thin_vec32/type.ThinVec32.html
+pub struct ThinVecBase<T, L: HeaderLen> {
     u: NonZeroUsize,
-    _marker: marker::PhantomData<T>,
+    _marker: marker::PhantomData<(T, L)>,
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for usize {}
+
+    impl Sealed for u32 {}
+}
+
+/// The integer type a [`ThinVecBase`] stores its length and capacity as, at the start of its heap
+/// block: `usize` for [`ThinVec`], `u32` for [`ThinVec32`]. This trait is sealed.
+///
+/// [`ThinVecBase`]: struct.ThinVecBase.html
+/// [`ThinVec`]: type.ThinVec.html
+/// [`ThinVec32`]: ../thin_vec32/type.ThinVec32.html
+pub trait HeaderLen: Copy + sealed::Sealed {
+    /// The largest capacity the header can hold.
+    const MAX: usize;
+
+    #[doc(hidden)]
+    fn to_usize(self) -> usize;
+
+    #[doc(hidden)]
+    fn from_usize(n: usize) -> Self;
+}
+
+impl HeaderLen for usize {
+    const MAX: usize = usize::MAX;
+
+    #[inline(always)]
+    fn to_usize(self) -> usize { self }
+
+    #[inline(always)]
+    fn from_usize(n: usize) -> usize { n }
+}
+
+impl HeaderLen for u32 {
+    const MAX: usize = u32::MAX as usize;
+
+    #[inline(always)]
+    fn to_usize(self) -> usize { self as usize }
+
+    #[inline(always)]
+    fn from_usize(n: usize) -> u32 { n as u32 }
+}
+
+// the heap block starts with [len, capacity], both an L
+#[inline(always)]
+unsafe fn load<L: HeaderLen>(p: *const L) -> usize {
+    (*p).to_usize()
+}
+
+#[inline(always)]
+unsafe fn store<L: HeaderLen>(p: *mut L, n: usize) {
+    *p = L::from_usize(n);
 }
 
 #[cfg(target_pointer_width = "64")]
-const ZST_MASK: usize = 0x8000_0000_0000_0000;
+pub(crate) const ZST_MASK: usize = 0x8000_0000_0000_0000;
 
 #[cfg(target_pointer_width = "32")]
-const ZST_MASK: usize = 0x8000_0000;
+pub(crate) const ZST_MASK: usize = 0x8000_0000;
+
+/// The header at the start of a `ThinVec`'s heap block, see [Memory layout].
+///
+/// The elements follow the header, at `max(size_of::<ThinVecHeader>(), align_of::<T>())` bytes
+/// from its start. Foreign code may read the header and the elements, and may lower `len` or
+/// overwrite elements of `Copy` types, but it must not free or reallocate the block.
+///
+/// [Memory layout]: struct.ThinVec.html#memory-layout
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThinVecHeader {
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl ThinVecHeader {
+    /// Returns a pointer to the first element of the block that starts with this header.
+    ///
+    /// # Safety
+    ///
+    /// `header` must come from `ThinVec::<T>::into_raw` and must not be null.
+    #[inline]
+    pub unsafe fn elements<T>(header: *mut ThinVecHeader) -> *mut T {
+        (header as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T
+    }
+}
+
+/// Heap blocks at least this big are grown and shrunk with `alloc::realloc`, which can often
+/// extend the block in place, or remap its pages, instead of copying it. Smaller blocks are
+/// copied into a fresh allocation, which benches faster.
+pub(crate) const REALLOC_THRESHOLD: usize = 64 * 1024;
+
+/// The capacity to grow to when `required` elements don't fit in `capacity`.
+///
+/// Growth is geometric (at least doubling, starting from 8) for every path that grows the vector,
+/// so repeated `push`, `insert`, `extend` or `reserve` calls cost amortized O(1) per element.
+#[inline]
+pub(crate) fn grow_capacity(capacity: usize, required: usize) -> usize {
+    cmp::max(cmp::max(capacity.saturating_mul(2), required), 8)
+}
 
 pub static DANGLE: usize = <usize>::MAX; // it is impossible for alloc to return this value, as we require at least 2*usize space
 
-impl<T> ThinVec<T> {
+impl<T, L: HeaderLen> ThinVecBase<T, L> {
     /// Constructs a new, empty `ThinVec<T>`.
     ///
     /// The vector will not allocate until elements are pushed onto it.
@@ -311,10 +430,10 @@ impl<T> ThinVec<T> {
     /// vec.push(42);
     /// ```
     #[inline]
-    pub fn new() -> ThinVec<T> {
+    pub fn new() -> ThinVecBase<T, L> {
         unsafe {
-            if mem::size_of::<T>() == 0 { return ThinVec { u: NonZeroUsize::new_unchecked(ZST_MASK), _marker: marker::PhantomData }; }
-            ThinVec { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData }
+            if mem::size_of::<T>() == 0 { return ThinVecBase { u: NonZeroUsize::new_unchecked(ZST_MASK), _marker: marker::PhantomData }; }
+            ThinVecBase { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData }
         }
     }
 
@@ -348,14 +467,14 @@ impl<T> ThinVec<T> {
     /// vec.push(11);
     /// ```
     #[inline]
-    pub fn with_capacity(capacity: usize) -> ThinVec<T> {
-        if mem::size_of::<T>() == 0 { return ThinVec::new(); }
+    pub fn with_capacity(capacity: usize) -> ThinVecBase<T, L> {
+        if mem::size_of::<T>() == 0 { return ThinVecBase::new(); }
         if capacity == 0 {
-            return <ThinVec<T>>::new();
+            return <ThinVecBase<T, L>>::new();
         }
-        let array = <ThinVec<T>>::allocate_array(capacity);
+        let array = <ThinVecBase<T, L>>::allocate_array(capacity);
         unsafe {
-            ThinVec { u: NonZeroUsize::new_unchecked(array as usize), _marker: marker::PhantomData }
+            ThinVecBase { u: NonZeroUsize::new_unchecked(array as usize), _marker: marker::PhantomData }
         }
     }
 
@@ -374,8 +493,8 @@ impl<T> ThinVec<T> {
         if mem::size_of::<T>() == 0 { return <usize>::MAX; }
         if self.u.get() == DANGLE { return 0; }
         unsafe {
-            let len_ptr = self.u.get() as *mut usize;
-            *len_ptr.add(1)
+            let len_ptr = self.u.get() as *mut L;
+            load(len_ptr.add(1))
         }
     }
 
@@ -401,7 +520,12 @@ impl<T> ThinVec<T> {
     pub fn reserve(&mut self, additional: usize) {
         if mem::size_of::<T>() == 0 { return; }
         let len = self.len();
-        self.reserve_exact(cmp::max(len * 2, len + additional));
+        let capacity = self.capacity();
+        if capacity - len >= additional { return; }
+        let required = len.checked_add(additional).expect("capacity overflow");
+        // the header caps the growth; block_layout panics if even `required` doesn't fit
+        let new_capacity = cmp::max(cmp::min(grow_capacity(capacity, required), L::MAX), required);
+        self.reserve_exact(new_capacity - len);
     }
 
     /// Reserves the minimum capacity for exactly `additional` more elements to
@@ -429,16 +553,16 @@ impl<T> ThinVec<T> {
         if mem::size_of::<T>() == 0 { return; }
         if self.u.get() == DANGLE {
             unsafe {
-                self.u = NonZeroUsize::new_unchecked(<ThinVec<T>>::allocate_array(additional) as usize);
+                self.u = NonZeroUsize::new_unchecked(<ThinVecBase<T, L>>::allocate_array(additional) as usize);
                 return;
             }
         }
         let len = self.len();
         let remain = self.capacity() - len;
         if remain < additional {
-            let len_ptr = self.u.get() as *mut usize;
+            let len_ptr = self.u.get() as *mut L;
             unsafe {
-                self.realloc_heap(len_ptr, *len_ptr + additional);
+                self.realloc_heap(len_ptr, load(len_ptr) + additional);
             }
         }
     }
@@ -464,9 +588,9 @@ impl<T> ThinVec<T> {
     pub fn shrink_to_fit(&mut self) {
         if mem::size_of::<T>() == 0 { return; }
         if self.u.get() == DANGLE { return; }
-        let len_ptr = self.u.get() as *mut usize;
+        let len_ptr = self.u.get() as *mut L;
         unsafe {
-            self.realloc_heap(len_ptr, *len_ptr);
+            self.realloc_heap(len_ptr, load(len_ptr));
         }
     }
 
@@ -535,23 +659,39 @@ impl<T> ThinVec<T> {
         }
         if self.u.get() == DANGLE { return; }
         unsafe {
-            let len_ptr = self.u.get() as *mut usize;
-            if *len_ptr > len {
+            let len_ptr = self.u.get() as *mut L;
+            if load(len_ptr) > len {
                 if mem::needs_drop::<T>() {
-                    let mut cur = ((len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T).add(len);
-                    let end = cur.add(*len_ptr - len);
+                    let mut cur = ((len_ptr as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T).add(len);
+                    let end = cur.add(load(len_ptr) - len);
                     while cur < end {
-                        *len_ptr -= 1;
+                        store(len_ptr, load(len_ptr) - 1);
                         ptr::drop_in_place(cur);
                         cur = cur.add(1);
                     }
                 } else {
-                    *len_ptr = len;
+                    store(len_ptr, len);
                 }
             }
         }
     }
 
+    /// Returns a raw pointer to the vector's elements.
+    ///
+    /// The pointer is dangling (but aligned) if the vector hasn't allocated.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.array_ptr()
+    }
+
+    /// Returns an unsafe mutable pointer to the vector's elements.
+    ///
+    /// The pointer is dangling (but aligned) if the vector hasn't allocated.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.array_ptr()
+    }
+
     /// Extracts a slice containing the entire vector.
     ///
     /// Equivalent to `&s[..]`.
@@ -631,10 +771,10 @@ impl<T> ThinVec<T> {
             // bounds check on hole succeeds there must be a last element (which
             // can be self[index] itself).
             if index < self.len() {
-                let len_ptr = self.u.get() as *mut usize;
-                *len_ptr -= 1;
+                let len_ptr = self.u.get() as *mut L;
+                store(len_ptr, load(len_ptr) - 1);
                 let ptr = len_ptr as *mut u8;
-                <ThinVec<T>>::replace(ptr.add(<ThinVec<T>>::header_bytes()) as *mut T, *len_ptr, index)
+                <ThinVecBase<T, L>>::replace(ptr.add(<ThinVecBase<T, L>>::header_bytes()) as *mut T, load(len_ptr), index)
             } else {
                 panic!("index out of bounds! len: {}, index {}", self.len(), index);
             }
@@ -674,8 +814,8 @@ impl<T> ThinVec<T> {
         assert!(index <= self.len());
         self.possibly_grow_heap();
         unsafe {
-            let len_ptr = self.u.get() as *mut usize;
-            let len = *len_ptr;
+            let len_ptr = self.u.get() as *mut L;
+            let len = load(len_ptr);
             // The spot to put the new value
             {
                 let p = self.as_mut_ptr().add(index);
@@ -686,7 +826,7 @@ impl<T> ThinVec<T> {
                 // element.
                 ptr::write(p, val);
             }
-            *len_ptr += 1;
+            store(len_ptr, len + 1);
         }
     }
 
@@ -728,12 +868,12 @@ impl<T> ThinVec<T> {
         }
         let array: *mut T;
         let len: usize;
-        let len_ptr = self.u.get() as *mut usize;
+        let len_ptr = self.u.get() as *mut L;
         let ptr = len_ptr as *mut u8;
         unsafe {
-            len = *len_ptr;
-            *len_ptr -= 1;
-            array = ptr.add(<ThinVec<T>>::header_bytes()) as *mut T;
+            len = load(len_ptr);
+            store(len_ptr, len - 1);
+            array = ptr.add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
         }
         assert!(index < len);
         unsafe {
@@ -787,11 +927,11 @@ impl<T> ThinVec<T> {
         let mut array: *mut T;
         let len: usize;
         {
-            let len_ptr = self.u.get() as *mut usize;
+            let len_ptr = self.u.get() as *mut L;
             let ptr = len_ptr as *mut u8;
             unsafe {
-                len = *len_ptr;
-                array = ptr.add(<ThinVec<T>>::header_bytes()) as *mut T;
+                len = load(len_ptr);
+                array = ptr.add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
             }
         }
         unsafe {
@@ -810,8 +950,8 @@ impl<T> ThinVec<T> {
                 }
                 array = array.add(1);
             }
-            let len_ptr = self.u.get() as *mut usize;
-            *len_ptr -= removed;
+            let len_ptr = self.u.get() as *mut L;
+            store(len_ptr, load(len_ptr) - removed);
         }
     }
 
@@ -971,7 +1111,7 @@ impl<T> ThinVec<T> {
     #[inline(always)]
     pub fn len(&self) -> usize {
         if mem::size_of::<T>() == 0 { return self.u.get() & (ZST_MASK - 1); }
-        unsafe { if self.u.get() == DANGLE { 0 } else { *(self.u.get() as *mut usize) } }
+        unsafe { if self.u.get() == DANGLE { 0 } else { load(self.u.get() as *mut L) } }
     }
 
     /// Returns `true` if the vector contains no elements.
@@ -1018,10 +1158,11 @@ impl<T> ThinVec<T> {
         }
         self.possibly_grow_heap();
         unsafe {
-            let len_ptr = self.u.get() as *mut usize;
-            let arr = (len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
-            ptr::write(arr.add(*len_ptr), val);
-            *len_ptr += 1;
+            let len_ptr = self.u.get() as *mut L;
+            let arr = (len_ptr as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
+            let len = load(len_ptr);
+            ptr::write(arr.add(len), val);
+            store(len_ptr, len + 1);
         }
     }
 
@@ -1057,13 +1198,13 @@ impl<T> ThinVec<T> {
         let array: *mut T;
         let len: usize;
         {
-            let len_ptr = self.u.get() as *mut usize;
+            let len_ptr = self.u.get() as *mut L;
             unsafe {
-                len = *len_ptr;
+                len = load(len_ptr);
                 if len == 0 { return None; }
                 let ptr = len_ptr as *mut u8;
-                *len_ptr -= 1;
-                array = ptr.add(<ThinVec<T>>::header_bytes()) as *mut T;
+                store(len_ptr, len - 1);
+                array = ptr.add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
             }
         }
         unsafe {
@@ -1117,12 +1258,12 @@ impl<T> ThinVec<T> {
         if count > 0 {
             self.reserve(count);
             let len = self.len();
-            ptr::copy_nonoverlapping(other as *const T, self.get_unchecked_mut(len), count);
+            ptr::copy_nonoverlapping(other as *const T, self.array_ptr().add(len), count);
             self.set_len(len + count);
         }
     }
 
-    unsafe fn set_len(&mut self, len: usize) {
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
         if mem::size_of::<T>() == 0 {
             self.u = NonZeroUsize::new_unchecked(len | ZST_MASK);
             return;
@@ -1134,8 +1275,7 @@ impl<T> ThinVec<T> {
                 return;
             }
         }
-        let len_ptr = self.u.get() as *mut usize;
-        *len_ptr = len;
+        store(self.u.get() as *mut L, len);
     }
 
     /// Splits the collection into two at the given index.
@@ -1167,7 +1307,7 @@ impl<T> ThinVec<T> {
         assert!(at <= len, "`at` out of bounds");
 
         let other_len = len - at;
-        let mut other = ThinVec::with_capacity(other_len);
+        let mut other = ThinVecBase::with_capacity(other_len);
 
         // Unsafely `set_len` and copy items to `other`.
         unsafe {
@@ -1242,25 +1382,7 @@ impl<T> ThinVec<T> {
     /// # }
     /// ```
     pub fn into_boxed_slice(self) -> Box<[T]> {
-        if mem::size_of::<T>() == 0 {
-            unsafe {
-                let slice = slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), self.len());
-                let output: Box<[T]> = Box::from_raw(slice);
-                return output;
-            }
-        }
-        unsafe {
-            let len_ptr = self.u.get() as *mut usize;
-            let array: *mut T = (len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
-            let len: usize = *len_ptr;
-            *len_ptr = 0; // prevents dropping of contents
-            let layout = Layout::from_size_align(mem::size_of::<T>() * len, mem::align_of::<T>()).unwrap();
-            let buffer = alloc::alloc(layout) as *mut T;
-            ptr::copy_nonoverlapping(array, buffer, len);
-            let slice = slice::from_raw_parts_mut(buffer, len);
-            let output: Box<[T]> = Box::from_raw(slice);
-            output
-        }
+        Vec::from(self).into_boxed_slice()
     }
 
     /// Creates a draining iterator that removes the specified range in the vector
@@ -1293,7 +1415,7 @@ impl<T> ThinVec<T> {
     /// assert_eq!(v, &[]);
     /// # }
     /// ```
-    pub fn drain<R>(&mut self, range: R) -> Drain<T>
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T, L>
         where R: RangeBounds<usize>
     {
         // Memory safety
@@ -1376,7 +1498,7 @@ impl<T> ThinVec<T> {
     /// # }
     /// ```
     #[inline]
-    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Splice<I::IntoIter>
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Splice<'_, I::IntoIter, L>
         where R: RangeBounds<usize>, I: IntoIterator<Item=T>
     {
         Splice {
@@ -1437,7 +1559,7 @@ impl<T> ThinVec<T> {
     /// assert_eq!(odds, thinvec![1, 3, 5, 9, 11, 13, 15]);
     /// # }
     /// ```
-    pub fn drain_filter<F>(&mut self, filter: F) -> DrainFilter<T, F>
+    pub fn drain_filter<F>(&mut self, filter: F) -> DrainFilter<'_, T, F, L>
         where F: FnMut(&mut T) -> bool,
     {
         let old_len = self.len();
@@ -1471,6 +1593,24 @@ impl<T> ThinVec<T> {
             }
             return;
         }
+        // Reserve for the lower size hint up front (exact for slices, vectors and most adapters),
+        // then fill that capacity without checking it on every element.
+        let (lower, _) = iterator.size_hint();
+        self.reserve(lower);
+        {
+            let capacity = self.capacity();
+            let mut guard = SetLenOnDrop { local_len: self.len(), vec: self };
+            let dst = guard.vec.array_ptr();
+            while guard.local_len < capacity {
+                match iterator.next() {
+                    Some(element) => unsafe {
+                        ptr::write(dst.add(guard.local_len), element);
+                        guard.local_len += 1;
+                    },
+                    None => return,
+                }
+            }
+        }
         while let Some(element) = iterator.next() {
             let len = self.len();
             if len == self.capacity() {
@@ -1478,7 +1618,7 @@ impl<T> ThinVec<T> {
                 self.reserve(lower.saturating_add(1));
             }
             unsafe {
-                ptr::write(self.get_unchecked_mut(len), element);
+                ptr::write(self.array_ptr().add(len), element);
                 // NB can't overflow since we would have had to alloc the address space
                 self.set_len(len + 1);
             }
@@ -1488,14 +1628,13 @@ impl<T> ThinVec<T> {
     #[cold]
     fn allocate_array(capacity: usize) -> *mut u8 {
         unsafe {
-            // align_of is a power of 2. 2 * size_of::<usize> is a power of 2.
-            // if align_of is smaller than 2*size_of::<usize>, we'll have no padding between the header and array
-            // if align_of is bigger than 2*size_of::<usize>, we'll use the first align for the header
-            let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
-            let layout = Layout::from_size_align(mem::size_of::<T>() * capacity + <ThinVec<T>>::header_bytes(), align).unwrap();
+            let layout = <ThinVecBase<T, L>>::block_layout(capacity);
             let buffer = alloc::alloc(layout);
-            ptr::write(buffer as *mut usize, 0); // current length
-            ptr::write((buffer as *mut usize).add(1), capacity);
+            if buffer.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            store(buffer as *mut L, 0); // current length
+            store((buffer as *mut L).add(1), capacity);
             buffer
         }
     }
@@ -1504,66 +1643,195 @@ impl<T> ThinVec<T> {
     fn possibly_grow_heap(&mut self) {
         unsafe {
             if self.u.get() == DANGLE {
-                self.u = NonZeroUsize::new_unchecked(<ThinVec<T>>::allocate_array(8) as usize);
+                self.u = NonZeroUsize::new_unchecked(<ThinVecBase<T, L>>::allocate_array(8) as usize);
                 return;
             }
-            let len_ptr = self.u.get() as *mut usize;
-            let cap_ptr = len_ptr.add(1);
-            if *len_ptr == *cap_ptr {
-                self.realloc_heap(len_ptr, *len_ptr * 2);
+            let len_ptr = self.u.get() as *mut L;
+            let cap = load(len_ptr.add(1));
+            if load(len_ptr) == cap {
+                assert!(cap < L::MAX, "capacity overflow");
+                self.realloc_heap(len_ptr, cmp::min(grow_capacity(cap, cap + 1), L::MAX));
             }
         }
     }
 
     #[inline(always)]
-    fn header_bytes() -> usize {
-        cmp::max(mem::size_of::<usize>() * 2, mem::align_of::<T>())
+    pub(crate) fn header_bytes() -> usize {
+        cmp::max(mem::size_of::<L>() * 2, mem::align_of::<T>())
     }
 
+    // the first element slot; unlike going through a slice, this can be used to write past len
     #[inline(always)]
-    fn realloc_heap(&mut self, len_ptr: *mut usize, new_capacity: usize) {
+    fn array_ptr(&self) -> *mut T {
+        if mem::size_of::<T>() == 0 || self.u.get() == DANGLE {
+            NonNull::dangling().as_ptr()
+        } else {
+            unsafe { (self.u.get() as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T }
+        }
+    }
+
+
+    #[inline(always)]
+    fn block_layout(capacity: usize) -> Layout {
+        // align_of is a power of 2. 2 * size_of::<L> is a power of 2.
+        // if align_of is smaller than 2*size_of::<L>, we'll have no padding between the header and array
+        // if align_of is bigger than 2*size_of::<L>, we'll use the first align for the header
+        assert!(capacity <= L::MAX, "capacity overflow");
+        let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
+        let size = capacity.checked_mul(mem::size_of::<T>())
+            .and_then(|x| x.checked_add(<ThinVecBase<T, L>>::header_bytes()))
+            .expect("capacity overflow");
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[inline(always)]
+    fn realloc_heap(&mut self, len_ptr: *mut L, new_capacity: usize) {
         unsafe {
-            //realloc seems to bench slower...
-            let head_ptr = <ThinVec<T>>::allocate_array(new_capacity);
-            let header_bytes = <ThinVec<T>>::header_bytes();
-            let to_move = cmp::min(new_capacity, *(len_ptr.add(1)));
-            ptr::copy_nonoverlapping(len_ptr as *mut u8, head_ptr, to_move * mem::size_of::<T>() + header_bytes);
-            let new_cap_ptr = (head_ptr as *mut usize).add(1);
-            *new_cap_ptr = new_capacity;
+            let old_layout = <ThinVecBase<T, L>>::block_layout(load(len_ptr.add(1)));
+            let new_layout = <ThinVecBase<T, L>>::block_layout(new_capacity);
+            let head_ptr: *mut u8;
+            if cmp::max(old_layout.size(), new_layout.size()) >= REALLOC_THRESHOLD {
+                head_ptr = alloc::realloc(len_ptr as *mut u8, old_layout, new_layout.size());
+                if head_ptr.is_null() {
+                    alloc::handle_alloc_error(new_layout);
+                }
+            } else {
+                head_ptr = <ThinVecBase<T, L>>::allocate_array(new_capacity);
+                let to_move = cmp::min(new_capacity, load(len_ptr));
+                ptr::copy_nonoverlapping(len_ptr as *mut u8, head_ptr, to_move * mem::size_of::<T>() + <ThinVecBase<T, L>>::header_bytes());
+                alloc::dealloc(len_ptr as *mut u8, old_layout);
+            }
+            store((head_ptr as *mut L).add(1), new_capacity);
             self.u = NonZeroUsize::new_unchecked(head_ptr as usize);
-            let old = len_ptr as *mut u8;
-            let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
-            let layout = Layout::from_size_align(mem::size_of::<T>() * (*(len_ptr.add(1))) + header_bytes, align).unwrap();
-            alloc::dealloc(old, layout);
         }
     }
 }
 
-impl<T: Clone> ThinVec<T> {
-    pub fn from_elem(elem: T, count: usize) -> ThinVec<T> {
-        let mut thinvec = ThinVec::with_capacity(count);
+impl<T> ThinVec<T> {
+    /// Consumes the vector and returns a pointer to its heap block, which starts with
+    /// a [`ThinVecHeader`]. See [Memory layout].
+    ///
+    /// Returns null if the vector hasn't allocated. For zero sized types the pointer only encodes
+    /// the length and must not be dereferenced.
+    ///
+    /// The block is leaked until it's passed back to [`ThinVec::from_raw`].
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_vec::{ThinVec, ThinVecHeader};
+    /// # fn main() {
+    /// let v: ThinVec<u32> = thinvec![1, 2, 3];
+    /// let raw = v.into_raw();
+    /// unsafe {
+    ///     assert_eq!(3, (*raw).len);
+    ///     assert_eq!(2, *ThinVecHeader::elements::<u32>(raw).add(1));
+    ///     let v = ThinVec::<u32>::from_raw(raw);
+    ///     assert_eq!(v, [1, 2, 3]);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [Memory layout]: struct.ThinVec.html#memory-layout
+    #[inline]
+    pub fn into_raw(self) -> *mut ThinVecHeader {
+        let u = self.u.get();
+        mem::forget(self);
+        if u == DANGLE && mem::size_of::<T>() != 0 { ptr::null_mut() } else { u as *mut ThinVecHeader }
+    }
+
+    /// Rebuilds a vector from a pointer returned by [`ThinVec::into_raw`]. Null gives an empty vector.
+    ///
+    /// # Safety
+    ///
+    /// `header` must be null or come from `ThinVec::<T>::into_raw` for the same `T`, and the
+    /// header and elements must still be valid. Each pointer may only be turned back into a
+    /// vector once.
+    #[inline]
+    pub unsafe fn from_raw(header: *mut ThinVecHeader) -> ThinVec<T> {
+        if header.is_null() {
+            return ThinVec::new();
+        }
+        ThinVecBase { u: NonZeroUsize::new_unchecked(header as usize), _marker: marker::PhantomData }
+    }
+}
+
+impl<T: Clone, L: HeaderLen> ThinVecBase<T, L> {
+    pub fn from_elem(elem: T, count: usize) -> ThinVecBase<T, L> {
+        let mut thinvec = ThinVecBase::with_capacity(count);
         for _i in 0..count as isize {
             thinvec.push(elem.clone());
         }
         thinvec
     }
 
+    /// Clones and appends all the elements of a slice to the vector.
+    ///
+    /// The space is reserved once. The length is only written back at the end (or if a clone
+    /// panics), which lets the loop compile down to a `memcpy` for `Copy` types.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_vec::ThinVec;
+    /// # fn main() {
+    /// let mut vec = thinvec![1];
+    /// vec.extend_from_slice(&[2, 3, 4]);
+    /// assert_eq!(vec, [1, 2, 3, 4]);
+    /// # }
+    /// ```
     pub fn extend_from_slice(&mut self, slice: &[T]) {
         self.reserve(slice.len());
-        unsafe {
-            let mut len = self.len();
-            let mut dst = self.get_unchecked_mut(len) as *mut T;
-            for t in slice.iter() {
-                ptr::write(dst, t.clone());
-                dst = dst.add(1);
-                len += 1;
-                self.set_len(len); // we set len here in the loop in case clone breaks.
-            }
+        let mut guard = SetLenOnDrop { local_len: self.len(), vec: self };
+        let dst = guard.vec.array_ptr();
+        for t in slice.iter() {
+            unsafe { ptr::write(dst.add(guard.local_len), t.clone()); }
+            guard.local_len += 1;
+        }
+    }
+
+    /// Clones the elements in the `src` range and appends them to the end of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, or if the end is
+    /// greater than the length of the vector.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_vec::ThinVec;
+    /// # fn main() {
+    /// let mut vec = thinvec![0, 1, 2, 3, 4];
+    /// vec.extend_from_within(2..);
+    /// assert_eq!(vec, [0, 1, 2, 3, 4, 2, 3, 4]);
+    /// vec.extend_from_within(..2);
+    /// assert_eq!(vec, [0, 1, 2, 3, 4, 2, 3, 4, 0, 1]);
+    /// # }
+    /// ```
+    pub fn extend_from_within<R>(&mut self, src: R) where R: RangeBounds<usize> {
+        let len = self.len();
+        let start = match src.start_bound() {
+            Included(&n) => n,
+            Excluded(&n) => n + 1,
+            Unbounded => 0,
+        };
+        let end = match src.end_bound() {
+            Included(&n) => n + 1,
+            Excluded(&n) => n,
+            Unbounded => len,
+        };
+        assert!(start <= end);
+        assert!(end <= len);
+
+        self.reserve(end - start);
+        let mut guard = SetLenOnDrop { local_len: len, vec: self };
+        let array = guard.vec.array_ptr();
+        for i in start..end {
+            unsafe { ptr::write(array.add(guard.local_len), (*array.add(i)).clone()); }
+            guard.local_len += 1;
         }
     }
 }
 
-impl<T> Drop for ThinVec<T> {
+impl<T, L: HeaderLen> Drop for ThinVecBase<T, L> {
     fn drop(&mut self) {
         unsafe {
             if mem::size_of::<T>() == 0 {
@@ -1571,12 +1839,12 @@ impl<T> Drop for ThinVec<T> {
                 return;
             }
             if self.u.get() == DANGLE { return; }
-            let len_ptr = self.u.get() as *mut usize;
-            let header_bytes = <ThinVec<T>>::header_bytes();
+            let len_ptr = self.u.get() as *mut L;
+            let header_bytes = <ThinVecBase<T, L>>::header_bytes();
 
             if mem::needs_drop::<T>() {
                 let mut cur = (len_ptr as *mut u8).add(header_bytes) as *mut T;
-                let end = cur.add(*len_ptr);
+                let end = cur.add(load(len_ptr));
                 while cur < end {
                     ptr::drop_in_place(cur);
                     cur = cur.add(1);
@@ -1584,14 +1852,14 @@ impl<T> Drop for ThinVec<T> {
             }
 
             let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
-            let layout = Layout::from_size_align(mem::size_of::<T>() * (*(len_ptr.add(1))) + header_bytes, align).unwrap();
+            let layout = Layout::from_size_align(mem::size_of::<T>() * load(len_ptr.add(1)) + header_bytes, align).unwrap();
             alloc::dealloc(self.u.get() as *mut u8, layout);
             self.u = NonZeroUsize::new_unchecked(DANGLE);
         }
     }
 }
 
-impl<T> ThinVec<T> {
+impl<T, L: HeaderLen> ThinVecBase<T, L> {
     /// Transmutes the vector `ThinVec<T>` into another type of vector `ThinVec<X>`.
     /// Consumes the original vector.
     ///
@@ -1659,18 +1927,18 @@ impl<T> ThinVec<T> {
     ///
     /// [Sorting]: #sorting
     ///
-    pub unsafe fn transmute<X>(self) -> ThinVec<X> {
+    pub unsafe fn transmute<X>(self) -> ThinVecBase<X, L> {
         assert_eq!(mem::size_of::<X>(), mem::size_of::<T>());
         assert_eq!(mem::align_of::<X>(), mem::align_of::<T>());
         assert!(!mem::needs_drop::<X>());
         assert!(!mem::needs_drop::<T>());
-        let v: ThinVec<X> = ThinVec { u: self.u, _marker: marker::PhantomData };
+        let v: ThinVecBase<X, L> = ThinVecBase { u: self.u, _marker: marker::PhantomData };
         mem::forget(self);
         v
     }
 }
 
-impl<T, I> Index<I> for ThinVec<T>
+impl<T, I, L: HeaderLen> Index<I> for ThinVecBase<T, L>
     where
         I: SliceIndex<[T]>,
 {
@@ -1682,7 +1950,7 @@ impl<T, I> Index<I> for ThinVec<T>
     }
 }
 
-impl<T, I> IndexMut<I> for ThinVec<T>
+impl<T, I, L: HeaderLen> IndexMut<I> for ThinVecBase<T, L>
     where
         I: SliceIndex<[T]>,
 {
@@ -1692,7 +1960,7 @@ impl<T, I> IndexMut<I> for ThinVec<T>
     }
 }
 
-impl<T> Deref for ThinVec<T> {
+impl<T, L: HeaderLen> Deref for ThinVecBase<T, L> {
     type Target = [T];
 
     fn deref(&self) -> &[T] {
@@ -1704,16 +1972,16 @@ impl<T> Deref for ThinVec<T> {
                 len = 0;
                 arr = NonNull::dangling().as_ptr();
             } else {
-                let len_ptr = self.u.get() as *mut usize;
-                len = *len_ptr;
-                arr = (len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
+                let len_ptr = self.u.get() as *mut L;
+                len = load(len_ptr);
+                arr = (len_ptr as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
             }
             slice::from_raw_parts(arr, len)
         }
     }
 }
 
-impl<T> DerefMut for ThinVec<T> {
+impl<T, L: HeaderLen> DerefMut for ThinVecBase<T, L> {
     fn deref_mut(&mut self) -> &mut [T] {
         unsafe {
             if mem::size_of::<T>() == 0 { return slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), self.len()); }
@@ -1723,9 +1991,9 @@ impl<T> DerefMut for ThinVec<T> {
                 len = 0;
                 arr = NonNull::dangling().as_ptr();
             } else {
-                let len_ptr = self.u.get() as *mut usize;
-                len = *len_ptr;
-                arr = (len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
+                let len_ptr = self.u.get() as *mut L;
+                len = load(len_ptr);
+                arr = (len_ptr as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
             }
             slice::from_raw_parts_mut(arr, len)
         }
@@ -1737,16 +2005,16 @@ macro_rules! __impl_slice_eq1 {
         __impl_slice_eq1! { $Lhs, $Rhs, Sized }
     };
     ($Lhs: ty, $Rhs: ty, $Bound: ident) => {
-        impl<'a, 'b, A: $Bound, B> PartialEq<$Rhs> for $Lhs where A: PartialEq<B> {
+        impl<'a, 'b, A: $Bound, B, L: HeaderLen> PartialEq<$Rhs> for $Lhs where A: PartialEq<B> {
             #[inline]
             fn eq(&self, other: &$Rhs) -> bool { self[..] == other[..] }
         }
     }
 }
 
-__impl_slice_eq1! { ThinVec<A>, ThinVec<B> }
-__impl_slice_eq1! { ThinVec<A>, &'b [B] }
-__impl_slice_eq1! { ThinVec<A>, &'b mut [B] }
+__impl_slice_eq1! { ThinVecBase<A, L>, ThinVecBase<B, L> }
+__impl_slice_eq1! { ThinVecBase<A, L>, &'b [B] }
+__impl_slice_eq1! { ThinVecBase<A, L>, &'b mut [B] }
 //__impl_slice_eq1! { &'b [A], ThinVec<B> }
 //__impl_slice_eq1! { &'b mut [A], ThinVec<B> }
 
@@ -1754,8 +2022,8 @@ macro_rules! array_impls {
     ($($N: expr)+) => {
         $(
             // NOTE: some less important impls are omitted to reduce code bloat
-            __impl_slice_eq1! { ThinVec<A>, [B; $N] }
-            __impl_slice_eq1! { ThinVec<A>, &'b [B; $N] }
+            __impl_slice_eq1! { ThinVecBase<A, L>, [B; $N] }
+            __impl_slice_eq1! { ThinVecBase<A, L>, &'b [B; $N] }
             // __impl_slice_eq1! { Vec<A>, &'b mut [B; $N] }
             // __impl_slice_eq1! { Cow<'a, [A]>, [B; $N], Clone }
             // __impl_slice_eq1! { Cow<'a, [A]>, &'b [B; $N], Clone }
@@ -1771,7 +2039,7 @@ array_impls! {
     30 31 32
 }
 
-impl<T: fmt::Debug> fmt::Debug for ThinVec<T> {
+impl<T: fmt::Debug, L: HeaderLen> fmt::Debug for ThinVecBase<T, L> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(&**self, f)
     }
@@ -1784,14 +2052,14 @@ impl<T: fmt::Debug> fmt::Debug for ThinVec<T> {
 ///
 /// [`ThinVec`]: struct.ThinVec.html
 /// [`IntoIterator`]: ../../std/iter/trait.IntoIterator.html
-pub struct IntoIter<T> {
+pub struct IntoIter<T, L: HeaderLen = usize> {
     buf: *mut u8,
-    _marker: marker::PhantomData<T>,
+    _marker: marker::PhantomData<(T, L)>,
     ptr: *const T,
     end: *const T,
 }
 
-impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
+impl<T: fmt::Debug, L: HeaderLen> fmt::Debug for IntoIter<T, L> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_tuple("IntoIter")
             .field(&self.as_slice())
@@ -1799,9 +2067,9 @@ impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
     }
 }
 
-impl<T> IntoIterator for ThinVec<T> {
+impl<T, L: HeaderLen> IntoIterator for ThinVecBase<T, L> {
     type Item = T;
-    type IntoIter = IntoIter<T>;
+    type IntoIter = IntoIter<T, L>;
 
     /// Creates a consuming iterator, that is, one that moves each value out of
     /// the vector (from start to end). The vector cannot be used after calling
@@ -1821,11 +2089,11 @@ impl<T> IntoIterator for ThinVec<T> {
     /// # }
     /// ```
     #[inline]
-    fn into_iter(mut self) -> IntoIter<T> {
+    fn into_iter(mut self) -> IntoIter<T, L> {
         unsafe {
             if mem::size_of::<T>() == 0 {
                 let ptr = self.as_mut_ptr();
-                let end = (ptr as *const u8).add(self.len()) as *const T;
+                let end = (ptr as *const u8).wrapping_add(self.len()) as *const T;
                 mem::forget(self);
                 return IntoIter {
                     buf: ptr::null_mut(),
@@ -1842,9 +2110,9 @@ impl<T> IntoIterator for ThinVec<T> {
                     end: ptr::null_mut(),
                 };
             }
-            let len_ptr = self.u.get() as *mut u8 as *mut usize;
-            let begin = (len_ptr as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
-            let end = begin.add(*len_ptr) as *const T;
+            let len_ptr = self.u.get() as *mut L;
+            let begin = (len_ptr as *mut u8).add(<ThinVecBase<T, L>>::header_bytes()) as *mut T;
+            let end = begin.add(load(len_ptr)) as *const T;
 
             let buf = len_ptr as *mut u8;
             mem::forget(self);
@@ -1858,7 +2126,7 @@ impl<T> IntoIterator for ThinVec<T> {
     }
 }
 
-impl<'a, T> IntoIterator for &'a ThinVec<T> {
+impl<'a, T, L: HeaderLen> IntoIterator for &'a ThinVecBase<T, L> {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
 
@@ -1867,7 +2135,7 @@ impl<'a, T> IntoIterator for &'a ThinVec<T> {
     }
 }
 
-impl<'a, T> IntoIterator for &'a mut ThinVec<T> {
+impl<'a, T, L: HeaderLen> IntoIterator for &'a mut ThinVecBase<T, L> {
     type Item = &'a mut T;
     type IntoIter = slice::IterMut<'a, T>;
 
@@ -1876,7 +2144,7 @@ impl<'a, T> IntoIterator for &'a mut ThinVec<T> {
     }
 }
 
-impl<T> IntoIter<T> {
+impl<T, L: HeaderLen> IntoIter<T, L> {
     /// Returns the remaining items of this iterator as a slice.
     ///
     /// # Examples
@@ -1922,11 +2190,11 @@ impl<T> IntoIter<T> {
     }
 }
 
-unsafe impl<T: Send> Send for IntoIter<T> {}
+unsafe impl<T: Send, L: HeaderLen> Send for IntoIter<T, L> {}
 
-unsafe impl<T: Sync> Sync for IntoIter<T> {}
+unsafe impl<T: Sync, L: HeaderLen> Sync for IntoIter<T, L> {}
 
-impl<T> Iterator for IntoIter<T> {
+impl<T, L: HeaderLen> Iterator for IntoIter<T, L> {
     type Item = T;
 
     #[inline]
@@ -1936,7 +2204,7 @@ impl<T> Iterator for IntoIter<T> {
                 None
             } else if mem::size_of::<T>() == 0 {
                 // cast to u8, so we add 1, not zero
-                self.ptr = (self.ptr as *mut u8).add(1) as *mut T;
+                self.ptr = (self.ptr as *mut u8).wrapping_add(1) as *mut T;
 
                 // Use a non-null pointer value
                 // (self.ptr might be null because of wrapping)
@@ -1966,14 +2234,14 @@ impl<T> Iterator for IntoIter<T> {
     }
 }
 
-impl<T> DoubleEndedIterator for IntoIter<T> {
+impl<T, L: HeaderLen> DoubleEndedIterator for IntoIter<T, L> {
     #[inline]
     fn next_back(&mut self) -> Option<T> {
         unsafe {
             if self.end == self.ptr {
                 None
             } else if mem::size_of::<T>() == 0 {
-                self.end = (self.end as *const i8).sub(1) as *mut T;
+                self.end = (self.end as *const i8).wrapping_sub(1) as *mut T;
 
                 // Use a non-null pointer value
                 // (self.end might be null because of wrapping)
@@ -1987,13 +2255,13 @@ impl<T> DoubleEndedIterator for IntoIter<T> {
     }
 }
 
-impl<T> ExactSizeIterator for IntoIter<T> {}
+impl<T, L: HeaderLen> ExactSizeIterator for IntoIter<T, L> {}
 
-impl<T> FusedIterator for IntoIter<T> {}
+impl<T, L: HeaderLen> FusedIterator for IntoIter<T, L> {}
 
-impl<T: Clone> Clone for IntoIter<T> {
-    fn clone(&self) -> IntoIter<T> {
-        self.as_slice().to_thinvec().into_iter()
+impl<T: Clone, L: HeaderLen> Clone for IntoIter<T, L> {
+    fn clone(&self) -> IntoIter<T, L> {
+        <ThinVecBase<T, L>>::from(self.as_slice()).into_iter()
     }
 }
 
@@ -2007,27 +2275,17 @@ pub trait IntoThinVec<T> {
 
 impl<T: Clone> ToThinVec<T> for [T] {
     fn to_thinvec(&self) -> ThinVec<T> {
-        let mut vector = ThinVec::with_capacity(self.len());
-        vector.extend_desugared(self.iter().cloned());
-        vector
+        ThinVec::from(self)
     }
 }
 
 impl<T> IntoThinVec<T> for Box<[T]> {
     fn into_thinvec(self) -> ThinVec<T> {
-        unsafe {
-            let len = self.len();
-            let ptr = self.as_ptr();
-            let mut vec = ThinVec::with_capacity(len);
-            ptr::copy(ptr, vec.as_mut_ptr(), len);
-            vec.set_len(len);
-            mem::forget(self); // prevent it from dropping the contents
-            vec
-        }
+        ThinVec::from(self.into_vec())
     }
 }
 
-impl<T> Drop for IntoIter<T> {
+impl<T, L: HeaderLen> Drop for IntoIter<T, L> {
     fn drop(&mut self) {
         if mem::size_of::<T>() == 0 { return; }
         // destroy the remaining elements
@@ -2035,9 +2293,9 @@ impl<T> Drop for IntoIter<T> {
 
         unsafe {
             if !self.buf.is_null() {
-                let len_ptr = self.buf as *mut usize;
+                let len_ptr = self.buf as *mut L;
                 let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
-                let layout = Layout::from_size_align(mem::size_of::<T>() * (*(len_ptr.add(1))) + <ThinVec<T>>::header_bytes(), align).unwrap();
+                let layout = Layout::from_size_align(mem::size_of::<T>() * load(len_ptr.add(1)) + <ThinVecBase<T, L>>::header_bytes(), align).unwrap();
                 alloc::dealloc(self.buf, layout);
                 self.buf = ptr::null_mut();
             }
@@ -2045,63 +2303,238 @@ impl<T> Drop for IntoIter<T> {
     }
 }
 
-impl<T> Extend<T> for ThinVec<T> {
+impl<T, L: HeaderLen> Extend<T> for ThinVecBase<T, L> {
     #[inline]
     fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
         self.extend_desugared(iter.into_iter())
     }
 }
 
-impl<'a, T: 'a + Copy> Extend<&'a T> for ThinVec<T> {
+impl<'a, T: 'a + Copy, L: HeaderLen> Extend<&'a T> for ThinVecBase<T, L> {
     #[inline]
     fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
         self.extend_desugared(iter.into_iter().cloned())
     }
 }
 
-impl<T> Default for ThinVec<T> {
+impl<T, L: HeaderLen> Extend<ThinVecBase<T, L>> for ThinVecBase<T, L> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=ThinVecBase<T, L>>>(&mut self, iter: I) {
+        for mut v in iter {
+            self.append(&mut v);
+        }
+    }
+}
+
+impl<T, L: HeaderLen> Extend<V64<T>> for ThinVecBase<T, L> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=V64<T>>>(&mut self, iter: I) {
+        for mut v in iter {
+            unsafe {
+                self.append_elements(v.as_slice() as _);
+                v.set_len(0);
+            }
+        }
+    }
+}
+
+impl<T, L: HeaderLen> Extend<Vec<T>> for ThinVecBase<T, L> {
+    /// Moves the elements of each vector to the end of this one with a `memcpy`.
+    fn extend<I: IntoIterator<Item=Vec<T>>>(&mut self, iter: I) {
+        for mut v in iter {
+            unsafe {
+                self.append_elements(v.as_slice() as _);
+                v.set_len(0);
+            }
+        }
+    }
+}
+
+// Writes the length back when it goes out of scope, so a panic part way through
+// filling the vector leaves it holding exactly the elements written so far.
+struct SetLenOnDrop<'a, T, L: HeaderLen> {
+    vec: &'a mut ThinVecBase<T, L>,
+    local_len: usize,
+}
+
+impl<'a, T, L: HeaderLen> Drop for SetLenOnDrop<'a, T, L> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { self.vec.set_len(self.local_len); }
+    }
+}
+
+impl<T, L: HeaderLen> Default for ThinVecBase<T, L> {
     /// Creates an empty `ThinVec<T>`.
-    fn default() -> ThinVec<T> {
-        ThinVec::new()
+    fn default() -> ThinVecBase<T, L> {
+        ThinVecBase::new()
+    }
+}
+
+impl<'a, T: Clone, L: HeaderLen> From<&'a [T]> for ThinVecBase<T, L> {
+    fn from(s: &'a [T]) -> ThinVecBase<T, L> {
+        let mut vector = ThinVecBase::with_capacity(s.len());
+        vector.extend_desugared(s.iter().cloned());
+        vector
     }
 }
 
-impl<'a, T: Clone> From<&'a [T]> for ThinVec<T> {
-    fn from(s: &'a [T]) -> ThinVec<T> {
-        s.to_thinvec()
+impl<'a, T: Clone, L: HeaderLen> From<&'a mut [T]> for ThinVecBase<T, L> {
+    fn from(s: &'a mut [T]) -> ThinVecBase<T, L> {
+        ThinVecBase::from(&*s)
     }
 }
 
-impl<'a, T: Clone> From<&'a mut [T]> for ThinVec<T> {
-    fn from(s: &'a mut [T]) -> ThinVec<T> {
-        s.to_thinvec()
+impl<T, L: HeaderLen> From<Box<[T]>> for ThinVecBase<T, L> {
+    fn from(s: Box<[T]>) -> ThinVecBase<T, L> {
+        ThinVecBase::from(s.into_vec())
     }
 }
 
-impl<T> From<Box<[T]>> for ThinVec<T> {
-    fn from(s: Box<[T]>) -> ThinVec<T> {
-        s.into_thinvec()
+impl<T, L: HeaderLen> From<Vec<T>> for ThinVecBase<T, L> {
+    /// Moves the elements without cloning them. When `T` is at least as aligned as `usize`, and
+    /// the capacity fits in the header, the `Vec`'s allocation is grown by the header with
+    /// `realloc` and reused.
+    fn from(mut v: Vec<T>) -> ThinVecBase<T, L> {
+        unsafe {
+            let len = v.len();
+            if mem::size_of::<T>() == 0 {
+                v.set_len(0);
+                let mut out = ThinVecBase::new();
+                out.set_len(len);
+                return out;
+            }
+            if len == 0 {
+                return ThinVecBase::new();
+            }
+            if mem::align_of::<T>() >= mem::align_of::<usize>() && v.capacity() <= L::MAX {
+                let block = vec_into_block::<T, L>(v, <ThinVecBase<T, L>>::header_bytes());
+                return ThinVecBase { u: NonZeroUsize::new_unchecked(block as usize), _marker: marker::PhantomData };
+            }
+            let mut out = ThinVecBase::with_capacity(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), out.as_mut_ptr(), len);
+            v.set_len(0);
+            out.set_len(len);
+            out
+        }
     }
 }
 
+impl<T, L: HeaderLen> From<ThinVecBase<T, L>> for Vec<T> {
+    /// Moves the elements without cloning them. When `T` is at least as aligned as `usize`,
+    /// the heap block is shrunk by the header with `realloc` and reused.
+    fn from(mut v: ThinVecBase<T, L>) -> Vec<T> {
+        unsafe {
+            let len = v.len();
+            if mem::size_of::<T>() == 0 {
+                // zero sized values have no bytes, so reading them from a dangling pointer
+                // hands the len values over to the Vec without allocating
+                v.set_len(0);
+                return (0..len).map(|_| ptr::read(NonNull::dangling().as_ptr())).collect();
+            }
+            if mem::align_of::<T>() >= mem::align_of::<usize>() {
+                if v.u.get() == DANGLE {
+                    return Vec::new();
+                }
+                let block = v.u.get() as *mut u8;
+                mem::forget(v);
+                return block_into_vec::<T, L>(block, <ThinVecBase<T, L>>::header_bytes());
+            }
+            let mut out = Vec::with_capacity(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), out.as_mut_ptr(), len);
+            v.set_len(0);
+            out.set_len(len);
+            out
+        }
+    }
+}
+
+impl<T, L: HeaderLen> From<ThinVecBase<T, L>> for Box<[T]> {
+    fn from(v: ThinVecBase<T, L>) -> Box<[T]> {
+        v.into_boxed_slice()
+    }
+}
+
+impl<T, L: HeaderLen> From<ThinVecBase<T, L>> for Rc<[T]> {
+    /// Moves the elements into the new `Rc` with a single copy.
+    fn from(mut v: ThinVecBase<T, L>) -> Rc<[T]> {
+        unsafe {
+            let len = v.len();
+            let mut rc = Rc::<[T]>::new_uninit_slice(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), Rc::get_mut(&mut rc).unwrap().as_mut_ptr() as *mut T, len);
+            v.set_len(0);
+            rc.assume_init()
+        }
+    }
+}
+
+impl<T, L: HeaderLen> From<ThinVecBase<T, L>> for Arc<[T]> {
+    /// Moves the elements into the new `Arc` with a single copy.
+    fn from(mut v: ThinVecBase<T, L>) -> Arc<[T]> {
+        unsafe {
+            let len = v.len();
+            let mut arc = Arc::<[T]>::new_uninit_slice(len);
+            ptr::copy_nonoverlapping(v.as_ptr(), Arc::get_mut(&mut arc).unwrap().as_mut_ptr() as *mut T, len);
+            v.set_len(0);
+            arc.assume_init()
+        }
+    }
+}
+
+/// Reuses the allocation of a non empty `Vec` of a non zero sized `T` as a `[len, cap, elements]`
+/// heap block, with the elements `header_bytes` into it. The capacity must fit in an `L`.
+///
+/// The block keeps the `Vec`'s alignment, so this is only correct if `align_of::<T>()` is the
+/// alignment the block's owner deallocates with.
+pub(crate) unsafe fn vec_into_block<T, L: HeaderLen>(v: Vec<T>, header_bytes: usize) -> *mut u8 {
+    let mut v = mem::ManuallyDrop::new(v);
+    let len = v.len();
+    let cap = v.capacity();
+    let old_size = cap * mem::size_of::<T>();
+    let new_size = old_size.checked_add(header_bytes).expect("capacity overflow");
+    let old_layout = Layout::from_size_align_unchecked(old_size, mem::align_of::<T>());
+    let block = alloc::realloc(v.as_mut_ptr() as *mut u8, old_layout, new_size);
+    if block.is_null() {
+        alloc::handle_alloc_error(Layout::from_size_align_unchecked(new_size, mem::align_of::<T>()));
+    }
+    ptr::copy(block, block.add(header_bytes), len * mem::size_of::<T>());
+    store(block as *mut L, len);
+    store((block as *mut L).add(1), cap);
+    block
+}
+
+/// The reverse of `vec_into_block`; `block` must be a heap block with the same alignment requirement.
+pub(crate) unsafe fn block_into_vec<T, L: HeaderLen>(block: *mut u8, header_bytes: usize) -> Vec<T> {
+    let len = load(block as *const L);
+    let cap = load((block as *const L).add(1));
+    let new_size = cap * mem::size_of::<T>();
+    ptr::copy(block.add(header_bytes), block, len * mem::size_of::<T>());
+    let old_layout = Layout::from_size_align_unchecked(new_size + header_bytes, mem::align_of::<T>());
+    let p = alloc::realloc(block, old_layout, new_size);
+    if p.is_null() {
+        alloc::handle_alloc_error(Layout::from_size_align_unchecked(new_size, mem::align_of::<T>()));
+    }
+    Vec::from_raw_parts(p as *mut T, len, cap)
+}
+
 impl<'a, T> From<Cow<'a, [T]>> for ThinVec<T> where [T]: ToOwned<Owned=ThinVec<T>> {
     fn from(s: Cow<'a, [T]>) -> ThinVec<T> {
         s.into_owned()
     }
 }
 
-impl<'a> From<&'a str> for ThinVec<u8> {
-    fn from(s: &'a str) -> ThinVec<u8> {
+impl<'a, L: HeaderLen> From<&'a str> for ThinVecBase<u8, L> {
+    fn from(s: &'a str) -> ThinVecBase<u8, L> {
         From::from(s.as_bytes())
     }
 }
 
-impl<T> FromIterator<T> for ThinVec<T> {
+impl<T, L: HeaderLen> FromIterator<T> for ThinVecBase<T, L> {
     #[inline]
-    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinVec<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinVecBase<T, L> {
         let into_iter = iter.into_iter();
         let (lower, _) = into_iter.size_hint();
-        let mut thinvec = ThinVec::with_capacity(lower);
+        let mut thinvec = ThinVecBase::with_capacity(lower);
         thinvec.extend_desugared(into_iter);
         thinvec
     }
@@ -2113,17 +2546,17 @@ impl<T> FromIterator<T> for ThinVec<T> {
 ///
 /// [`drain`]: struct.ThinVec.html#method.drain
 /// [`ThinVec`]: struct.ThinVec.html
-pub struct Drain<'a, T: 'a> {
+pub struct Drain<'a, T: 'a, L: HeaderLen = usize> {
     /// Index of tail to preserve
     tail_start: usize,
     /// Length of tail
     tail_len: usize,
     /// Current remaining range to remove
     iter: slice::Iter<'a, T>,
-    vec: NonNull<ThinVec<T>>,
+    vec: NonNull<ThinVecBase<T, L>>,
 }
 
-impl<'a, T: 'a + fmt::Debug> fmt::Debug for Drain<'a, T> {
+impl<'a, T: 'a + fmt::Debug, L: HeaderLen> fmt::Debug for Drain<'a, T, L> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_tuple("Drain")
             .field(&self.iter.as_slice())
@@ -2131,11 +2564,11 @@ impl<'a, T: 'a + fmt::Debug> fmt::Debug for Drain<'a, T> {
     }
 }
 
-unsafe impl<'a, T: Sync> Sync for Drain<'a, T> {}
+unsafe impl<'a, T: Sync, L: HeaderLen> Sync for Drain<'a, T, L> {}
 
-unsafe impl<'a, T: Send> Send for Drain<'a, T> {}
+unsafe impl<'a, T: Send, L: HeaderLen> Send for Drain<'a, T, L> {}
 
-impl<'a, T> Iterator for Drain<'a, T> {
+impl<'a, T, L: HeaderLen> Iterator for Drain<'a, T, L> {
     type Item = T;
 
     #[inline]
@@ -2148,14 +2581,14 @@ impl<'a, T> Iterator for Drain<'a, T> {
     }
 }
 
-impl<'a, T> DoubleEndedIterator for Drain<'a, T> {
+impl<'a, T, L: HeaderLen> DoubleEndedIterator for Drain<'a, T, L> {
     #[inline]
     fn next_back(&mut self) -> Option<T> {
         self.iter.next_back().map(|elt| unsafe { ptr::read(elt as *const _) })
     }
 }
 
-impl<'a, T> Drop for Drain<'a, T> {
+impl<'a, T, L: HeaderLen> Drop for Drain<'a, T, L> {
     fn drop(&mut self) {
         // exhaust self first
         self.for_each(drop);
@@ -2178,9 +2611,9 @@ impl<'a, T> Drop for Drain<'a, T> {
 }
 
 
-impl<'a, T> ExactSizeIterator for Drain<'a, T> {}
+impl<'a, T, L: HeaderLen> ExactSizeIterator for Drain<'a, T, L> {}
 
-impl<'a, T> FusedIterator for Drain<'a, T> {}
+impl<'a, T, L: HeaderLen> FusedIterator for Drain<'a, T, L> {}
 
 /// A splicing iterator for `ThinVec`.
 ///
@@ -2190,12 +2623,12 @@ impl<'a, T> FusedIterator for Drain<'a, T> {}
 /// [`splice()`]: struct.ThinVec.html#method.splice
 /// [`ThinVec`]: struct.ThinVec.html
 #[derive(Debug)]
-pub struct Splice<'a, I: Iterator + 'a> {
-    drain: Drain<'a, I::Item>,
+pub struct Splice<'a, I: Iterator + 'a, L: HeaderLen = usize> {
+    drain: Drain<'a, I::Item, L>,
     replace_with: I,
 }
 
-impl<'a, I: Iterator> Iterator for Splice<'a, I> {
+impl<'a, I: Iterator, L: HeaderLen> Iterator for Splice<'a, I, L> {
     type Item = I::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -2207,16 +2640,16 @@ impl<'a, I: Iterator> Iterator for Splice<'a, I> {
     }
 }
 
-impl<'a, I: Iterator> DoubleEndedIterator for Splice<'a, I> {
+impl<'a, I: Iterator, L: HeaderLen> DoubleEndedIterator for Splice<'a, I, L> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.drain.next_back()
     }
 }
 
-impl<'a, I: Iterator> ExactSizeIterator for Splice<'a, I> {}
+impl<'a, I: Iterator, L: HeaderLen> ExactSizeIterator for Splice<'a, I, L> {}
 
 
-impl<'a, I: Iterator> Drop for Splice<'a, I> {
+impl<'a, I: Iterator, L: HeaderLen> Drop for Splice<'a, I, L> {
     fn drop(&mut self) {
         self.drain.by_ref().for_each(drop);
 //        unsafe { self.drain.vec.as_ref().debug_i32(); }
@@ -2243,7 +2676,7 @@ impl<'a, I: Iterator> Drop for Splice<'a, I> {
 
             // Collect any remaining elements.
             // This is a zero-length vector which does not allocate if `lower_bound` was exact.
-            let mut collected = self.replace_with.by_ref().collect::<ThinVec<I::Item>>().into_iter();
+            let mut collected = self.replace_with.by_ref().collect::<ThinVecBase<I::Item, L>>().into_iter();
             // Now we have an exact count.
             if collected.len() > 0 {
                 self.drain.move_tail(collected.len());
@@ -2257,7 +2690,7 @@ impl<'a, I: Iterator> Drop for Splice<'a, I> {
 }
 
 /// Private helper methods for `Splice::drop`
-impl<'a, T> Drain<'a, T> {
+impl<'a, T, L: HeaderLen> Drain<'a, T, L> {
     /// The range from `self.vec.len` to `self.tail_start` contains elements
     /// that have been moved out.
     /// Fill that range as much as possible with new elements from the `replace_with` iterator.
@@ -2286,7 +2719,14 @@ impl<'a, T> Drain<'a, T> {
     /// Make room for inserting more elements before the tail.
     unsafe fn move_tail(&mut self, extra_capacity: usize) {
         let vec = self.vec.as_mut();
-        vec.reserve(extra_capacity);
+        // reserve only moves the first len() elements, so count the tail in while it runs
+        let len = vec.len();
+        vec.set_len(self.tail_start + self.tail_len);
+        {
+            let guard = SetLenOnDrop { vec, local_len: len };
+            guard.vec.reserve(extra_capacity);
+        }
+        let vec = self.vec.as_mut();
 
         let new_tail_start = self.tail_start + extra_capacity;
         let src = vec.as_ptr().add(self.tail_start);
@@ -2315,29 +2755,33 @@ impl<T: Clone> CloneIntoThinVec<T> for [T] {
     }
 }
 
-impl<T: Clone> Clone for ThinVec<T> {
-    fn clone(&self) -> ThinVec<T> {
-        <[T]>::to_thinvec(&**self)
+impl<T: Clone, L: HeaderLen> Clone for ThinVecBase<T, L> {
+    fn clone(&self) -> ThinVecBase<T, L> {
+        ThinVecBase::from(&**self)
     }
 
-    fn clone_from(&mut self, other: &ThinVec<T>) {
-        other.as_slice().clone_into_thinvec(self);
+    fn clone_from(&mut self, other: &ThinVecBase<T, L>) {
+        // drop anything that will not be overwritten, and reuse the rest's resources
+        self.truncate(other.len());
+        let len = self.len();
+        self.clone_from_slice(&other[..len]);
+        self.extend_from_slice(&other[len..]);
     }
 }
 
 /// An iterator produced by calling `drain_filter` on ThinVec.
 #[derive(Debug)]
-pub struct DrainFilter<'a, T: 'a, F>
+pub struct DrainFilter<'a, T: 'a, F, L: HeaderLen = usize>
     where F: FnMut(&mut T) -> bool,
 {
-    vec: &'a mut ThinVec<T>,
+    vec: &'a mut ThinVecBase<T, L>,
     idx: usize,
     del: usize,
     old_len: usize,
     pred: F,
 }
 
-impl<'a, T, F> Iterator for DrainFilter<'a, T, F>
+impl<'a, T, F, L: HeaderLen> Iterator for DrainFilter<'a, T, F, L>
     where F: FnMut(&mut T) -> bool,
 {
     type Item = T;
@@ -2370,7 +2814,7 @@ impl<'a, T, F> Iterator for DrainFilter<'a, T, F>
     }
 }
 
-impl<'a, T, F> Drop for DrainFilter<'a, T, F>
+impl<'a, T, F, L: HeaderLen> Drop for DrainFilter<'a, T, F, L>
     where F: FnMut(&mut T) -> bool,
 {
     fn drop(&mut self) {
@@ -2381,7 +2825,7 @@ impl<'a, T, F> Drop for DrainFilter<'a, T, F>
     }
 }
 
-impl<T: PartialEq> ThinVec<T> {
+impl<T: PartialEq, L: HeaderLen> ThinVecBase<T, L> {
     /// Removes consecutive repeated elements in the vector.
     ///
     /// If the vector is sorted, this removes all duplicates.
@@ -2425,13 +2869,13 @@ impl<T: PartialEq> ThinVec<T> {
     }
 }
 
-impl<T> Borrow<[T]> for ThinVec<T> {
+impl<T, L: HeaderLen> Borrow<[T]> for ThinVecBase<T, L> {
     fn borrow(&self) -> &[T] {
         &self[..]
     }
 }
 
-impl<T> BorrowMut<[T]> for ThinVec<T> {
+impl<T, L: HeaderLen> BorrowMut<[T]> for ThinVecBase<T, L> {
     fn borrow_mut(&mut self) -> &mut [T] {
         &mut self[..]
     }
@@ -0,0 +1,89 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # `ThinVec32` a `ThinVec` with a smaller heap header.
+//! The length and capacity are stored as `u32`s, so the header takes 8 bytes instead of 16,
+//! and the vector is limited to `u32::MAX` elements.
+use crate::thin_vec::{self, ThinVecBase};
+
+/// A thin (usize) vector with a `u32` length and capacity. Guaranteed to be a usize-sized smart pointer.
+///
+/// `ThinVec32` differs from `ThinVec` only in its heap header: the length and capacity
+/// are stored as `u32`s in 8 bytes instead of two `usize`s in 16 bytes. This matters when there
+/// are lots of small vectors, for example a `ThinVec32<u16>` of 4 elements uses 16 bytes of heap
+/// instead of 24. Growing the capacity beyond `u32::MAX` elements panics.
+///
+/// Both are the same [`ThinVecBase`] type with a different header type, so `ThinVec32` has all of
+/// `ThinVec`'s methods; see [`ThinVec`] for their documentation.
+///
+/// ```
+/// # #[macro_use] extern crate thincollections;
+/// # use thincollections::thin_vec32::ThinVec32;
+/// # fn main() {
+/// let mut v: ThinVec32<u16> = thinvec32![1, 2, 3];
+/// v.push(4);
+/// v.retain(|x| x % 2 == 0);
+/// assert_eq!(v, [2, 4]);
+/// assert_eq!(std::mem::size_of::<usize>(), std::mem::size_of::<ThinVec32<u16>>());
+/// # }
+/// ```
+///
+/// [`ThinVecBase`]: ../thin_vec/struct.ThinVecBase.html
+/// [`ThinVec`]: ../thin_vec/type.ThinVec.html
+pub type ThinVec32<T> = ThinVecBase<T, u32>;
+
+/// An iterator that moves out of a `ThinVec32`.
+pub type IntoIter<T> = thin_vec::IntoIter<T, u32>;
+
+/// A draining iterator for `ThinVec32<T>`.
+pub type Drain<'a, T> = thin_vec::Drain<'a, T, u32>;
+
+/// A splicing iterator for `ThinVec32`.
+pub type Splice<'a, I> = thin_vec::Splice<'a, I, u32>;
+
+/// An iterator produced by calling `drain_filter` on `ThinVec32`.
+pub type DrainFilter<'a, T, F> = thin_vec::DrainFilter<'a, T, F, u32>;
+
+pub trait ToThinVec32<T> {
+    fn to_thinvec32(&self) -> ThinVec32<T>;
+}
+
+pub trait IntoThinVec32<T> {
+    fn into_thinvec32(self) -> ThinVec32<T>;
+}
+
+impl<T: Clone> ToThinVec32<T> for [T] {
+    fn to_thinvec32(&self) -> ThinVec32<T> {
+        ThinVec32::from(self)
+    }
+}
+
+impl<T> IntoThinVec32<T> for Box<[T]> {
+    fn into_thinvec32(self) -> ThinVec32<T> {
+        ThinVec32::from(self)
+    }
+}
+
+pub trait CloneIntoThinVec32<T> {
+    fn clone_into_thinvec32(&self, target: &mut ThinVec32<T>);
+}
+
+impl<T: Clone> CloneIntoThinVec32<T> for [T] {
+    fn clone_into_thinvec32(&self, target: &mut ThinVec32<T>) {
+        // drop anything in target that will not be overwritten
+        target.truncate(self.len());
+        let len = target.len();
+
+        // reuse the contained values' allocations/resources.
+        target.clone_from_slice(&self[..len]);
+
+        // target.len <= self.len due to the truncate above, so the
+        // slice here is always in-bounds.
+        target.extend_from_slice(&self[len..]);
+    }
+}
@@ -0,0 +1,1033 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+//! # `ThinVecDeque` a double ended queue in a single usize-sized pointer.
+use std::{
+    alloc::{self, Layout},
+    marker, mem, ptr,
+};
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::mem::MaybeUninit;
+use std::num::NonZeroUsize;
+use std::ops::{Index, IndexMut, RangeBounds};
+use std::ops::Bound::*;
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::thin_vec::{DANGLE, ThinVec, ThinVecHeader, ZST_MASK};
+
+/// A double ended queue implemented with a growable ring buffer. Guaranteed to be a
+/// usize-sized pointer.
+///
+/// `ThinVecDeque<T>` is to `VecDeque<T>` what `ThinVec<T>` is to `Vec<T>`: the head, length and
+/// capacity live in the heap block, so a queue that's kept inside a map or another vector only
+/// costs one word. An empty deque doesn't allocate.
+///
+/// # Memory layout
+///
+/// The block has the same length, capacity and element offsets as a `ThinVec<T>`'s block, with
+/// the head index stored in a word in front of it. That's what makes `From<ThinVec<T>>` O(1):
+/// the vector's block is adopted as it is, with an implied head of 0, and only gets moved into a
+/// block with a head word the first time the head has to move (for example on `push_front` or
+/// `pop_front`). `drain` never moves the head of an adopted block; it closes the gap by moving
+/// the elements after the drained range instead.
+///
+/// # Examples
+///
+/// ```
+/// use thincollections::thin_vec_deque::ThinVecDeque;
+/// use std::mem::size_of;
+///
+/// let mut q = ThinVecDeque::new();
+/// q.push_back(2);
+/// q.push_back(3);
+/// q.push_front(1);
+/// assert_eq!(Some(1), q.pop_front());
+/// assert_eq!(Some(&3), q.back());
+/// assert_eq!(size_of::<usize>(), size_of::<ThinVecDeque<u64>>());
+/// ```
+pub struct ThinVecDeque<T> {
+    u: NonZeroUsize,
+    _marker: marker::PhantomData<T>,
+}
+
+// Set on blocks adopted from a ThinVec, which have no head word. Blocks are at least usize
+// aligned, so the low bit of their address is free.
+const ADOPTED: usize = 1;
+
+impl<T> ThinVecDeque<T> {
+    /// Constructs a new, empty `ThinVecDeque<T>`. It doesn't allocate.
+    #[inline]
+    pub fn new() -> ThinVecDeque<T> {
+        unsafe {
+            if mem::size_of::<T>() == 0 {
+                return ThinVecDeque { u: NonZeroUsize::new_unchecked(ZST_MASK), _marker: marker::PhantomData };
+            }
+            ThinVecDeque { u: NonZeroUsize::new_unchecked(DANGLE), _marker: marker::PhantomData }
+        }
+    }
+
+    /// Constructs a new, empty `ThinVecDeque<T>` with room for at least `capacity` elements.
+    /// If `capacity` is 0, the deque will not allocate.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> ThinVecDeque<T> {
+        if capacity == 0 || mem::size_of::<T>() == 0 {
+            return ThinVecDeque::new();
+        }
+        unsafe {
+            ThinVecDeque { u: NonZeroUsize::new_unchecked(<ThinVecDeque<T>>::allocate(capacity)), _marker: marker::PhantomData }
+        }
+    }
+
+    /// Returns the number of elements in the deque.
+    #[inline]
+    pub fn len(&self) -> usize {
+        if mem::size_of::<T>() == 0 { return self.u.get() & (ZST_MASK - 1); }
+        if self.u.get() == DANGLE { 0 } else { unsafe { *(self.block() as *const usize) } }
+    }
+
+    /// Returns `true` if the deque has no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements the deque can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        if mem::size_of::<T>() == 0 { return usize::MAX; }
+        if self.u.get() == DANGLE { 0 } else { unsafe { *(self.block() as *const usize).add(1) } }
+    }
+
+    /// Reserves capacity for at least `additional` more elements. Growth is geometric, like
+    /// `ThinVec`'s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self.capacity();
+        let required = self.required(additional);
+        if required > capacity {
+            self.grow_to(crate::thin_vec::grow_capacity(capacity, required));
+        }
+    }
+
+    /// Reserves capacity for exactly `additional` more elements, if they don't already fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let required = self.required(additional);
+        if required > self.capacity() {
+            self.grow_to(required);
+        }
+    }
+
+    /// Shrinks the capacity to the length. An empty deque frees its block.
+    pub fn shrink_to_fit(&mut self) {
+        let len = self.len();
+        if len == self.capacity() || mem::size_of::<T>() == 0 {
+            return;
+        }
+        if len == 0 {
+            unsafe { self.free_block(); }
+        } else {
+            self.grow_to(len);
+        }
+    }
+
+    /// Shortens the deque to `len` elements, dropping the ones at the back.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            unsafe {
+                let last = self.len() - 1;
+                self.set_len(last);
+                ptr::drop_in_place(self.slot(last));
+            }
+        }
+    }
+
+    /// Removes all the elements, keeping the capacity.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+        unsafe { self.set_head(0); }
+    }
+
+    /// Returns a reference to the element at `index`, counting from the front.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len() { unsafe { Some(&*self.slot(index)) } } else { None }
+    }
+
+    /// Returns a mutable reference to the element at `index`, counting from the front.
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len() { unsafe { Some(&mut *self.slot(index)) } } else { None }
+    }
+
+    /// Returns the first element, or `None` if the deque is empty.
+    #[inline]
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Returns the first element mutably, or `None` if the deque is empty.
+    #[inline]
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    /// Returns the last element, or `None` if the deque is empty.
+    #[inline]
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len().wrapping_sub(1))
+    }
+
+    /// Returns the last element mutably, or `None` if the deque is empty.
+    #[inline]
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        let last = self.len().wrapping_sub(1);
+        self.get_mut(last)
+    }
+
+    /// Appends an element to the back of the deque.
+    #[inline]
+    pub fn push_back(&mut self, value: T) {
+        let len = self.len();
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            ptr::write(self.slot(len), value);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Prepends an element to the front of the deque.
+    #[inline]
+    pub fn push_front(&mut self, value: T) {
+        let len = self.len();
+        if len == self.capacity() {
+            self.reserve(1);
+        }
+        unsafe {
+            let head = self.wrap_sub(self.head(), 1);
+            self.set_head(head);
+            ptr::write(self.slot(0), value);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes the last element and returns it, or `None` if the deque is empty.
+    #[inline]
+    pub fn pop_back(&mut self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        unsafe {
+            self.set_len(len - 1);
+            Some(ptr::read(self.slot(len - 1)))
+        }
+    }
+
+    /// Removes the first element and returns it, or `None` if the deque is empty.
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        unsafe {
+            let value = ptr::read(self.slot(0));
+            let head = if len == 1 { 0 } else { self.wrap_add(self.head(), 1) };
+            self.set_head(head);
+            self.set_len(len - 1);
+            Some(value)
+        }
+    }
+
+    /// Returns `true` if the deque contains an element equal to `x`.
+    pub fn contains(&self, x: &T) -> bool where T: PartialEq {
+        let (a, b) = self.as_slices();
+        a.contains(x) || b.contains(x)
+    }
+
+    /// Returns the elements as two slices: the front of the deque, then the part that wrapped
+    /// around to the start of the buffer, which may be empty.
+    ///
+    /// ```
+    /// use thincollections::thin_vec_deque::ThinVecDeque;
+    ///
+    /// let mut q = ThinVecDeque::with_capacity(4);
+    /// q.push_back(2);
+    /// q.push_back(3);
+    /// q.push_front(1);
+    /// let (a, b) = q.as_slices();
+    /// assert_eq!(a.len() + b.len(), 3);
+    /// assert_eq!([a, b].concat(), [1, 2, 3]);
+    /// ```
+    #[inline]
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        unsafe {
+            let (front, back) = self.slice_lens();
+            let buf = self.buf();
+            (slice::from_raw_parts(buf.add(self.head()), front), slice::from_raw_parts(buf, back))
+        }
+    }
+
+    /// Returns the elements as two mutable slices, see [`as_slices`](#method.as_slices).
+    #[inline]
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        unsafe {
+            let (front, back) = self.slice_lens();
+            let buf = self.buf();
+            (slice::from_raw_parts_mut(buf.add(self.head()), front), slice::from_raw_parts_mut(buf, back))
+        }
+    }
+
+    /// Rearranges the elements so they're in one slice, in order, and returns that slice.
+    ///
+    /// ```
+    /// use thincollections::thin_vec_deque::ThinVecDeque;
+    ///
+    /// let mut q = ThinVecDeque::with_capacity(4);
+    /// q.extend(vec![2, 3, 4]);
+    /// q.push_front(1);
+    /// q.make_contiguous().sort_by(|a, b| b.cmp(a));
+    /// assert_eq!(q.as_slices(), (&[4, 3, 2, 1][..], &[][..]));
+    /// ```
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        unsafe {
+            let (_, back) = self.slice_lens();
+            if back != 0 {
+                // the gap is between the wrapped part and the front, so rotating the whole buffer
+                // by the head lines the elements up from index 0
+                let buf = self.buf() as *mut MaybeUninit<T>;
+                slice::from_raw_parts_mut(buf, self.capacity()).rotate_left(self.head());
+                self.set_head(0);
+            }
+            slice::from_raw_parts_mut(self.buf().add(self.head()), self.len())
+        }
+    }
+
+    /// Rotates the deque `n` places to the left: the first `n` elements move to the back.
+    /// It moves `min(n, len - n)` elements, or none if the deque is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the length.
+    ///
+    /// ```
+    /// use thincollections::thin_vec_deque::ThinVecDeque;
+    ///
+    /// let mut q: ThinVecDeque<_> = (0..5).collect();
+    /// q.rotate_left(2);
+    /// assert!(q.iter().eq(&[2, 3, 4, 0, 1]));
+    /// q.rotate_right(2);
+    /// assert!(q.iter().eq(&[0, 1, 2, 3, 4]));
+    /// ```
+    pub fn rotate_left(&mut self, n: usize) {
+        let len = self.len();
+        assert!(n <= len);
+        if n <= len - n { self.rotate_left_inner(n) } else { self.rotate_right_inner(len - n) }
+    }
+
+    /// Rotates the deque `n` places to the right: the last `n` elements move to the front.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the length.
+    pub fn rotate_right(&mut self, n: usize) {
+        let len = self.len();
+        assert!(n <= len);
+        if n <= len - n { self.rotate_right_inner(n) } else { self.rotate_left_inner(len - n) }
+    }
+
+    /// Removes the elements in `range` and returns them as an iterator. The elements that
+    /// aren't consumed are dropped when the iterator is dropped.
+    ///
+    /// A deque made from a `ThinVec` keeps its block: the elements after the range are moved
+    /// down even when the ones in front of it are fewer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, or if the end is
+    /// greater than the length of the deque.
+    ///
+    /// ```
+    /// use thincollections::thin_vec_deque::ThinVecDeque;
+    ///
+    /// let mut q: ThinVecDeque<_> = (0..6).collect();
+    /// let drained: Vec<_> = q.drain(1..3).collect();
+    /// assert_eq!(drained, [1, 2]);
+    /// assert!(q.iter().eq(&[0, 3, 4, 5]));
+    /// ```
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T> where R: RangeBounds<usize> {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Included(&n) => n,
+            Excluded(&n) => n + 1,
+            Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Included(&n) => n + 1,
+            Excluded(&n) => n,
+            Unbounded => len,
+        };
+        assert!(start <= end);
+        assert!(end <= len);
+        unsafe {
+            // if the Drain is leaked, the deque only keeps the elements in front of the range
+            self.set_len(start);
+        }
+        Drain { deque: self, start, end, idx: start, back: end, orig_len: len }
+    }
+
+    /// Returns a front to back iterator.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (a, b) = self.as_slices();
+        Iter { a: a.iter(), b: b.iter() }
+    }
+
+    /// Returns a front to back iterator that allows modifying each element.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (a, b) = self.as_mut_slices();
+        IterMut { a: a.iter_mut(), b: b.iter_mut() }
+    }
+
+    fn rotate_left_inner(&mut self, n: usize) {
+        let len = self.len();
+        unsafe {
+            let mut head = self.head();
+            if len == self.capacity() {
+                head = self.wrap_add(head, n);
+            } else {
+                // move the front elements, one at a time, into the free slot after the back
+                let buf = self.buf();
+                for _ in 0..n {
+                    ptr::copy_nonoverlapping(buf.add(head), buf.add(self.wrap_add(head, len)), 1);
+                    head = self.wrap_add(head, 1);
+                }
+            }
+            self.set_head(head);
+        }
+    }
+
+    fn rotate_right_inner(&mut self, n: usize) {
+        let len = self.len();
+        unsafe {
+            let mut head = self.head();
+            if len == self.capacity() {
+                head = self.wrap_sub(head, n);
+            } else {
+                let buf = self.buf();
+                for _ in 0..n {
+                    let last = self.wrap_add(head, len - 1);
+                    head = self.wrap_sub(head, 1);
+                    ptr::copy_nonoverlapping(buf.add(last), buf.add(head), 1);
+                }
+            }
+            self.set_head(head);
+        }
+    }
+
+    fn required(&self, additional: usize) -> usize {
+        let required = self.len().checked_add(additional).expect("capacity overflow");
+        if mem::size_of::<T>() == 0 && required >= ZST_MASK {
+            panic!("capacity overflow");
+        }
+        required
+    }
+
+    // moves the elements, in order, into a new block with `capacity` slots
+    fn grow_to(&mut self, capacity: usize) {
+        if mem::size_of::<T>() == 0 {
+            return;
+        }
+        unsafe {
+            let u = <ThinVecDeque<T>>::allocate(capacity);
+            let len = self.len();
+            if len > 0 {
+                let (a, b) = self.as_slices();
+                let dst = (u as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T;
+                ptr::copy_nonoverlapping(a.as_ptr(), dst, a.len());
+                ptr::copy_nonoverlapping(b.as_ptr(), dst.add(a.len()), b.len());
+            }
+            self.free_block();
+            self.u = NonZeroUsize::new_unchecked(u);
+            self.set_len(len);
+        }
+    }
+
+    // copies an adopted block into a block with a head word, keeping every slot where it was
+    #[cold]
+    unsafe fn make_native(&mut self) {
+        let capacity = self.capacity();
+        let len = self.len();
+        let u = <ThinVecDeque<T>>::allocate(capacity);
+        let dst = (u as *mut u8).add(<ThinVec<T>>::header_bytes());
+        ptr::copy_nonoverlapping(self.buf() as *const u8, dst, capacity * mem::size_of::<T>());
+        self.free_block();
+        self.u = NonZeroUsize::new_unchecked(u);
+        self.set_len(len);
+    }
+
+    // frees the block without dropping any elements
+    unsafe fn free_block(&mut self) {
+        if !self.is_heap() {
+            return;
+        }
+        let capacity = self.capacity();
+        if self.u.get() & ADOPTED != 0 {
+            alloc::dealloc(self.block() as *mut u8, <ThinVecDeque<T>>::layout(capacity, 0));
+        } else {
+            let prefix = <ThinVecDeque<T>>::prefix_bytes();
+            alloc::dealloc((self.block() as *mut u8).sub(prefix), <ThinVecDeque<T>>::layout(capacity, prefix));
+        }
+        self.u = NonZeroUsize::new_unchecked(DANGLE);
+    }
+
+    #[cold]
+    fn allocate(capacity: usize) -> usize {
+        unsafe {
+            let prefix = <ThinVecDeque<T>>::prefix_bytes();
+            let layout = <ThinVecDeque<T>>::layout(capacity, prefix);
+            let buffer = alloc::alloc(layout);
+            if buffer.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            let block = buffer.add(prefix) as *mut usize;
+            ptr::write(block.sub(1), 0); // head
+            ptr::write(block, 0); // length
+            ptr::write(block.add(1), capacity);
+            block as usize
+        }
+    }
+
+    // the head word sits in front of the ThinVec style header; padding it to the alignment of T
+    // keeps the elements aligned
+    #[inline(always)]
+    fn prefix_bytes() -> usize {
+        cmp::max(mem::size_of::<usize>(), mem::align_of::<T>())
+    }
+
+    #[inline(always)]
+    fn layout(capacity: usize, prefix: usize) -> Layout {
+        let align = cmp::max(mem::align_of::<usize>(), mem::align_of::<T>());
+        let size = capacity.checked_mul(mem::size_of::<T>())
+            .and_then(|x| x.checked_add(<ThinVec<T>>::header_bytes() + prefix))
+            .expect("capacity overflow");
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[inline(always)]
+    fn is_heap(&self) -> bool {
+        mem::size_of::<T>() != 0 && self.u.get() != DANGLE
+    }
+
+    // the address of the length word
+    #[inline(always)]
+    fn block(&self) -> usize {
+        self.u.get() & !ADOPTED
+    }
+
+    #[inline(always)]
+    fn buf(&self) -> *mut T {
+        if self.is_heap() {
+            unsafe { (self.block() as *mut u8).add(<ThinVec<T>>::header_bytes()) as *mut T }
+        } else {
+            NonNull::dangling().as_ptr()
+        }
+    }
+
+    #[inline(always)]
+    fn head(&self) -> usize {
+        if !self.is_heap() || self.u.get() & ADOPTED != 0 { 0 } else { unsafe { *(self.block() as *const usize).sub(1) } }
+    }
+
+    #[inline(always)]
+    unsafe fn set_head(&mut self, head: usize) {
+        if !self.is_heap() {
+            return;
+        }
+        if self.u.get() & ADOPTED != 0 {
+            if head == 0 {
+                return;
+            }
+            self.make_native();
+        }
+        ptr::write((self.block() as *mut usize).sub(1), head);
+    }
+
+    #[inline(always)]
+    unsafe fn set_len(&mut self, len: usize) {
+        if mem::size_of::<T>() == 0 {
+            self.u = NonZeroUsize::new_unchecked(len | ZST_MASK);
+        } else if self.u.get() != DANGLE {
+            ptr::write(self.block() as *mut usize, len);
+        }
+    }
+
+    // the slot of the element at `index`, counting from the front
+    #[inline(always)]
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { self.buf().add(self.wrap_add(self.head(), index)) }
+    }
+
+    #[inline(always)]
+    fn wrap_add(&self, index: usize, add: usize) -> usize {
+        if mem::size_of::<T>() == 0 { return 0; }
+        let i = index + add;
+        let capacity = self.capacity();
+        if i >= capacity { i - capacity } else { i }
+    }
+
+    #[inline(always)]
+    fn wrap_sub(&self, index: usize, sub: usize) -> usize {
+        if mem::size_of::<T>() == 0 { return 0; }
+        if index >= sub { index - sub } else { index + self.capacity() - sub }
+    }
+
+    // the lengths of the front slice and the wrapped around slice
+    #[inline(always)]
+    fn slice_lens(&self) -> (usize, usize) {
+        let len = self.len();
+        let to_end = self.capacity() - self.head();
+        if len <= to_end { (len, 0) } else { (to_end, len - to_end) }
+    }
+}
+
+impl<T> Drop for ThinVecDeque<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let (a, b) = self.as_mut_slices();
+            let (a, b) = (a as *mut [T], b as *mut [T]);
+            ptr::drop_in_place(a);
+            ptr::drop_in_place(b);
+            self.free_block();
+        }
+    }
+}
+
+impl<T> From<ThinVec<T>> for ThinVecDeque<T> {
+    /// Adopts the vector's heap block, without allocating or moving any element.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate thincollections;
+    /// # use thincollections::thin_vec_deque::ThinVecDeque;
+    /// # fn main() {
+    /// let v = thinvec![1, 2, 3];
+    /// let ptr = v.as_ptr();
+    /// let q = ThinVecDeque::from(v);
+    /// assert_eq!(ptr, q.as_slices().0.as_ptr());
+    /// # }
+    /// ```
+    fn from(v: ThinVec<T>) -> ThinVecDeque<T> {
+        if mem::size_of::<T>() == 0 {
+            let mut q = ThinVecDeque::new();
+            unsafe { q.set_len(v.len()); }
+            mem::forget(v);
+            return q;
+        }
+        let raw = v.into_raw();
+        if raw.is_null() {
+            return ThinVecDeque::new();
+        }
+        unsafe { ThinVecDeque { u: NonZeroUsize::new_unchecked(raw as usize | ADOPTED), _marker: marker::PhantomData } }
+    }
+}
+
+impl<T> From<ThinVecDeque<T>> for ThinVec<T> {
+    /// Hands the block back without copying if it came from a `ThinVec` and the head never
+    /// moved; otherwise copies the elements, in order, into a new vector.
+    fn from(mut q: ThinVecDeque<T>) -> ThinVec<T> {
+        let len = q.len();
+        unsafe {
+            if q.is_heap() && q.u.get() & ADOPTED != 0 {
+                let raw = q.block() as *mut ThinVecHeader;
+                mem::forget(q);
+                return ThinVec::from_raw(raw);
+            }
+            let mut v = ThinVec::with_capacity(len);
+            {
+                let (a, b) = q.as_slices();
+                ptr::copy_nonoverlapping(a.as_ptr(), v.as_mut_ptr(), a.len());
+                ptr::copy_nonoverlapping(b.as_ptr(), v.as_mut_ptr().add(a.len()), b.len());
+            }
+            q.set_len(0);
+            v.set_len(len);
+            v
+        }
+    }
+}
+
+impl<T> Extend<T> for ThinVecDeque<T> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for x in iter {
+            self.push_back(x);
+        }
+    }
+}
+
+impl<'a, T: 'a + Copy> Extend<&'a T> for ThinVecDeque<T> {
+    fn extend<I: IntoIterator<Item=&'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned())
+    }
+}
+
+impl<T> FromIterator<T> for ThinVecDeque<T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> ThinVecDeque<T> {
+        let mut q = ThinVecDeque::new();
+        q.extend(iter);
+        q
+    }
+}
+
+impl<T> Default for ThinVecDeque<T> {
+    #[inline]
+    fn default() -> ThinVecDeque<T> {
+        ThinVecDeque::new()
+    }
+}
+
+impl<T: Clone> Clone for ThinVecDeque<T> {
+    fn clone(&self) -> ThinVecDeque<T> {
+        let mut q = ThinVecDeque::with_capacity(self.len());
+        q.extend(self.iter().cloned());
+        q
+    }
+}
+
+impl<T> Index<usize> for ThinVecDeque<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("Out of bounds access")
+    }
+}
+
+impl<T> IndexMut<usize> for ThinVecDeque<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("Out of bounds access")
+    }
+}
+
+impl<T: PartialEq> PartialEq for ThinVecDeque<T> {
+    fn eq(&self, other: &ThinVecDeque<T>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for ThinVecDeque<T> {}
+
+impl<T: PartialOrd> PartialOrd for ThinVecDeque<T> {
+    fn partial_cmp(&self, other: &ThinVecDeque<T>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for ThinVecDeque<T> {
+    fn cmp(&self, other: &ThinVecDeque<T>) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for ThinVecDeque<T> {
+    /// Hashes the same way as a slice with the same elements, wherever the ring buffer wraps.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for x in self.iter() {
+            x.hash(state);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ThinVecDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> IntoIterator for ThinVecDeque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { deque: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ThinVecDeque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ThinVecDeque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+/// A front to back iterator over the elements of a `ThinVecDeque`.
+///
+/// This `struct` is created by the [`iter`] method on [`ThinVecDeque`].
+///
+/// [`iter`]: struct.ThinVecDeque.html#method.iter
+/// [`ThinVecDeque`]: struct.ThinVecDeque.html
+pub struct Iter<'a, T: 'a> {
+    a: slice::Iter<'a, T>,
+    b: slice::Iter<'a, T>,
+}
+
+impl<'a, T> Clone for Iter<'a, T> {
+    fn clone(&self) -> Iter<'a, T> {
+        Iter { a: self.a.clone(), b: self.b.clone() }
+    }
+}
+
+impl<'a, T: 'a + fmt::Debug> fmt::Debug for Iter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Iter").field(&self.a.as_slice()).field(&self.b.as_slice()).finish()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        match self.a.next() {
+            None => self.b.next(),
+            x => x,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.a.len() + self.b.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a T> {
+        match self.b.next_back() {
+            None => self.a.next_back(),
+            x => x,
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> FusedIterator for Iter<'a, T> {}
+
+/// A front to back iterator over mutable references to the elements of a `ThinVecDeque`.
+///
+/// This `struct` is created by the [`iter_mut`] method on [`ThinVecDeque`].
+///
+/// [`iter_mut`]: struct.ThinVecDeque.html#method.iter_mut
+/// [`ThinVecDeque`]: struct.ThinVecDeque.html
+pub struct IterMut<'a, T: 'a> {
+    a: slice::IterMut<'a, T>,
+    b: slice::IterMut<'a, T>,
+}
+
+impl<'a, T: 'a + fmt::Debug> fmt::Debug for IterMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IterMut").field(&self.a).field(&self.b).finish()
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        match self.a.next() {
+            None => self.b.next(),
+            x => x,
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.a.len() + self.b.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        match self.b.next_back() {
+            None => self.a.next_back(),
+            x => x,
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+impl<'a, T> FusedIterator for IterMut<'a, T> {}
+
+/// An iterator that moves out of a `ThinVecDeque`.
+///
+/// This `struct` is created by the `into_iter` method on [`ThinVecDeque`][`ThinVecDeque`]
+/// (provided by the [`IntoIterator`] trait).
+///
+/// [`ThinVecDeque`]: struct.ThinVecDeque.html
+/// [`IntoIterator`]: https://doc.rust-lang.org/std/iter/trait.IntoIterator.html
+#[derive(Clone)]
+pub struct IntoIter<T> {
+    deque: ThinVecDeque<T>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IntoIter").field(&self.deque).finish()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.deque.pop_front()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.deque.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.deque.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+/// A draining iterator for `ThinVecDeque<T>`.
+///
+/// This `struct` is created by the [`drain`] method on [`ThinVecDeque`].
+///
+/// [`drain`]: struct.ThinVecDeque.html#method.drain
+/// [`ThinVecDeque`]: struct.ThinVecDeque.html
+pub struct Drain<'a, T: 'a> {
+    deque: &'a mut ThinVecDeque<T>,
+    start: usize,
+    end: usize,
+    // the elements in idx..back haven't been yielded yet
+    idx: usize,
+    back: usize,
+    orig_len: usize,
+}
+
+impl<'a, T: 'a + fmt::Debug> fmt::Debug for Drain<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Drain").field(&(self.back - self.idx)).finish()
+    }
+}
+
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.idx == self.back {
+            return None;
+        }
+        self.idx += 1;
+        unsafe { Some(ptr::read(self.deque.slot(self.idx - 1))) }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Drain<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        if self.idx == self.back {
+            return None;
+        }
+        self.back -= 1;
+        unsafe { Some(ptr::read(self.deque.slot(self.back))) }
+    }
+}
+
+impl<'a, T> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        // drop any elements that weren't yielded
+        while self.idx < self.back {
+            self.idx += 1;
+            unsafe { ptr::drop_in_place(self.deque.slot(self.idx - 1)); }
+        }
+
+        // close the gap by moving whichever side of it is shorter, except that an adopted
+        // block always moves its tail so the head stays at 0 and the block isn't copied
+        let drained = self.end - self.start;
+        let tail = self.orig_len - self.end;
+        if drained > 0 {
+            let deque = &mut *self.deque;
+            unsafe {
+                if self.start <= tail && deque.u.get() & ADOPTED == 0 {
+                    for i in (0..self.start).rev() {
+                        ptr::copy_nonoverlapping(deque.slot(i), deque.slot(i + drained), 1);
+                    }
+                    let head = deque.wrap_add(deque.head(), drained);
+                    deque.set_head(head);
+                } else {
+                    for i in self.end..self.orig_len {
+                        ptr::copy_nonoverlapping(deque.slot(i), deque.slot(i - drained), 1);
+                    }
+                }
+            }
+        }
+        unsafe { self.deque.set_len(self.orig_len - drained); }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Drain<'a, T> {}
+
+impl<'a, T> FusedIterator for Drain<'a, T> {}
@@ -0,0 +1,211 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::mem::size_of;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use thincollections::thin_arc_slice::{ThinArcSlice, Weak};
+use thincollections::thin_vec::ThinVec;
+
+struct Counted<'a>(u32, &'a AtomicUsize);
+
+impl<'a> Clone for Counted<'a> {
+    fn clone(&self) -> Self {
+        Counted(self.0, self.1)
+    }
+}
+
+impl<'a> Drop for Counted<'a> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_sizeof() {
+    assert_eq!(size_of::<usize>(), size_of::<ThinArcSlice<u64>>());
+    assert_eq!(size_of::<usize>(), size_of::<Option<ThinArcSlice<u64>>>());
+    assert_eq!(size_of::<usize>(), size_of::<Weak<u64>>());
+}
+
+#[test]
+fn test_from_and_deref() {
+    let v: ThinVec<u32> = thinvec![1, 2, 3];
+    let a = ThinArcSlice::from(v);
+    assert_eq!(&a[..], &[1, 2, 3]);
+    assert_eq!(3, a.len());
+    let b: ThinArcSlice<u32> = ThinArcSlice::from(vec![1, 2, 3]);
+    assert_eq!(a, b);
+    let c: ThinArcSlice<u32> = (1..4).collect();
+    assert_eq!(a, c);
+    let d: ThinArcSlice<u32> = ThinArcSlice::from(&[1, 2, 3][..]);
+    assert_eq!(a, d);
+    assert_eq!(6, a.iter().sum::<u32>());
+    assert_eq!("[1, 2, 3]", format!("{:?}", a));
+    let e: ThinArcSlice<u32> = ThinArcSlice::new();
+    assert!(e.is_empty());
+    assert!(e < a);
+}
+
+#[test]
+fn test_clone_shares() {
+    let a: ThinArcSlice<String> = ThinArcSlice::from(vec!["x".to_string(), "y".to_string()]);
+    let b = a.clone();
+    assert!(ThinArcSlice::ptr_eq(&a, &b));
+    assert_eq!(2, ThinArcSlice::strong_count(&a));
+    drop(b);
+    assert_eq!(1, ThinArcSlice::strong_count(&a));
+    assert_eq!("y", a[1]);
+}
+
+#[test]
+fn test_drops_elements_once() {
+    let drops = AtomicUsize::new(0);
+    {
+        let a: ThinArcSlice<Counted> = ThinArcSlice::from(vec![Counted(1, &drops), Counted(2, &drops)]);
+        let b = a.clone();
+        let w = ThinArcSlice::downgrade(&a);
+        drop(a);
+        assert_eq!(0, drops.load(Ordering::SeqCst));
+        drop(b);
+        assert_eq!(2, drops.load(Ordering::SeqCst));
+        assert!(w.upgrade().is_none());
+    }
+    assert_eq!(2, drops.load(Ordering::SeqCst));
+}
+
+struct PanicOnClone<'a>(u32, &'a AtomicUsize);
+
+impl<'a> Clone for PanicOnClone<'a> {
+    fn clone(&self) -> Self {
+        if self.0 == 3 {
+            panic!("clone of 3");
+        }
+        PanicOnClone(self.0, self.1)
+    }
+}
+
+impl<'a> Drop for PanicOnClone<'a> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_from_slice_clone_panics() {
+    let drops = AtomicUsize::new(0);
+    let src: Vec<PanicOnClone> = (1..6).map(|i| PanicOnClone(i, &drops)).collect();
+    let r = panic::catch_unwind(AssertUnwindSafe(|| ThinArcSlice::from(&src[..])));
+    assert!(r.is_err());
+    // only the two clones made before the panic were dropped
+    assert_eq!(2, drops.load(Ordering::SeqCst));
+    drop(src);
+    assert_eq!(7, drops.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_weak() {
+    let a: ThinArcSlice<u8> = ThinArcSlice::from(vec![1u8, 2]);
+    let w = ThinArcSlice::downgrade(&a);
+    let w2 = w.clone();
+    assert_eq!(2, ThinArcSlice::weak_count(&a));
+    assert_eq!(1, w.strong_count());
+    let up = w2.upgrade().unwrap();
+    assert!(ThinArcSlice::ptr_eq(&a, &up));
+    drop(up);
+    drop(a);
+    assert!(w.upgrade().is_none());
+    assert_eq!(0, w.strong_count());
+    let empty: Weak<u8> = Weak::new();
+    assert!(empty.upgrade().is_none());
+    assert!(empty.clone().upgrade().is_none());
+}
+
+#[test]
+fn test_make_mut() {
+    let mut a: ThinArcSlice<u32> = ThinArcSlice::from(vec![1, 2, 3]);
+    let p = a.as_ptr();
+    ThinArcSlice::make_mut(&mut a)[0] = 5;
+    assert_eq!(p, a.as_ptr());
+
+    let b = a.clone();
+    ThinArcSlice::make_mut(&mut a)[1] = 6;
+    assert_eq!(&a[..], &[5, 6, 3]);
+    assert_eq!(&b[..], &[5, 2, 3]);
+    assert!(!ThinArcSlice::ptr_eq(&a, &b));
+
+    let w = ThinArcSlice::downgrade(&a);
+    ThinArcSlice::make_mut(&mut a)[2] = 7;
+    assert_eq!(&a[..], &[5, 6, 7]);
+    assert!(w.upgrade().is_none());
+}
+
+#[test]
+fn test_make_mut_with_weak_moves() {
+    let drops = AtomicUsize::new(0);
+    {
+        let mut a: ThinArcSlice<Counted> = ThinArcSlice::from(vec![Counted(1, &drops)]);
+        let w = ThinArcSlice::downgrade(&a);
+        ThinArcSlice::make_mut(&mut a)[0].0 = 2;
+        assert_eq!(0, drops.load(Ordering::SeqCst));
+        drop(w);
+        assert_eq!(2, a[0].0);
+    }
+    assert_eq!(1, drops.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_get_mut() {
+    let mut a: ThinArcSlice<u32> = ThinArcSlice::from(vec![1, 2]);
+    ThinArcSlice::get_mut(&mut a).unwrap()[0] = 3;
+    let b = a.clone();
+    assert!(ThinArcSlice::get_mut(&mut a).is_none());
+    drop(b);
+    let w = ThinArcSlice::downgrade(&a);
+    assert!(ThinArcSlice::get_mut(&mut a).is_none());
+    drop(w);
+    assert_eq!(&ThinArcSlice::get_mut(&mut a).unwrap()[..], &[3, 2]);
+}
+
+#[test]
+fn test_zst_and_alignment() {
+    let a: ThinArcSlice<()> = ThinArcSlice::from(vec![(); 10]);
+    assert_eq!(10, a.len());
+    let b: ThinArcSlice<u128> = ThinArcSlice::from(vec![u128::MAX]);
+    assert_eq!(0, b.as_ptr() as usize % std::mem::align_of::<u128>());
+    assert_eq!(u128::MAX, b[0]);
+}
+
+#[test]
+fn test_threads() {
+    let a: ThinArcSlice<u64> = (0..1000).collect();
+    let weak = Arc::new(ThinArcSlice::downgrade(&a));
+    let handles: Vec<_> = (0..4).map(|_| {
+        let a = a.clone();
+        let weak = weak.clone();
+        thread::spawn(move || {
+            let mut sum = 0;
+            for _ in 0..100 {
+                let b = weak.upgrade().unwrap();
+                sum += b.iter().sum::<u64>() + a[999];
+            }
+            sum
+        })
+    }).collect();
+    for h in handles {
+        assert_eq!(100 * (499_500 + 999), h.join().unwrap());
+    }
+    assert_eq!(1, ThinArcSlice::strong_count(&a));
+}
@@ -0,0 +1,149 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::cmp::Reverse;
+use std::mem::size_of;
+
+use thincollections::thin_binary_heap::ThinBinaryHeap;
+use thincollections::thin_binary_heap::V64BinaryHeap;
+use thincollections::thin_vec::ThinVec;
+
+fn is_heap<T: Ord>(data: &[T]) -> bool {
+    (1..data.len()).all(|i| data[(i - 1) / 2] >= data[i])
+}
+
+#[test]
+fn test_size() {
+    assert_eq!(size_of::<usize>(), size_of::<ThinBinaryHeap<u32>>());
+    assert_eq!(8, size_of::<V64BinaryHeap<u32>>());
+}
+
+#[test]
+fn test_push_pop_in_order() {
+    let data = [5, 9, 1, 7, 3, 3, 8, 0, 6, 2, 4];
+    let mut heap = ThinBinaryHeap::new();
+    for x in data.iter() {
+        heap.push(*x);
+        assert!(is_heap(heap.as_slice()));
+    }
+    let mut popped = Vec::new();
+    while let Some(x) = heap.pop() {
+        assert!(is_heap(heap.as_slice()));
+        popped.push(x);
+    }
+    assert_eq!(popped, [9, 8, 7, 6, 5, 4, 3, 3, 2, 1, 0]);
+    assert_eq!(None, heap.peek());
+}
+
+#[test]
+fn test_min_heap() {
+    let mut heap: ThinBinaryHeap<Reverse<u32>> = vec![4, 1, 3].into_iter().map(Reverse).collect();
+    assert_eq!(Some(&Reverse(1)), heap.peek());
+    heap.push(Reverse(0));
+    assert_eq!(Some(Reverse(0)), heap.pop());
+}
+
+#[test]
+fn test_peek_mut() {
+    let mut heap: ThinBinaryHeap<i32> = (0..10).collect();
+    {
+        let top = heap.peek_mut().unwrap();
+        assert_eq!(9, *top);
+    }
+    assert_eq!(Some(&9), heap.peek());
+    *heap.peek_mut().unwrap() = -1;
+    assert!(is_heap(heap.as_slice()));
+    assert_eq!(Some(&8), heap.peek());
+    assert!(ThinBinaryHeap::<i32>::new().peek_mut().is_none());
+}
+
+#[test]
+fn test_sorted_and_vec() {
+    let heap: ThinBinaryHeap<String> = ["pear", "apple", "fig", "kiwi"].iter().map(|s| s.to_string()).collect();
+    assert_eq!(4, heap.clone().into_vec().len());
+    let sorted: ThinVec<String> = heap.into_sorted_vec();
+    assert_eq!(sorted, ["apple", "fig", "kiwi", "pear"]);
+
+    let heap = ThinBinaryHeap::from(thinvec![2, 8, 5]);
+    assert!(is_heap(heap.as_slice()));
+    let v: ThinVec<i32> = heap.into();
+    assert_eq!(3, v.len());
+}
+
+#[test]
+fn test_drain_retain_append() {
+    let mut heap: ThinBinaryHeap<u32> = (0..20).collect();
+    heap.retain(|x| x % 3 == 0);
+    assert!(is_heap(heap.as_slice()));
+    assert_eq!(heap.clone().into_sorted_vec(), [0, 3, 6, 9, 12, 15, 18]);
+
+    let mut small: ThinBinaryHeap<u32> = vec![100, 1].into_iter().collect();
+    heap.append(&mut small);
+    assert!(small.is_empty());
+    assert!(is_heap(heap.as_slice()));
+    assert_eq!(Some(&100), heap.peek());
+
+    let mut big: ThinBinaryHeap<u32> = (50..90).collect();
+    small.append(&mut big);
+    small.append(&mut heap);
+    assert!(is_heap(small.as_slice()));
+    assert_eq!(49, small.len());
+
+    let mut drained: Vec<u32> = small.drain().collect();
+    drained.sort();
+    assert_eq!(49, drained.len());
+    assert!(small.is_empty());
+}
+
+#[test]
+fn test_extend() {
+    let mut heap: V64BinaryHeap<u64> = V64BinaryHeap::new();
+    heap.extend(vec![1, 5]);
+    heap.extend(&[9, 2, 7]);
+    heap.extend(100..300);
+    assert!(is_heap(heap.as_slice()));
+    assert_eq!(205, heap.len());
+    assert_eq!(Some(299), heap.pop());
+}
+
+#[test]
+fn test_v64_inline() {
+    let mut heap: V64BinaryHeap<u8> = V64BinaryHeap::new();
+    for x in [3, 6, 1, 7, 2, 5, 4].iter() {
+        heap.push(*x);
+    }
+    assert_eq!(Some(&7), heap.peek());
+    *heap.peek_mut().unwrap() = 0;
+    assert_eq!(0, heap.clone().into_vec().bytes_on_heap());
+    assert_eq!(heap.into_sorted_vec(), [0, 1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_matches_sort() {
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let mut heap = ThinBinaryHeap::new();
+    let mut expected = Vec::new();
+    for i in 0..2000 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        if i % 3 == 2 {
+            expected.sort();
+            assert_eq!(expected.pop(), heap.pop());
+        } else {
+            heap.push(seed % 1000);
+            expected.push(seed % 1000);
+        }
+    }
+    expected.sort();
+    assert_eq!(heap.into_sorted_vec(), expected.as_slice());
+}
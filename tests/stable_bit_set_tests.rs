@@ -0,0 +1,130 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::collections::BTreeSet;
+
+use thincollections::thin_bit_set::ThinBitSet;
+use thincollections::thin_set::ThinSet;
+
+fn random_sets(seed: &mut u64, count: usize, max: u64) -> (ThinBitSet, BTreeSet<usize>) {
+    let mut bits = ThinBitSet::new();
+    let mut expected = BTreeSet::new();
+    for _ in 0..count {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        let x = (*seed % max) as usize;
+        assert_eq!(expected.insert(x), bits.insert(x));
+    }
+    (bits, expected)
+}
+
+#[test]
+fn test_insert_remove_contains() {
+    let mut s = ThinBitSet::new();
+    assert!(s.is_empty());
+    assert!(s.insert(0));
+    assert!(s.insert(63));
+    assert!(s.insert(64));
+    assert!(!s.insert(63));
+    assert_eq!(3, s.len());
+    assert!(s.contains(64));
+    assert!(!s.contains(65));
+    assert!(!s.contains(100_000));
+    assert!(s.remove(64));
+    assert!(!s.remove(64));
+    assert!(!s.remove(100_000));
+    assert_eq!(1, s.as_words().len());
+    assert_eq!(Some(0), s.first());
+    assert_eq!(Some(63), s.last());
+    s.clear();
+    assert_eq!(None, s.last());
+}
+
+#[test]
+fn test_equality_ignores_history() {
+    let mut a = ThinBitSet::new();
+    a.insert(1000);
+    a.insert(3);
+    a.remove(1000);
+    let b: ThinBitSet = vec![3].into_iter().collect();
+    assert_eq!(a, b);
+    assert_eq!("{3}", format!("{:?}", a));
+}
+
+#[test]
+fn test_rank_select() {
+    let mut seed = 0x1234_5678_9abc_def1u64;
+    let (bits, expected) = random_sets(&mut seed, 500, 5000);
+    for (i, x) in expected.iter().enumerate() {
+        assert_eq!(i, bits.rank(*x));
+        assert_eq!(Some(*x), bits.select(i));
+    }
+    assert_eq!(None, bits.select(expected.len()));
+    assert_eq!(expected.len(), bits.rank(usize::MAX / 2));
+    assert_eq!(0, bits.rank(0));
+}
+
+#[test]
+fn test_set_operations() {
+    let mut seed = 0xdead_beef_cafe_f00du64;
+    for round in 0..20 {
+        let (a, ea) = random_sets(&mut seed, 100, 300 + round * 50);
+        let (b, eb) = random_sets(&mut seed, 100, 600);
+
+        let mut u = a.clone();
+        u.union_with(&b);
+        assert!(u.iter().eq(ea.union(&eb).cloned()));
+
+        let mut i = a.clone();
+        i.intersect_with(&b);
+        assert!(i.iter().eq(ea.intersection(&eb).cloned()));
+        assert_eq!(i.len(), ea.intersection(&eb).count());
+
+        let mut d = a.clone();
+        d.difference_with(&b);
+        assert!(d.iter().eq(ea.difference(&eb).cloned()));
+
+        let mut x = a.clone();
+        x.symmetric_difference_with(&b);
+        assert!(x.iter().eq(ea.symmetric_difference(&eb).cloned()));
+
+        assert_eq!(ea.is_subset(&eb), a.is_subset(&b));
+        assert!(i.is_subset(&a));
+        assert_eq!(ea.is_disjoint(&eb), a.is_disjoint(&b));
+        assert!(d.is_disjoint(&b));
+    }
+}
+
+#[test]
+fn test_thin_set_conversions() {
+    let mut set: ThinSet<u16> = ThinSet::new();
+    set.insert(0);
+    set.insert(7);
+    set.insert(65535);
+    let bits = ThinBitSet::from(set.clone());
+    assert_eq!(bits.iter().collect::<Vec<_>>(), [0, 7, 65535]);
+    let back: ThinSet<u16> = bits.into();
+    assert_eq!(set, back);
+
+    let small: ThinSet<u8> = vec![1u8, 255].into_iter().collect();
+    let bits = ThinBitSet::from(small);
+    assert_eq!(2, bits.len());
+    let back: ThinSet<u8> = bits.into();
+    assert!(back.contains(&255));
+}
+
+#[test]
+#[should_panic]
+fn test_conversion_out_of_range() {
+    let bits: ThinBitSet = vec![256].into_iter().collect();
+    let _: ThinSet<u8> = bits.into();
+}
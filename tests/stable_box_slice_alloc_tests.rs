@@ -0,0 +1,111 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use thincollections::thin_box_slice::ThinBoxSlice;
+use thincollections::thin_vec::ThinVec;
+
+struct CountingAlloc;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Counts {
+    allocations: usize,
+    shrinks: usize,
+    grows: usize,
+}
+
+thread_local! {
+    static COUNTS: Cell<Counts> = const { Cell::new(Counts { allocations: 0, shrinks: 0, grows: 0 }) };
+}
+
+fn count(f: impl FnOnce(&mut Counts)) {
+    COUNTS.with(|c| {
+        let mut counts = c.get();
+        f(&mut counts);
+        c.set(counts);
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(|c| c.allocations += 1);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size < layout.size() {
+            count(|c| c.shrinks += 1);
+        } else {
+            count(|c| c.grows += 1);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// counts the allocator calls made on this thread by f
+fn counts<R>(f: impl FnOnce() -> R) -> (Counts, R) {
+    let before = COUNTS.with(|c| c.get());
+    let r = f();
+    let after = COUNTS.with(|c| c.get());
+    (Counts {
+        allocations: after.allocations - before.allocations,
+        shrinks: after.shrinks - before.shrinks,
+        grows: after.grows - before.grows,
+    }, r)
+}
+
+#[test]
+fn test_from_exact_thinvec_reuses_block() {
+    let mut v: ThinVec<u64> = ThinVec::with_capacity(1000);
+    v.extend(0..1000);
+    let (c, b) = counts(|| ThinBoxSlice::from(v));
+    assert_eq!(Counts { allocations: 0, shrinks: 1, grows: 0 }, c);
+    assert_eq!(499_500, b.iter().sum::<u64>());
+
+    let (c, v) = counts(|| b.into_thinvec());
+    assert_eq!(Counts { allocations: 0, shrinks: 0, grows: 1 }, c);
+    assert_eq!(1000, v.capacity());
+    assert_eq!(&v[..], &(0..1000).collect::<Vec<u64>>()[..]);
+}
+
+#[repr(align(32))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Wide(u8);
+
+#[test]
+fn test_from_exact_thinvec_same_header_size() {
+    // the alignment pads both headers to 32 bytes, so the block is kept as it is
+    let mut v: ThinVec<Wide> = ThinVec::with_capacity(3);
+    v.extend_from_slice(&[Wide(1), Wide(2), Wide(3)]);
+    let (c, b) = counts(|| ThinBoxSlice::from(v));
+    assert_eq!(Counts::default(), c);
+    let (c, v) = counts(|| b.into_thinvec());
+    assert_eq!(Counts::default(), c);
+    assert_eq!(v, [Wide(1), Wide(2), Wide(3)]);
+}
+
+#[test]
+fn test_from_thinvec_with_spare_capacity_copies() {
+    let mut v: ThinVec<u32> = ThinVec::with_capacity(10);
+    v.extend(0..3);
+    let (c, b) = counts(|| ThinBoxSlice::from(v));
+    assert_eq!(Counts { allocations: 1, shrinks: 0, grows: 0 }, c);
+    assert_eq!(&b[..], &[0, 1, 2]);
+}
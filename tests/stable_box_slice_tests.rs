@@ -0,0 +1,149 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::cell::Cell;
+use std::mem::size_of;
+
+use thincollections::thin_box_slice::ThinBoxSlice;
+use thincollections::thin_vec::ThinVec;
+
+thread_local! {
+    static DROPS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Tracked(u32);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        DROPS.with(|d| d.set(d.get() + 1));
+    }
+}
+
+fn drops() -> usize {
+    DROPS.with(|d| d.get())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(align(32))]
+struct Wide(u8);
+
+#[test]
+fn test_sizeof() {
+    assert_eq!(size_of::<usize>(), size_of::<ThinBoxSlice<u64>>());
+    assert_eq!(size_of::<usize>(), size_of::<Option<ThinBoxSlice<u64>>>());
+}
+
+#[test]
+fn test_bytes_on_heap() {
+    let b: ThinBoxSlice<u32> = ThinBoxSlice::new();
+    assert_eq!(0, b.bytes_on_heap());
+    let b: ThinBoxSlice<u32> = ThinBoxSlice::from(vec![1, 2, 3]);
+    assert_eq!(3 * 4 + size_of::<usize>(), b.bytes_on_heap());
+    let b: ThinBoxSlice<Wide> = ThinBoxSlice::from(vec![Wide(1)]);
+    assert_eq!(64, b.bytes_on_heap());
+    assert_eq!(0, b.as_ptr() as usize % 32);
+}
+
+#[test]
+fn test_from_exact_thinvec_round_trip() {
+    // stable_box_slice_alloc_tests checks that the block is reused
+    let mut v: ThinVec<u64> = ThinVec::with_capacity(1000);
+    v.extend(0..1000);
+    let b = ThinBoxSlice::from(v);
+    assert_eq!(1000, b.len());
+    assert_eq!(1000 * 8 + size_of::<usize>(), b.bytes_on_heap());
+    assert_eq!(499_500, b.iter().sum::<u64>());
+    let v = b.into_thinvec();
+    assert_eq!(1000, v.capacity());
+    assert_eq!(&v[..], &(0..1000).collect::<Vec<u64>>()[..]);
+}
+
+#[test]
+fn test_from_exact_thinvec_wide() {
+    let mut v: ThinVec<Wide> = ThinVec::with_capacity(3);
+    v.extend_from_slice(&[Wide(1), Wide(2), Wide(3)]);
+    let p = v.as_ptr();
+    let b = ThinBoxSlice::from(v);
+    assert_eq!(p, b.as_ptr());
+    assert_eq!(&b[..], &[Wide(1), Wide(2), Wide(3)]);
+    let mut v = b.into_thinvec();
+    assert_eq!(p, v.as_ptr());
+    v.push(Wide(4));
+    assert_eq!(4, v.len());
+}
+
+#[test]
+fn test_from_thinvec_with_spare_capacity() {
+    let mut v: ThinVec<String> = ThinVec::with_capacity(10);
+    v.push("a".to_string());
+    v.push("b".to_string());
+    let b = ThinBoxSlice::from(v);
+    assert_eq!(&b[..], &["a".to_string(), "b".to_string()]);
+    assert_eq!(2 * size_of::<String>() + size_of::<usize>(), b.bytes_on_heap());
+    let empty: ThinBoxSlice<String> = ThinBoxSlice::from(ThinVec::with_capacity(10));
+    assert_eq!(0, empty.bytes_on_heap());
+    assert!(empty.into_thinvec().is_empty());
+}
+
+#[test]
+fn test_from_iter_clone_eq() {
+    let b: ThinBoxSlice<i32> = (0..5).collect();
+    let c = b.clone();
+    assert_eq!(b, c);
+    assert_ne!(b.as_ptr(), c.as_ptr());
+    let d: ThinBoxSlice<i32> = ThinBoxSlice::from(&[0, 1, 2, 3, 4][..]);
+    assert_eq!(b, d);
+    let e: ThinBoxSlice<i32> = ThinBoxSlice::from(vec![0, 1, 2, 3, 4].into_boxed_slice());
+    assert_eq!(b, e);
+    assert_eq!("[0, 1, 2, 3, 4]", format!("{:?}", b));
+    assert!(ThinBoxSlice::<i32>::new() < b);
+}
+
+#[test]
+fn test_deref_mut() {
+    let mut b: ThinBoxSlice<i32> = ThinBoxSlice::from(thinvec![5, 3, 4]);
+    b.sort();
+    b[0] = 1;
+    for x in &mut b {
+        *x *= 2;
+    }
+    assert_eq!(&b[..], &[2, 8, 10]);
+    assert_eq!(vec![2, 8, 10], b.into_iter().collect::<Vec<i32>>());
+}
+
+#[test]
+fn test_drops() {
+    let before = drops();
+    {
+        let b: ThinBoxSlice<Tracked> = (0..4).map(Tracked).collect();
+        let c = b.clone();
+        drop(b);
+        assert_eq!(before + 4, drops());
+        let mut v = c.into_thinvec();
+        assert_eq!(before + 4, drops());
+        v.truncate(1);
+        assert_eq!(before + 7, drops());
+    }
+    assert_eq!(before + 8, drops());
+}
+
+#[test]
+fn test_zst() {
+    let b: ThinBoxSlice<()> = ThinBoxSlice::from(thinvec![(), (), ()]);
+    assert_eq!(3, b.len());
+    assert_eq!(0, b.bytes_on_heap());
+    let c = b.clone();
+    let v = c.into_thinvec();
+    assert_eq!(3, v.len());
+    assert_eq!(3, b.into_iter().count());
+}
@@ -0,0 +1,138 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::cmp;
+use std::mem::{align_of, size_of};
+
+use thincollections::thin_vec::{ThinVec, ThinVecHeader};
+
+// These play the part of the C side: they only use the header layout and the offset rule
+// from include/thincollections.h, not any ThinVec methods.
+
+fn c_elements(v: *mut ThinVecHeader, align: usize) -> *mut u8 {
+    if v.is_null() {
+        return std::ptr::null_mut();
+    }
+    unsafe { (v as *mut u8).add(cmp::max(size_of::<ThinVecHeader>(), align)) }
+}
+
+extern "C" fn c_sum_u32(v: *mut ThinVecHeader) -> u64 {
+    if v.is_null() {
+        return 0;
+    }
+    let e = c_elements(v, align_of::<u32>()) as *const u32;
+    let mut sum = 0;
+    unsafe {
+        for i in 0..(*v).len {
+            sum += *e.add(i) as u64;
+        }
+    }
+    sum
+}
+
+extern "C" fn c_keep_even_u64(v: *mut ThinVecHeader) {
+    let e = c_elements(v, align_of::<u64>()) as *mut u64;
+    unsafe {
+        let mut w = 0;
+        for r in 0..(*v).len {
+            if *e.add(r) % 2 == 0 {
+                *e.add(w) = *e.add(r);
+                w += 1;
+            }
+        }
+        (*v).len = w;
+    }
+}
+
+extern "C" fn c_first_u128(v: *mut ThinVecHeader) -> u128 {
+    unsafe { *(c_elements(v, align_of::<u128>()) as *const u128) }
+}
+
+// A Rust callback handed to the C side, taking ownership and giving it back.
+extern "C" fn rust_push_string(v: *mut ThinVecHeader) -> *mut ThinVecHeader {
+    let mut v: ThinVec<String> = unsafe { ThinVec::from_raw(v) };
+    v.push(format!("s{}", v.len()));
+    v.into_raw()
+}
+
+#[test]
+fn test_header_layout() {
+    assert_eq!(2 * size_of::<usize>(), size_of::<ThinVecHeader>());
+    assert_eq!(align_of::<usize>(), align_of::<ThinVecHeader>());
+    let mut v: ThinVec<u16> = ThinVec::with_capacity(5);
+    v.push(7);
+    let p = v.as_ptr();
+    let raw = v.into_raw();
+    unsafe {
+        assert_eq!(ThinVecHeader { len: 1, cap: 5 }, *raw);
+        assert_eq!(p as *mut u16, ThinVecHeader::elements::<u16>(raw));
+        assert_eq!(p as *mut u8, c_elements(raw, align_of::<u16>()));
+        drop(ThinVec::<u16>::from_raw(raw));
+    }
+}
+
+#[test]
+fn test_read_from_c() {
+    let v: ThinVec<u32> = (1..=100).collect();
+    let raw = v.into_raw();
+    assert_eq!(5050, c_sum_u32(raw));
+    let v = unsafe { ThinVec::<u32>::from_raw(raw) };
+    assert_eq!(100, v.len());
+}
+
+#[test]
+fn test_c_lowers_len() {
+    let v: ThinVec<u64> = thinvec![1, 2, 3, 4, 5, 6];
+    let raw = v.into_raw();
+    c_keep_even_u64(raw);
+    let mut v = unsafe { ThinVec::<u64>::from_raw(raw) };
+    assert_eq!(v, [2, 4, 6]);
+    assert_eq!(6, v.capacity());
+    v.push(8);
+    assert_eq!(v, [2, 4, 6, 8]);
+}
+
+#[test]
+fn test_over_aligned_elements() {
+    let v: ThinVec<u128> = thinvec![u128::MAX - 1];
+    let p = v.as_ptr();
+    let raw = v.into_raw();
+    assert_eq!(p as *mut u8, c_elements(raw, align_of::<u128>()));
+    assert_eq!(u128::MAX - 1, c_first_u128(raw));
+    unsafe { drop(ThinVec::<u128>::from_raw(raw)); }
+}
+
+#[test]
+fn test_callback_round_trip() {
+    let mut raw = ThinVec::<String>::new().into_raw();
+    assert!(raw.is_null());
+    for _ in 0..20 {
+        raw = rust_push_string(raw);
+    }
+    let v = unsafe { ThinVec::<String>::from_raw(raw) };
+    assert_eq!(20, v.len());
+    assert_eq!("s19", v[19]);
+}
+
+#[test]
+fn test_empty_and_zst() {
+    let raw = ThinVec::<u32>::new().into_raw();
+    assert!(raw.is_null());
+    assert_eq!(0, c_sum_u32(raw));
+    let v = unsafe { ThinVec::<u32>::from_raw(raw) };
+    assert!(v.is_empty());
+
+    let v: ThinVec<()> = thinvec![(); 4];
+    let raw = v.into_raw();
+    let v = unsafe { ThinVec::<()>::from_raw(raw) };
+    assert_eq!(4, v.len());
+}
@@ -0,0 +1,205 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::cell::Cell;
+use std::mem::size_of;
+
+use thincollections::thin_header_vec::{ThinHeader, ThinHeaderVec};
+
+#[derive(Clone, PartialEq, Debug, Hash)]
+struct Meta {
+    version: u32,
+    name: Option<String>,
+}
+
+impl ThinHeader for Meta {
+    const DEFAULT: &'static Self = &Meta { version: 0, name: None };
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Aligned([u64; 4]);
+
+impl ThinHeader for Aligned {
+    const DEFAULT: &'static Self = &Aligned([0; 4]);
+}
+
+thread_local! {
+    static DROPS: Cell<usize> = Cell::new(0);
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Tracked(u32);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        DROPS.with(|d| d.set(d.get() + 1));
+    }
+}
+
+impl ThinHeader for Tracked {
+    const DEFAULT: &'static Self = &Tracked(0);
+}
+
+fn drops() -> usize {
+    DROPS.with(|d| d.get())
+}
+
+#[test]
+fn test_sizeof() {
+    assert_eq!(size_of::<usize>(), size_of::<ThinHeaderVec<Meta, u8>>());
+    assert_eq!(size_of::<usize>(), size_of::<Option<ThinHeaderVec<Meta, u8>>>());
+}
+
+#[test]
+fn test_default_header_does_not_allocate() {
+    let v: ThinHeaderVec<Meta, u16> = ThinHeaderVec::new();
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(Meta::DEFAULT, v.header());
+    let mut v: ThinHeaderVec<Meta, u16> = ThinHeaderVec::with_header(Meta { version: 0, name: None });
+    assert_eq!(0, v.bytes_on_heap());
+    v.set_header(Meta { version: 0, name: None });
+    assert_eq!(0, v.bytes_on_heap());
+    v.set_header(Meta { version: 2, name: None });
+    assert!(v.bytes_on_heap() > 0);
+    assert_eq!(0, v.capacity());
+    assert_eq!(2, v.header().version);
+}
+
+#[test]
+fn test_header_survives_growth() {
+    let mut v: ThinHeaderVec<Meta, u32> = ThinHeaderVec::with_header(Meta { version: 7, name: Some("adj".to_string()) });
+    for i in 0..1000 {
+        v.push(i);
+    }
+    assert_eq!(1000, v.len());
+    assert_eq!(7, v.header().version);
+    assert_eq!(Some("adj"), v.header().name.as_deref());
+    v.header_mut().version += 1;
+    v.truncate(10);
+    v.shrink_to_fit();
+    assert_eq!(10, v.capacity());
+    assert_eq!(8, v.header().version);
+    assert_eq!(v, (0..10).collect::<Vec<u32>>());
+}
+
+#[test]
+fn test_header_alignment() {
+    let mut v: ThinHeaderVec<Aligned, u8> = ThinHeaderVec::new();
+    v.header_mut().0[3] = 9;
+    v.extend(vec![1u8, 2, 3]);
+    assert_eq!(0, v.header() as *const Aligned as usize % std::mem::align_of::<Aligned>());
+    assert_eq!(Aligned([0, 0, 0, 9]), *v.header());
+    assert_eq!(v, [1, 2, 3]);
+
+    let mut v: ThinHeaderVec<u8, u64> = ThinHeaderVec::with_header(1);
+    v.push(u64::MAX);
+    assert_eq!(0, v.as_ptr() as usize % std::mem::align_of::<u64>());
+    assert_eq!(v, [u64::MAX]);
+}
+
+#[test]
+fn test_drops_header_and_elements() {
+    let before = drops();
+    {
+        let mut v: ThinHeaderVec<Tracked, Tracked> = ThinHeaderVec::with_header(Tracked(1));
+        for i in 0..5 {
+            v.push(Tracked(i));
+        }
+        v.truncate(3);
+        assert_eq!(before + 2, drops());
+        drop(v.remove(0));
+        assert_eq!(before + 3, drops());
+        let mut it = v.into_iter();
+        assert_eq!(1, it.header().0);
+        drop(it.next());
+        assert_eq!(before + 4, drops());
+    }
+    // the remaining element and the header
+    assert_eq!(before + 6, drops());
+}
+
+#[test]
+fn test_shrink_to_fit_frees_default() {
+    let mut v: ThinHeaderVec<u64, u8> = ThinHeaderVec::with_capacity(10);
+    assert!(v.bytes_on_heap() > 0);
+    v.shrink_to_fit();
+    assert_eq!(0, v.bytes_on_heap());
+    v.set_header(5);
+    v.shrink_to_fit();
+    assert!(v.bytes_on_heap() > 0);
+    assert_eq!(5, *v.header());
+}
+
+#[test]
+fn test_vec_operations() {
+    let mut v: ThinHeaderVec<u8, i32> = ThinHeaderVec::with_header(3);
+    v.extend_from_slice(&[5, 1, 4, 1, 1, 3]);
+    v.sort();
+    assert_eq!(v, [1, 1, 1, 3, 4, 5]);
+    v.dedup();
+    assert_eq!(v, [1, 3, 4, 5]);
+    v.insert(1, 2);
+    assert_eq!(5, v.swap_remove(4));
+    v.retain(|&x| x != 3);
+    assert_eq!(v, [1, 2, 4]);
+    let other = v.split_off(1);
+    assert_eq!(v, [1]);
+    assert_eq!(other, [2, 4]);
+    assert_eq!(3, *other.header());
+    let drained: Vec<i32> = ThinHeaderVec::<u8, i32>::from_iter_with(vec![1, 2, 3, 4]).drain(1..3).collect();
+    assert_eq!(vec![2, 3], drained);
+    v.resize(3, 9);
+    assert_eq!(v, [1, 9, 9]);
+    assert_eq!(Some(9), v.pop());
+}
+
+trait FromIterWith {
+    fn from_iter_with(v: Vec<i32>) -> Self;
+}
+
+impl FromIterWith for ThinHeaderVec<u8, i32> {
+    fn from_iter_with(v: Vec<i32>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+#[test]
+fn test_eq_clone_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    fn hash_of<T: Hash>(t: &T) -> u64 {
+        let mut h = DefaultHasher::new();
+        t.hash(&mut h);
+        h.finish()
+    }
+    let mut a: ThinHeaderVec<Meta, u8> = ThinHeaderVec::with_header(Meta { version: 1, name: None });
+    a.push(1);
+    let b = a.clone();
+    assert_eq!(a, b);
+    assert_eq!(hash_of(&a), hash_of(&b));
+    let mut c = b.clone();
+    c.header_mut().version = 2;
+    assert!(a != c);
+    assert_eq!(a, [1]);
+    assert_eq!(c, [1]);
+    assert_eq!("ThinHeaderVec { header: Meta { version: 1, name: None }, elements: [1] }", format!("{:?}", a));
+}
+
+#[test]
+fn test_zst_elements() {
+    let mut v: ThinHeaderVec<u8, ()> = ThinHeaderVec::new();
+    for _ in 0..100 {
+        v.push(());
+    }
+    assert_eq!(100, v.len());
+    assert_eq!(Some(()), v.pop());
+    assert_eq!(99, v.into_iter().count());
+}
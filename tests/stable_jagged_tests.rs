@@ -0,0 +1,108 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use thincollections::thin_jagged::ThinJagged;
+use thincollections::thin_v64::V64;
+use thincollections::thin_vec::ThinVec;
+
+#[test]
+fn test_empty() {
+    let j: ThinJagged<u32> = ThinJagged::new();
+    assert!(j.is_empty());
+    assert_eq!(None, j.get(0));
+    assert_eq!(0, j.rows().count());
+    assert!(j.values().is_empty());
+}
+
+#[test]
+fn test_push_and_rows() {
+    let mut j = ThinJagged::new();
+    j.push_row(vec![1, 2, 3]);
+    j.push_row(Vec::new());
+    j.push_row(thinvec![4]);
+    assert_eq!(3, j.len());
+    assert_eq!(j.row(0), [1, 2, 3]);
+    assert!(j.row(1).is_empty());
+    assert_eq!(j.get(2), Some(&[4][..]));
+    assert_eq!(j.values(), [1, 2, 3, 4]);
+
+    j.extend_row_last(5..7);
+    assert_eq!(j[2], [4, 5, 6]);
+    j.row_mut(0)[1] = 20;
+    j[2][0] = 40;
+    let rows: Vec<&[i32]> = j.rows().collect();
+    assert_eq!(rows, [&[1, 20, 3][..], &[][..], &[40, 5, 6][..]]);
+    assert_eq!(j.rows().rev().next(), Some(&[40, 5, 6][..]));
+    assert_eq!(3, j.rows().len());
+    assert_eq!("[[1, 20, 3], [], [40, 5, 6]]", format!("{:?}", j));
+}
+
+#[test]
+#[should_panic]
+fn test_row_out_of_bounds() {
+    let mut j = ThinJagged::new();
+    j.push_row(vec![1]);
+    j.row(1);
+}
+
+#[test]
+#[should_panic]
+fn test_extend_row_last_without_rows() {
+    let mut j: ThinJagged<u8> = ThinJagged::new();
+    j.extend_row_last(vec![1]);
+}
+
+#[test]
+fn test_pop_row() {
+    let mut j: ThinJagged<String> = vec![vec!["a", "b"], vec!["c"]].into_iter()
+        .map(|r| r.into_iter().map(String::from))
+        .collect();
+    assert_eq!(j.pop_row().unwrap(), ["c"]);
+    assert_eq!(j.pop_row().unwrap(), ["a", "b"]);
+    assert_eq!(None, j.pop_row());
+    assert!(j.values().is_empty());
+}
+
+#[test]
+fn test_freeze_and_thaw() {
+    let rows: ThinVec<ThinVec<String>> = thinvec![
+        thinvec!["x".to_string()],
+        thinvec![],
+        thinvec!["y".to_string(), "z".to_string()]
+    ];
+    let j = ThinJagged::from(rows.clone());
+    assert_eq!(3, j.len());
+    assert_eq!(j[2], ["y", "z"]);
+    let back: ThinVec<ThinVec<String>> = j.into();
+    assert_eq!(back, rows);
+
+    let rows: Vec<V64<u8>> = vec![v64![1, 2], v64![], v64![3, 4, 5, 6, 7, 8, 9, 10]];
+    let j = ThinJagged::from(rows);
+    assert_eq!(j.values(), [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    assert!(j[1].is_empty());
+    assert_eq!(j[2].len(), 8);
+}
+
+#[test]
+fn test_traits() {
+    let a: ThinJagged<u8> = vec![vec![1], vec![2, 3]].into_iter().collect();
+    let mut b = a.clone();
+    assert_eq!(a, b);
+    b.extend(vec![vec![4]]);
+    assert_ne!(a, b);
+    // same values, different rows
+    let c: ThinJagged<u8> = vec![vec![1, 2], vec![3]].into_iter().collect();
+    assert_ne!(a, c);
+    assert_eq!(ThinJagged::<u8>::default(), ThinJagged::new());
+    let total: usize = (&a).into_iter().map(|r| r.len()).sum();
+    assert_eq!(3, total);
+}
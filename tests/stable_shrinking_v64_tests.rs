@@ -0,0 +1,140 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::mem;
+
+use thincollections::thin_shrinking_v64::ShrinkingV64;
+use thincollections::thin_v64::V64;
+
+#[test]
+fn test_size() {
+    assert_eq!(8, mem::size_of::<ShrinkingV64<u8>>());
+    assert_eq!(8, mem::size_of::<Option<ShrinkingV64<u8>>>());
+}
+
+#[test]
+fn test_pop_shrinks_below_half_the_inline_capacity() {
+    let mut v: ShrinkingV64<u16> = ShrinkingV64::from(v64![1, 2, 3, 4, 5, 6]);
+    v.pop();
+    v.pop();
+    v.pop();
+    assert_eq!(v, [1, 2, 3]);
+    assert!(v.bytes_on_heap() > 0);
+    v.pop();
+    assert!(v.bytes_on_heap() > 0);
+    v.pop();
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [1]);
+    v.pop();
+    assert_eq!(None, v.pop());
+    assert!(v.is_empty());
+}
+
+#[test]
+fn test_push_pop_at_the_boundary_keeps_the_heap_block() {
+    let mut v: ShrinkingV64<u8> = (0..8).collect();
+    let ptr = v.as_ptr();
+    for i in 0..10 {
+        assert_eq!(Some(7), v.pop());
+        assert!(v.bytes_on_heap() > 0, "iteration {}", i);
+        v.push(7);
+        assert_eq!(ptr, v.as_ptr());
+    }
+    v.truncate(4);
+    assert!(v.bytes_on_heap() > 0);
+    v.truncate(3);
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [0, 1, 2]);
+}
+
+#[test]
+fn test_removals_shrink() {
+    let mut v: ShrinkingV64<u16> = ShrinkingV64::from(v64![1, 2, 3, 4, 5, 6]);
+    v.remove(0);
+    v.swap_remove(0);
+    assert_eq!(v, [6, 3, 4, 5]);
+    assert!(v.bytes_on_heap() > 0);
+    v.retain(|&x| x == 4);
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [4]);
+
+    let mut v: ShrinkingV64<u16> = (0..10).collect();
+    v.clear();
+    assert_eq!(0, v.bytes_on_heap());
+    v.extend(0..10);
+    v.retain(|&x| x < 5);
+    assert!(v.bytes_on_heap() > 0);
+    v.remove(4);
+    v.swap_remove(0);
+    v.swap_remove(0);
+    assert_eq!(v, [2, 1]);
+    assert!(v.bytes_on_heap() > 0);
+    v.swap_remove(0);
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [1]);
+}
+
+#[test]
+fn test_large_elements_shrink_when_empty() {
+    let mut v: ShrinkingV64<String> = ShrinkingV64::new();
+    v.push(String::from("a"));
+    v.push(String::from("b"));
+    v.pop();
+    assert!(v.bytes_on_heap() > 0);
+    assert_eq!(v, ["a"]);
+    v.pop();
+    assert_eq!(0, v.bytes_on_heap());
+    v.push(String::from("c"));
+    assert_eq!(v, ["c"]);
+}
+
+#[test]
+fn test_zero_sized() {
+    let mut v: ShrinkingV64<()> = ShrinkingV64::new();
+    v.extend(vec![(); 10]);
+    v.truncate(3);
+    assert_eq!(Some(()), v.pop());
+    assert_eq!(2, v.len());
+    v.clear();
+    assert!(v.is_empty());
+}
+
+#[test]
+fn test_v64_does_not_shrink() {
+    let mut v: V64<u16> = v64![1, 2, 3, 4, 5, 6];
+    v.truncate(1);
+    assert!(v.bytes_on_heap() > 0);
+
+    let mut s = ShrinkingV64::from(v);
+    s.pop();
+    assert_eq!(0, s.bytes_on_heap());
+    let mut v: V64<u16> = s.into_v64();
+    v.extend(1..10);
+    v.clear();
+    assert!(v.bytes_on_heap() > 0);
+}
+
+#[test]
+fn test_conversions() {
+    let v: ShrinkingV64<u32> = ShrinkingV64::from(vec![1, 2, 3]);
+    assert_eq!(v, [1, 2, 3]);
+    let c = v.clone();
+    assert_eq!(c, v);
+    assert_eq!("[1, 2, 3]", format!("{:?}", c));
+    let v64: V64<u32> = V64::from(c);
+    assert_eq!(v64, [1, 2, 3]);
+    let collected: Vec<u32> = v.into_iter().collect();
+    assert_eq!(collected, [1, 2, 3]);
+    let s: ShrinkingV64<u32> = ShrinkingV64::from(&[4, 5][..]);
+    assert_eq!(s.as_v64().as_slice(), [4, 5]);
+    assert_eq!(ShrinkingV64::<u32>::default(), ShrinkingV64::new());
+}
@@ -0,0 +1,237 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::cell::Cell;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use thincollections::thin_small_vec::ThinSmallVec;
+use thincollections::thin_vec::ThinVec;
+
+struct DropCounter {
+    count: Rc<Cell<usize>>,
+}
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.count.set(self.count.get() + 1);
+    }
+}
+
+#[test]
+fn test_sizeof() {
+    assert_eq!(size_of::<ThinSmallVec<u32, 3>>(), size_of::<Option<ThinSmallVec<u32, 3>>>());
+    assert_eq!(2 * size_of::<usize>(), size_of::<ThinSmallVec<u8, 1>>());
+    assert_eq!(2 * size_of::<usize>(), size_of::<ThinSmallVec<u8, 0>>());
+    assert_eq!(size_of::<usize>() + 16, size_of::<ThinSmallVec<u32, 3>>());
+}
+
+#[test]
+fn test_inline_then_spill() {
+    let mut v: ThinSmallVec<u32, 3> = ThinSmallVec::new();
+    for i in 0..3 {
+        v.push(i);
+        assert!(!v.spilled());
+        assert_eq!(3, v.capacity());
+    }
+    v.push(3);
+    assert!(v.spilled());
+    assert!(v.capacity() >= 4);
+    for i in 4..100 {
+        v.push(i);
+    }
+    assert_eq!(v, (0..100).collect::<Vec<u32>>());
+    assert_eq!(Some(99), v.pop());
+    assert_eq!(99, v.len());
+}
+
+#[test]
+fn test_zero_inline() {
+    let mut v: ThinSmallVec<String, 0> = ThinSmallVec::new();
+    assert!(!v.spilled());
+    assert_eq!(0, v.capacity());
+    v.push("a".to_string());
+    assert!(v.spilled());
+    v.pop();
+    v.shrink_to_fit();
+    assert!(!v.spilled());
+    assert!(v.is_empty());
+}
+
+#[test]
+fn test_zst() {
+    let mut v: ThinSmallVec<(), 2> = ThinSmallVec::new();
+    for _ in 0..10 {
+        v.push(());
+    }
+    assert!(!v.spilled());
+    assert_eq!(10, v.len());
+    assert_eq!(Some(()), v.pop());
+    assert_eq!(9, v.into_iter().count());
+}
+
+#[test]
+fn test_insert_remove() {
+    let mut v: ThinSmallVec<i32, 4> = ThinSmallVec::new();
+    v.insert(0, 2);
+    v.insert(0, 1);
+    v.insert(2, 4);
+    v.insert(2, 3);
+    assert!(!v.spilled());
+    v.insert(4, 5);
+    assert!(v.spilled());
+    assert_eq!(v, [1, 2, 3, 4, 5]);
+    assert_eq!(1, v.remove(0));
+    assert_eq!(5, v.remove(3));
+    assert_eq!(2, v.swap_remove(0));
+    assert_eq!(v, [4, 3]);
+}
+
+#[test]
+#[should_panic]
+fn test_insert_out_of_bounds() {
+    let mut v: ThinSmallVec<i32, 4> = ThinSmallVec::new();
+    v.insert(1, 1);
+}
+
+#[test]
+#[should_panic]
+fn test_remove_out_of_bounds() {
+    let mut v: ThinSmallVec<i32, 4> = ThinSmallVec::from_slice(&[1]);
+    v.remove(1);
+}
+
+#[test]
+fn test_drops() {
+    let count = Rc::new(Cell::new(0));
+    {
+        let mut v: ThinSmallVec<DropCounter, 2> = ThinSmallVec::new();
+        for _ in 0..5 {
+            v.push(DropCounter { count: count.clone() });
+        }
+        v.truncate(3);
+        assert_eq!(2, count.get());
+        v.retain(|_| false);
+        assert_eq!(5, count.get());
+        v.push(DropCounter { count: count.clone() });
+        v.shrink_to_fit();
+        assert!(!v.spilled());
+        assert_eq!(5, count.get());
+    }
+    assert_eq!(6, count.get());
+
+    count.set(0);
+    {
+        let v: ThinSmallVec<DropCounter, 2> = (0..2).map(|_| DropCounter { count: count.clone() }).collect();
+        let mut it = v.into_iter();
+        drop(it.next());
+        assert_eq!(1, count.get());
+    }
+    assert_eq!(2, count.get());
+}
+
+#[test]
+fn test_drain() {
+    for n in 0..8 {
+        let mut v: ThinSmallVec<i32, 4> = (0..n).collect();
+        let range = (n / 3) as usize..(n - n / 3) as usize;
+        let drained: Vec<i32> = v.drain(range.clone()).collect();
+        let mut expected: Vec<i32> = (0..n).collect();
+        let expected_drained: Vec<i32> = expected.drain(range).collect();
+        assert_eq!(expected_drained, drained);
+        assert_eq!(v, expected);
+    }
+    let mut v: ThinSmallVec<String, 2> = ThinSmallVec::new();
+    v.push("a".to_string());
+    v.push("b".to_string());
+    v.push("c".to_string());
+    {
+        let mut d = v.drain(..2);
+        assert_eq!(Some("a".to_string()), d.next());
+    }
+    assert_eq!(v, ["c".to_string()]);
+}
+
+#[test]
+fn test_dedup_retain() {
+    let mut v: ThinSmallVec<i32, 3> = vec![1, 1, 2, 3, 3, 3, 4].into();
+    v.dedup();
+    assert_eq!(v, [1, 2, 3, 4]);
+    v.retain(|&x| x != 2);
+    assert_eq!(v, [1, 3, 4]);
+    let mut v: ThinSmallVec<i32, 3> = vec![1, 1, 2].into();
+    v.dedup();
+    assert_eq!(v, [1, 2]);
+}
+
+#[test]
+fn test_append_split_off_resize() {
+    let mut a: ThinSmallVec<u8, 4> = ThinSmallVec::from_slice(&[1, 2]);
+    let mut b: ThinSmallVec<u8, 4> = ThinSmallVec::from_slice(&[3, 4, 5]);
+    a.append(&mut b);
+    assert!(b.is_empty());
+    assert_eq!(a, [1, 2, 3, 4, 5]);
+    let c = a.split_off(2);
+    assert_eq!(a, [1, 2]);
+    assert_eq!(c, [3, 4, 5]);
+    assert!(!c.spilled());
+    a.resize(6, 9);
+    assert_eq!(a, [1, 2, 9, 9, 9, 9]);
+    a.resize(1, 0);
+    assert_eq!(a, [1]);
+}
+
+#[test]
+fn test_conversions() {
+    let v: ThinSmallVec<i32, 3> = ThinSmallVec::from([1, 2, 3]);
+    assert!(!v.spilled());
+    let t: ThinVec<i32> = v.clone().into();
+    assert_eq!(t, [1, 2, 3]);
+    let back: ThinSmallVec<i32, 3> = t.into();
+    assert!(back.spilled());
+    assert_eq!(back, v);
+    let vec: Vec<i32> = back.into();
+    assert_eq!(vec, [1, 2, 3]);
+    let other: ThinSmallVec<i32, 8> = vec.into();
+    assert_eq!(other, v);
+}
+
+#[test]
+fn test_into_iter() {
+    let v: ThinSmallVec<String, 2> = vec!["a".to_string(), "b".to_string(), "c".to_string()].into();
+    let mut it = v.into_iter();
+    assert_eq!(3, it.len());
+    assert_eq!(Some("c".to_string()), it.next_back());
+    let clone = it.clone();
+    assert_eq!(&["a".to_string(), "b".to_string()], it.as_slice());
+    assert_eq!(vec!["a".to_string(), "b".to_string()], clone.collect::<Vec<_>>());
+
+    let v: ThinSmallVec<u8, 4> = ThinSmallVec::from_slice(&[1, 2, 3]);
+    let mut it = v.into_iter();
+    it.as_mut_slice()[0] = 7;
+    assert_eq!(vec![7, 2, 3], it.collect::<Vec<_>>());
+}
+
+#[test]
+fn test_eq_ord_hash_debug() {
+    use std::collections::HashSet;
+    let a: ThinSmallVec<i32, 2> = vec![1, 2, 3].into();
+    let b: ThinSmallVec<i32, 4> = vec![1, 2, 3].into();
+    assert_eq!(a, b);
+    assert_eq!(a, vec![1, 2, 3]);
+    assert_eq!(a, &[1, 2, 3][..]);
+    let c: ThinSmallVec<i32, 2> = vec![1, 2, 4].into();
+    assert!(a < c);
+    let mut set = HashSet::new();
+    set.insert(a.clone());
+    assert!(set.contains(&a));
+    assert_eq!("[1, 2, 3]", format!("{:?}", a));
+}
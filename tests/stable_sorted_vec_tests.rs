@@ -0,0 +1,121 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::ops::Bound::{Included, Unbounded};
+
+use thincollections::thin_sorted_vec::SortedThinVec;
+use thincollections::thin_sorted_vec::SortedV64;
+use thincollections::thin_v64::V64;
+use thincollections::thin_vec::ThinVec;
+
+#[test]
+fn test_insert_remove_contains() {
+    let mut s = SortedThinVec::new();
+    for x in [5u32, 1, 9, 3, 7, 3, 1].iter() {
+        s.insert(*x);
+    }
+    assert_eq!(*s, [1, 3, 5, 7, 9]);
+    assert!(s.contains(&7));
+    assert!(!s.contains(&4));
+    assert_eq!(Some(5), s.remove(&5));
+    assert_eq!(None, s.remove(&5));
+    assert_eq!(*s, [1, 3, 7, 9]);
+}
+
+#[test]
+fn test_borrowed_lookup() {
+    let mut s: SortedThinVec<String> = SortedThinVec::new();
+    s.insert("b".to_string());
+    s.insert("a".to_string());
+    assert!(s.contains("a"));
+    assert_eq!(Some("b".to_string()), s.remove("b"));
+    assert_eq!(s.range::<str, _>((Included("a"), Unbounded)), ["a"]);
+}
+
+#[test]
+fn test_range() {
+    let s: SortedThinVec<i32> = (0..10).map(|x| x * 2).collect();
+    assert_eq!(s.range(3..9), [4, 6, 8]);
+    assert_eq!(s.range(4..=8), [4, 6, 8]);
+    assert_eq!(s.range(..4), [0, 2]);
+    assert_eq!(s.range(15..), [16, 18]);
+    assert_eq!(s.range(..), s.as_slice());
+    assert!(s.range(7..3).is_empty());
+    assert!(s.range(100..).is_empty());
+}
+
+#[test]
+fn test_from_iter_sorts_and_dedups() {
+    let s: SortedV64<u8> = vec![3, 3, 2, 1, 2].into_iter().collect();
+    assert_eq!(*s, [1, 2, 3]);
+    let s = SortedThinVec::from(thinvec![4, 2, 4, 0]);
+    assert_eq!(ThinVec::from(s), [0, 2, 4]);
+}
+
+#[test]
+fn test_merge() {
+    let mut a: SortedThinVec<String> = ["b", "d", "f"].iter().map(|s| s.to_string()).collect();
+    let b: SortedThinVec<String> = ["a", "d", "g"].iter().map(|s| s.to_string()).collect();
+    a.merge(b);
+    assert_eq!(*a, ["a", "b", "d", "f", "g"]);
+
+    // disjoint and ordered: appended
+    let c: SortedThinVec<String> = ["x", "y"].iter().map(|s| s.to_string()).collect();
+    a.merge(c);
+    assert_eq!(*a, ["a", "b", "d", "f", "g", "x", "y"]);
+
+    let mut e = SortedThinVec::new();
+    e.merge(a.clone());
+    assert_eq!(e, a);
+    e.merge(SortedThinVec::new());
+    assert_eq!(e, a);
+}
+
+#[test]
+fn test_union_intersection() {
+    let a: SortedV64<u32> = vec![1, 3, 5, 7].into_iter().collect();
+    let b: SortedV64<u32> = vec![2, 3, 4, 7, 8].into_iter().collect();
+    assert_eq!(*a.union(&b), [1, 2, 3, 4, 5, 7, 8]);
+    assert_eq!(*a.intersection(&b), [3, 7]);
+    assert_eq!(*a.intersection(&SortedV64::new()), []);
+    assert_eq!(a.union(&SortedV64::new()), a);
+}
+
+#[test]
+fn test_extend_and_retain() {
+    let mut s: SortedV64<i64> = vec![10, 20].into_iter().collect();
+    s.extend(vec![15, 5, 20, 25]);
+    assert_eq!(*s, [5, 10, 15, 20, 25]);
+    s.retain(|x| x % 10 == 0);
+    assert_eq!(*s, [10, 20]);
+    let v: V64<i64> = s.into_inner();
+    assert_eq!(v, [10, 20]);
+}
+
+#[test]
+fn test_small_v64_stays_inline() {
+    let mut s: SortedV64<u8> = SortedV64::new();
+    for x in (0..7).rev() {
+        s.insert(x);
+    }
+    assert_eq!(*s, [0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(0, s.into_inner().bytes_on_heap());
+}
+
+#[test]
+fn test_debug_and_ord() {
+    let a: SortedThinVec<u8> = vec![2, 1].into_iter().collect();
+    let b: SortedThinVec<u8> = vec![1, 3].into_iter().collect();
+    assert_eq!("{1, 2}", format!("{:?}", a));
+    assert!(a < b);
+    assert_eq!(vec![1, 2], a.into_iter().collect::<Vec<_>>());
+}
@@ -0,0 +1,156 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::collections::HashSet;
+use std::mem::size_of;
+
+use thincollections::thin_sparse_set::{ThinSparseMap, ThinSparseSet};
+
+#[test]
+fn test_size_and_empty() {
+    assert_eq!(2 * size_of::<usize>(), size_of::<ThinSparseSet>());
+    let set = ThinSparseSet::new();
+    assert!(set.is_empty());
+    assert!(!set.contains(0));
+    assert!(!set.contains(u32::MAX));
+    assert_eq!(None, set.iter().next());
+}
+
+#[test]
+fn test_insert_remove_order() {
+    let mut set = ThinSparseSet::new();
+    for id in &[9, 2, 40, 0, 7] {
+        assert!(set.insert(*id));
+    }
+    assert!(!set.insert(40));
+    assert_eq!(set.as_slice(), [9, 2, 40, 0, 7]);
+
+    // the last id fills the hole
+    assert!(set.remove(2));
+    assert!(!set.remove(2));
+    assert!(!set.remove(1000));
+    assert_eq!(set.as_slice(), [9, 7, 40, 0]);
+    assert!(set.remove(0));
+    assert_eq!(set.as_slice(), [9, 7, 40]);
+    assert!(set.contains(7));
+    assert!(!set.contains(0));
+}
+
+#[test]
+fn test_clear_leaves_stale_entries() {
+    let mut set: ThinSparseSet = (0..100).collect();
+    set.clear();
+    assert!(set.is_empty());
+    for id in 0..100 {
+        assert!(!set.contains(id));
+    }
+    // refill in a different order, over the stale sparse entries
+    set.extend(&[50, 3, 99]);
+    assert_eq!(set.as_slice(), [50, 3, 99]);
+    assert!(set.contains(3));
+    assert!(!set.contains(0));
+    set.shrink_to_fit();
+    assert!(set.contains(99));
+    assert!(!set.contains(98));
+}
+
+#[test]
+fn test_set_operations() {
+    let a: ThinSparseSet = vec![1, 2, 3, 4].into_iter().collect();
+    let b: ThinSparseSet = vec![6, 4, 2].into_iter().collect();
+    assert_eq!(a.union(&b).cloned().collect::<Vec<_>>(), [1, 2, 3, 4, 6]);
+    assert_eq!(a.intersection(&b).cloned().collect::<Vec<_>>(), [2, 4]);
+    assert_eq!(b.intersection(&a).cloned().collect::<Vec<_>>(), [4, 2]);
+    assert_eq!(a.difference(&b).cloned().collect::<Vec<_>>(), [1, 3]);
+    assert_eq!(a.symmetric_difference(&b).cloned().collect::<Vec<_>>(), [1, 3, 6]);
+    assert_eq!("[2, 4]", format!("{:?}", a.intersection(&b)));
+    assert!(!a.is_disjoint(&b));
+    assert!(a.is_disjoint(&vec![0, 5].into_iter().collect()));
+    assert!(a.intersection(&b).cloned().collect::<ThinSparseSet>().is_subset(&a));
+
+    let c: ThinSparseSet = vec![4, 3, 2, 1].into_iter().collect();
+    assert_eq!(a, c);
+    assert_ne!(a, b);
+    assert_eq!("{1, 2, 3, 4}", format!("{:?}", a));
+}
+
+#[test]
+fn test_map() {
+    let mut map = ThinSparseMap::new();
+    assert_eq!(None, map.insert(5, "five".to_string()));
+    assert_eq!(None, map.insert(1, "one".to_string()));
+    assert_eq!(None, map.insert(8, "eight".to_string()));
+    assert_eq!(Some("one".to_string()), map.insert(1, "uno".to_string()));
+    assert_eq!(3, map.len());
+    assert_eq!(Some(&"uno".to_string()), map.get(1));
+    map.get_mut(8).unwrap().push('!');
+
+    assert_eq!(Some("five".to_string()), map.remove(5));
+    assert_eq!(None, map.remove(5));
+    assert_eq!(map.keys().cloned().collect::<Vec<_>>(), [8, 1]);
+    assert_eq!(map.values().cloned().collect::<Vec<_>>(), ["eight!", "uno"]);
+    assert!(!map.contains_key(5));
+    for (_, v) in map.iter_mut() {
+        v.make_ascii_uppercase();
+    }
+    assert_eq!(r#"{8: "EIGHT!", 1: "UNO"}"#, format!("{:?}", map));
+
+    let other: ThinSparseMap<()> = vec![(1, ()), (2, ())].into_iter().collect();
+    assert_eq!(map.union(&other).cloned().collect::<Vec<_>>(), [8, 1, 2]);
+    assert_eq!(map.intersection(&other).cloned().collect::<Vec<_>>(), [1]);
+    assert_eq!(map.difference(&other).cloned().collect::<Vec<_>>(), [8]);
+    assert_eq!(map.symmetric_difference(&other).cloned().collect::<Vec<_>>(), [8, 2]);
+
+    let same: ThinSparseMap<String> = map.iter().rev().map(|(k, v)| (*k, v.clone())).collect();
+    assert_eq!(map, same);
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(None, map.get(8));
+}
+
+#[test]
+fn test_matches_hash_set() {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let mut set = ThinSparseSet::new();
+    let mut map = ThinSparseMap::new();
+    let mut expected = HashSet::new();
+    for _ in 0..20_000 {
+        let r = next();
+        let id = (r >> 16) as u32 % 500;
+        match r % 7 {
+            0..=2 => {
+                assert_eq!(expected.insert(id), set.insert(id));
+                map.insert(id, id * 2);
+            }
+            3..=5 => {
+                let removed = expected.remove(&id);
+                assert_eq!(removed, set.remove(id));
+                assert_eq!(removed, map.remove(id).is_some());
+            }
+            _ if r & 0xff00 == 0 => {
+                expected.clear();
+                set.clear();
+                map.clear();
+            }
+            _ => assert_eq!(expected.contains(&id), set.contains(id)),
+        }
+        assert_eq!(expected.len(), set.len());
+    }
+    assert_eq!(expected, set.iter().cloned().collect());
+    assert_eq!(set.as_slice(), map.keys().as_slice());
+    assert!(map.iter().all(|(k, v)| *v == k * 2));
+}
@@ -29,6 +29,96 @@ impl ThinSentinel for Color {
     const SENTINEL_ONE: Self = Color { r: 0, g: 0, b: 1 };
 }
 
+#[test]
+fn entry_grows_the_table() {
+    let mut map: ThinMap<u32, u32> = ThinMap::new();
+    for i in 0..1000 {
+        *map.entry(i).or_insert(0) += 1;
+        *map.entry(i % 7).or_default() += 1;
+    }
+    assert_eq!(1000, map.len());
+    assert_eq!(Some(&143), map.get(&6));
+    assert_eq!(Some(&1), map.get(&999));
+}
+
+#[test]
+fn entry_on_a_full_table_only_grows_for_new_keys() {
+    let mut map: ThinMap<u32, u32> = ThinMap::new();
+    map.insert(2, 2);
+    let mut next = 3;
+    while map.len() < map.capacity() {
+        map.entry(next).or_insert(next);
+        next += 1;
+    }
+    let capacity = map.capacity();
+    for key in 2..next {
+        *map.entry(key).or_insert(0) += 1;
+    }
+    assert_eq!(capacity, map.capacity());
+    assert_eq!(Some(&3), map.get(&2));
+
+    map.entry(next).or_insert(0);
+    assert!(map.capacity() > capacity);
+    assert_eq!(capacity + 1, map.len());
+    assert_eq!(Some(&next), map.get(&(next - 1)));
+}
+
+#[test]
+fn hash_is_order_independent() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash<T: Hash>(t: &T) -> u64 {
+        let mut h = DefaultHasher::new();
+        t.hash(&mut h);
+        h.finish()
+    }
+
+    let a: ThinMap<u32, u64> = (0..300).map(|k| (k, k as u64 * 3)).collect();
+    let b: ThinMap<u32, u64> = (0..300).rev().map(|k| (k, k as u64 * 3)).collect();
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+
+    let mut c = b.clone();
+    c.insert(7, 0);
+    assert_ne!(hash(&a), hash(&c));
+    // swapping two values keeps the same multiset of keys and of values
+    let mut d = b.clone();
+    d.insert(1, 6);
+    d.insert(2, 3);
+    assert_ne!(hash(&a), hash(&d));
+
+    let e: ThinMap<u64, u64> = vec![(1, 2)].into_iter().collect();
+    let f: ThinMap<u64, u64> = vec![(2, 1)].into_iter().collect();
+    assert_ne!(hash(&e), hash(&f));
+}
+
+#[test]
+fn compare_and_convert() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let a: ThinMap<u32, char> = vec![(0, 'a'), (1, 'b'), (7, 'c')].into_iter().collect();
+    let b: ThinMap<u32, char, TrivialOneFieldHasherBuilder> =
+        vec![(7, 'c'), (1, 'b'), (0, 'a')].into_iter().collect();
+    assert_eq!(a, b);
+    assert_eq!(b, a);
+
+    let std: HashMap<u32, char> = vec![(0, 'a'), (1, 'b'), (7, 'c')].into_iter().collect();
+    assert_eq!(a, std);
+    let sorted: BTreeMap<u32, char> = std.iter().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(a, sorted);
+    let mut other = std.clone();
+    other.insert(1, 'x');
+    assert_ne!(a, other);
+    other.remove(&1);
+    assert_ne!(a, other);
+
+    let thin: ThinMap<u32, char> = ThinMap::from(std.clone());
+    assert_eq!(thin, std);
+    let std2: HashMap<u32, char> = thin.into();
+    assert_eq!(std, std2);
+}
+
 #[test]
 fn custom_key()
 {
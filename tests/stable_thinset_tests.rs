@@ -0,0 +1,437 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+
+use thincollections::thin_hasher::TrivialOneFieldHasherBuilder;
+use thincollections::thin_sentinel::{ThinSentinel, ThinSentinelEnum};
+use thincollections::thin_set::{self, Entry, ThinSet};
+use thincollections::thin_v64str::V64Str;
+
+fn set(values: &[i32]) -> ThinSet<i32> {
+    values.iter().cloned().collect()
+}
+
+fn sorted(set: &ThinSet<i32>) -> Vec<i32> {
+    let mut v: Vec<_> = set.iter().cloned().collect();
+    v.sort();
+    v
+}
+
+// 0 and 1 are the sentinels for i32, so they're in every case below
+#[test]
+fn test_in_place_set_algebra() {
+    let a = set(&[0, 1, 2, 3, 10]);
+    let b = set(&[1, 3, 4, 5, 6, 7]);
+
+    let mut s = a.clone();
+    s.union_with(&b);
+    assert_eq!(sorted(&s), [0, 1, 2, 3, 4, 5, 6, 7, 10]);
+
+    let mut s = a.clone();
+    s.intersect_with(&b);
+    assert_eq!(sorted(&s), [1, 3]);
+    // the larger side is the receiver
+    let mut s = b.clone();
+    s.intersect_with(&a);
+    assert_eq!(sorted(&s), [1, 3]);
+
+    let mut s = a.clone();
+    s.difference_with(&b);
+    assert_eq!(sorted(&s), [0, 2, 10]);
+    let mut s = b.clone();
+    s.difference_with(&set(&[0, 1]));
+    assert_eq!(sorted(&s), [3, 4, 5, 6, 7]);
+
+    let mut s = a.clone();
+    s.symmetric_difference_with(&b);
+    assert_eq!(sorted(&s), [0, 2, 4, 5, 6, 7, 10]);
+
+    let mut s = a.clone();
+    s.intersect_with(&ThinSet::new());
+    assert!(s.is_empty());
+}
+
+#[test]
+fn test_assign_operators() {
+    let a = set(&[0, 1, 2, 3]);
+    let b = set(&[1, 3, 5]);
+
+    let mut s = a.clone();
+    s |= &b;
+    assert_eq!(s, &a | &b);
+    let mut s = a.clone();
+    s |= b.clone();
+    assert_eq!(s, &a | &b);
+
+    let mut s = a.clone();
+    s &= &b;
+    assert_eq!(s, &a & &b);
+    let mut s = a.clone();
+    s &= b.clone();
+    assert_eq!(s, &a & &b);
+
+    let mut s = a.clone();
+    s -= &b;
+    assert_eq!(s, &a - &b);
+    let mut s = a.clone();
+    s -= b.clone();
+    assert_eq!(s, &a - &b);
+
+    let mut s = a.clone();
+    s ^= &b;
+    assert_eq!(s, &a ^ &b);
+    let mut s = a.clone();
+    s ^= b.clone();
+    assert_eq!(s, &a ^ &b);
+}
+
+#[test]
+fn test_assign_operators_move_values() {
+    fn strs(values: &[&str]) -> ThinSet<V64Str> {
+        values.iter().map(|s| V64Str::from(*s)).collect()
+    }
+
+    let mut a = strs(&["x", "y"]);
+    a |= strs(&["y", "z", "w"]);
+    a ^= strs(&["x", "v"]);
+    a &= strs(&["v", "w", "y", "q"]);
+    let mut v: Vec<_> = a.iter().map(|s| s.as_str()).collect();
+    v.sort();
+    assert_eq!(v, ["v", "w", "y"]);
+}
+
+#[test]
+fn test_assign_operators_keep_own_values() {
+    // ids 0 and 1 are the sentinels, which the set keeps as its own constants
+    fn tagged(ids: &[u32], tag: char) -> ThinSet<Tagged> {
+        ids.iter().map(|&id| Tagged { id: id + 10, tag }).collect()
+    }
+    fn tags(set: &ThinSet<Tagged>) -> Vec<(u32, char)> {
+        let mut v: Vec<_> = set.iter().map(|t| (t.id - 10, t.tag)).collect();
+        v.sort();
+        v
+    }
+
+    // the right hand side is the larger set
+    let mut s = tagged(&[1, 2, 3], 'a');
+    s |= tagged(&[2, 3, 4, 5, 6], 'b');
+    assert_eq!(tags(&s), [(1, 'a'), (2, 'a'), (3, 'a'), (4, 'b'), (5, 'b'), (6, 'b')]);
+
+    let mut s = tagged(&[1, 2, 3], 'a');
+    s &= tagged(&[2, 3, 4, 5, 6], 'b');
+    assert_eq!(tags(&s), [(2, 'a'), (3, 'a')]);
+
+    // and the smaller one
+    let mut s = tagged(&[0, 1, 2, 3, 4, 5], 'a');
+    s &= tagged(&[1, 3, 7], 'b');
+    assert_eq!(tags(&s), [(1, 'a'), (3, 'a')]);
+
+    let mut s = tagged(&[0, 1, 2, 3, 4, 5], 'a');
+    s.intersect_with(&tagged(&[0, 4, 7], 'b'));
+    assert_eq!(tags(&s), [(0, 'a'), (4, 'a')]);
+}
+
+#[test]
+fn test_matches_hash_set() {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for round in 0..200 {
+        let mut make = |n: u64| -> (ThinSet<i32>, HashSet<i32>) {
+            let values: Vec<i32> = (0..next() % n).map(|_| (next() % 40) as i32).collect();
+            (values.iter().cloned().collect(), values.into_iter().collect())
+        };
+        let (mut a, mut ha) = make(30);
+        let (b, hb) = make(if round % 2 == 0 { 10 } else { 60 });
+        match round % 4 {
+            0 => {
+                a.union_with(&b);
+                ha = &ha | &hb;
+            }
+            1 => {
+                a.intersect_with(&b);
+                ha = &ha & &hb;
+            }
+            2 => {
+                a.difference_with(&b);
+                ha = &ha - &hb;
+            }
+            _ => {
+                a.symmetric_difference_with(&b);
+                ha = &ha ^ &hb;
+            }
+        }
+        assert_eq!(ha, a.iter().cloned().collect());
+    }
+}
+
+#[test]
+fn test_n_way_operations() {
+    let a = set(&[0, 1, 2, 3, 4, 5, 6]);
+    let b = set(&[1, 3, 5, 7, 9]);
+    let c = set(&[5, 3, 0, 1]);
+
+    let all: ThinSet<_> = thin_set::intersection_all(&[&a, &b, &c]).cloned().collect();
+    assert_eq!(sorted(&all), [1, 3, 5]);
+    let all: ThinSet<_> = thin_set::union_all(&[&a, &b, &c]).cloned().collect();
+    assert_eq!(sorted(&all), [0, 1, 2, 3, 4, 5, 6, 7, 9]);
+    assert_eq!(9, thin_set::union_all(&[&c, &b, &a]).count());
+
+    let none: [&ThinSet<i32>; 0] = [];
+    assert_eq!(0, thin_set::intersection_all(&none).count());
+    assert_eq!(0, thin_set::union_all(&none).count());
+    assert_eq!(7, thin_set::intersection_all(&[&a]).count());
+    let empty = ThinSet::new();
+    assert_eq!(0, thin_set::intersection_all(&[&a, &empty, &b]).count());
+    assert_eq!(5, thin_set::union_all(&[&empty, &b, &empty]).count());
+
+    assert_eq!(3, thin_set::count_intersection(&a, &b));
+    assert_eq!(3, thin_set::count_intersection(&b, &a));
+    assert_eq!(3.0 / 9.0, thin_set::jaccard(&a, &b));
+    assert_eq!(1.0, thin_set::jaccard(&c, &c));
+    assert_eq!(0.0, thin_set::jaccard(&c, &empty));
+    assert_eq!(1.0, thin_set::jaccard(&empty, &ThinSet::new()));
+}
+
+#[test]
+fn test_n_way_matches_hash_set() {
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for _ in 0..100 {
+        let sets: Vec<ThinSet<i32>> = (0..next() % 6)
+            .map(|_| (0..next() % 50).map(|_| (next() % 30) as i32).collect())
+            .collect();
+        let refs: Vec<&ThinSet<i32>> = sets.iter().collect();
+        let hash_sets: Vec<HashSet<i32>> =
+            sets.iter().map(|s| s.iter().cloned().collect()).collect();
+
+        let union: HashSet<i32> = hash_sets.iter().flatten().cloned().collect();
+        let mut visited: Vec<i32> = thin_set::union_all(&refs).cloned().collect();
+        assert_eq!(union.len(), visited.len());
+        visited.retain(|v| union.contains(v));
+        assert_eq!(union.len(), visited.len());
+
+        let intersection: HashSet<i32> = match hash_sets.split_first() {
+            Some((first, rest)) => {
+                first.iter().filter(|v| rest.iter().all(|s| s.contains(v))).cloned().collect()
+            }
+            None => HashSet::new(),
+        };
+        let visited: Vec<i32> = thin_set::intersection_all(&refs).cloned().collect();
+        assert_eq!(intersection.len(), visited.len());
+        assert!(visited.iter().all(|v| intersection.contains(v)));
+    }
+}
+
+// equal when the ids are, so replace can be told apart from insert
+#[derive(Debug, Clone, Copy)]
+struct Tagged {
+    id: u32,
+    tag: char,
+}
+
+impl PartialEq for Tagged {
+    fn eq(&self, other: &Tagged) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Tagged {}
+
+impl Hash for Tagged {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl ThinSentinel for Tagged {
+    const SENTINEL_ZERO: Self = Tagged { id: 0, tag: '0' };
+    const SENTINEL_ONE: Self = Tagged { id: 1, tag: '1' };
+}
+
+#[test]
+fn test_get_or_insert_and_replace() {
+    let mut set: ThinSet<Tagged> = ThinSet::new();
+    // the sentinels live outside the table, so each path is checked with them too
+    for id in 0..3 {
+        assert_eq!('a', set.get_or_insert(Tagged { id, tag: 'a' }).tag);
+        assert_eq!('a', set.get_or_insert(Tagged { id, tag: 'b' }).tag);
+        assert_eq!('a', set.get_or_insert_with(&Tagged { id, tag: 'c' }, |_| unreachable!()).tag);
+        assert_eq!('a', set.replace(Tagged { id, tag: 'd' }).unwrap().tag);
+        assert_eq!('d', set.get(&Tagged { id, tag: '?' }).unwrap().tag);
+    }
+    for id in 3..6 {
+        let made = set.get_or_insert_with(&Tagged { id, tag: 'x' }, |t| Tagged { id: t.id, tag: 'y' });
+        assert_eq!('y', made.tag);
+        assert!(set.replace(Tagged { id: id + 10, tag: 'z' }).is_none());
+    }
+    assert_eq!(9, set.len());
+
+    let mut ints = ThinSet::new();
+    assert_eq!(None, ints.replace(0));
+    assert_eq!(&1, ints.get_or_insert_with(&1, |v| *v));
+    assert_eq!(Some(0), ints.replace(0));
+    assert_eq!(sorted(&ints), [0, 1]);
+}
+
+#[test]
+#[should_panic]
+fn test_get_or_insert_with_different_value() {
+    let mut set = ThinSet::new();
+    set.get_or_insert_with(&5, |_| 6);
+}
+
+#[test]
+fn test_entry() {
+    let mut set: ThinSet<i32> = ThinSet::new();
+    for (i, v) in [0, 1, 2, 0, 1, 2].iter().enumerate() {
+        match set.entry(*v) {
+            Entry::Occupied(entry) => {
+                assert!(i >= 3);
+                assert_eq!(v, entry.get());
+            }
+            Entry::Vacant(entry) => {
+                assert!(i < 3);
+                assert_eq!(v, entry.get());
+                assert_eq!(v, entry.insert());
+            }
+        }
+    }
+    assert_eq!(sorted(&set), [0, 1, 2]);
+
+    for v in &[0, 1, 2] {
+        match set.entry(*v) {
+            Entry::Occupied(entry) => assert_eq!(*v, entry.remove()),
+            Entry::Vacant(_) => panic!("{} should be there", v),
+        }
+        assert!(!set.contains(v));
+    }
+    assert!(set.is_empty());
+    match set.entry(1) {
+        Entry::Vacant(entry) => assert_eq!(1, entry.into_value()),
+        Entry::Occupied(_) => panic!("the set is empty"),
+    }
+    assert!(set.is_empty());
+
+    // fills the table through entries alone
+    for v in 0..1000 {
+        assert_eq!(&v, set.entry(v).or_insert());
+        assert_eq!(&v, set.entry(v).get());
+    }
+    assert_eq!(1000, set.len());
+    assert!((0..1000).all(|v| set.contains(&v)));
+}
+
+fn hash_of<T: Hash>(t: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    t.hash(&mut h);
+    h.finish()
+}
+
+#[test]
+fn test_hash_is_order_independent() {
+    let a: ThinSet<u64> = (0..500).collect();
+    let mut b: ThinSet<u64> = ThinSet::with_capacity(4096);
+    b.extend((0..1000).rev());
+    b.retain(|v| *v < 500);
+    assert_eq!(a, b);
+    assert_eq!(hash_of(&a), hash_of(&b));
+
+    // each set has its own seed; equal sets with different seeds must still hash the same
+    let c: ThinSet<u64> = (0..499).collect();
+    assert_ne!(hash_of(&a), hash_of(&c));
+    let d: ThinSet<u64> = (1..500).collect();
+    assert_ne!(hash_of(&c), hash_of(&d));
+    assert_eq!(hash_of(&ThinSet::<u64>::new()), hash_of(&ThinSet::<u64>::with_capacity(8)));
+
+    let e: ThinSet<u64, TrivialOneFieldHasherBuilder> = (0..500).collect();
+    assert_eq!(hash_of(&a), hash_of(&e));
+
+    let mut seen = HashSet::new();
+    assert!(seen.insert(a.clone()));
+    assert!(!seen.insert(b));
+    assert!(seen.insert(c));
+}
+
+#[test]
+fn test_hash_mixes_entries() {
+    let strings = |values: &[&str]| -> ThinSet<V64Str> { values.iter().map(|&s| V64Str::from(s)).collect() };
+    assert_ne!(hash_of(&strings(&["alpha/2024/report.txt"])), hash_of(&strings(&["omega/1999/report.txt"])));
+    assert_ne!(hash_of(&strings(&["ab", "cd"])), hash_of(&strings(&["ad", "cb"])));
+
+    type Pair = ThinSentinelEnum<(u64, u64)>;
+    let pairs = |values: &[(u64, u64)]| -> ThinSet<Pair> { values.iter().map(|&p| Pair::from(p)).collect() };
+    assert_ne!(hash_of(&pairs(&[(1, 2)])), hash_of(&pairs(&[(2, 1)])));
+    assert_ne!(hash_of(&pairs(&[(1, 1), (2, 2)])), hash_of(&pairs(&[(1, 2), (2, 1)])));
+
+    let mut seen = HashSet::new();
+    for i in 0..32 {
+        for j in 0..32 {
+            assert!(seen.insert(hash_of(&pairs(&[(i, j)]))));
+            assert!(seen.insert(hash_of(&strings(&[&format!("{}/{}/report.txt", i, j)]))));
+        }
+    }
+}
+
+#[test]
+fn test_nested_sets() {
+    type Group = ThinSentinelEnum<ThinSet<u32>>;
+    let group = |values: &[u32]| -> Group { values.iter().cloned().collect::<ThinSet<_>>().into() };
+
+    let mut groups: ThinSet<Group> = ThinSet::new();
+    assert!(groups.insert(group(&[1, 2, 3])));
+    assert!(!groups.insert(group(&[3, 1, 2])));
+    assert!(groups.insert(group(&[])));
+    assert!(groups.insert(group(&[0, 1])));
+    assert!(!groups.insert(group(&[1, 0])));
+    for i in 0..100 {
+        groups.insert(group(&[i, i + 1, i + 2]));
+    }
+    assert_eq!(102, groups.len());
+    assert!(groups.contains(&group(&[2, 1, 0])));
+    assert!(groups.remove(&group(&[])));
+    assert!(!groups.contains(&group(&[])));
+
+    let mut outer: ThinSet<ThinSentinelEnum<ThinSet<Group>>> = ThinSet::new();
+    outer.insert(groups.clone().into());
+    outer.insert(groups.into());
+    assert_eq!(1, outer.len());
+}
+
+#[test]
+fn test_compare_and_convert() {
+    let a: ThinSet<i32> = (0..50).collect();
+    let b: ThinSet<i32, TrivialOneFieldHasherBuilder> = (0..50).rev().collect();
+    assert_eq!(a, b);
+    assert_eq!(b, a);
+    let std: HashSet<i32> = (0..50).collect();
+    assert_eq!(a, std);
+    assert_eq!(a, (0..50).collect::<BTreeSet<_>>());
+    assert_ne!(a, (1..51).collect::<BTreeSet<_>>());
+    assert_ne!(a, (0..49).collect::<HashSet<_>>());
+
+    let back: ThinSet<i32> = ThinSet::from(std.clone());
+    assert_eq!(back, std);
+    let std2: HashSet<i32> = back.into();
+    assert_eq!(std, std2);
+}
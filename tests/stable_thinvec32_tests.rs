@@ -0,0 +1,70 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+// ThinVec32 runs the same code as ThinVec (stable_thinvec_tests), so these only cover the u32 header.
+
+extern crate thincollections;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use thincollections::thin_vec32::ThinVec32;
+
+struct RecordingAlloc;
+
+thread_local! {
+    static LAST_ALLOC_SIZE: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for RecordingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LAST_ALLOC_SIZE.with(|s| s.set(layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: RecordingAlloc = RecordingAlloc;
+
+#[test]
+fn test_header_is_8_bytes() {
+    let v: ThinVec32<u16> = ThinVec32::with_capacity(4);
+    assert_eq!(8 + 4 * 2, LAST_ALLOC_SIZE.with(|s| s.get()));
+    drop(v);
+    let v: ThinVec32<u64> = ThinVec32::with_capacity(3);
+    assert_eq!(8 + 3 * 8, LAST_ALLOC_SIZE.with(|s| s.get()));
+    drop(v);
+    let v: ThinVec32<u128> = ThinVec32::with_capacity(1);
+    assert_eq!(std::mem::align_of::<u128>() + 16, LAST_ALLOC_SIZE.with(|s| s.get()));
+    drop(v);
+}
+
+#[test]
+fn test_grow_past_u16_lengths() {
+    let mut v: ThinVec32<u16> = ThinVec32::new();
+    for i in 0..100_000u32 {
+        v.push(i as u16);
+    }
+    assert_eq!(100_000, v.len());
+    assert_eq!(1, v[65_537]);
+    v.truncate(70_000);
+    assert_eq!(70_000, v.len());
+    assert_eq!(Some(69_999u32 as u16), v.pop());
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+#[should_panic(expected = "capacity overflow")]
+fn test_capacity_overflow() {
+    let _v: ThinVec32<u8> = ThinVec32::with_capacity(u32::MAX as usize + 1);
+}
@@ -0,0 +1,1172 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Portions copyright The Rust Project Developers. Licensed under
+// the MIT License.
+
+#[macro_use]
+extern crate thincollections;
+
+use std::mem::size_of;
+use std::usize;
+
+use thincollections::thin_v128::Drain;
+use thincollections::thin_v128::IntoIter;
+use thincollections::thin_v128::V128;
+
+struct DropCounter<'a> {
+    count: &'a mut u32,
+}
+
+impl<'a> Drop for DropCounter<'a> {
+    fn drop(&mut self) {
+        *self.count += 1;
+    }
+}
+
+#[test]
+fn test_v128_sizeof() {
+    assert_eq!(size_of::<V128<u8>>(), size_of::<u128>());
+}
+
+#[test]
+fn test_v128_sizeof_option() {
+    assert_eq!(size_of::<Option<V128<u8>>>(), size_of::<u128>());
+}
+
+#[test]
+fn test_v128_fat() {
+    let mut v: V128<u128> = V128::new();
+    v.push(12);
+    assert_eq!(1, v.len());
+}
+
+#[test]
+fn test_double_drop() {
+    struct TwoV128<T> {
+        x: V128<T>,
+        y: V128<T>,
+    }
+
+    let (mut count_x, mut count_y) = (0, 0);
+    {
+        let mut tv = TwoV128 {
+            x: V128::new(),
+            y: V128::new(),
+        };
+        tv.x.push(DropCounter { count: &mut count_x });
+        tv.y.push(DropCounter { count: &mut count_y });
+
+        // If V128 had a drop flag, here is where it would be zeroed.
+        // Instead, it should rely on its internal state to prevent
+        // doing anything significant when dropped multiple times.
+        drop(tv.x);
+
+        // Here tv goes out of scope, tv.y should be dropped, but not tv.x.
+    }
+
+    assert_eq!(count_x, 1);
+    assert_eq!(count_y, 1);
+}
+
+#[test]
+fn test_reserve() {
+    let mut v = V128::new();
+    assert_eq!(v.capacity(), 3);
+
+    v.reserve(2);
+    assert!(v.capacity() >= 2);
+
+    for i in 0..16 {
+        v.push(i);
+    }
+
+    assert!(v.capacity() >= 16);
+    v.reserve(16);
+    assert!(v.capacity() >= 32);
+
+    v.push(16);
+
+    v.reserve(16);
+    assert!(v.capacity() >= 33)
+}
+
+#[test]
+fn test_extend() {
+    let mut v = V128::new();
+    let mut w = V128::new();
+
+
+    v.extend(w.clone());
+    assert_eq!(v, &[]);
+
+    v.extend(0..3);
+    for i in 0..3 {
+        w.push(i)
+    }
+
+    assert_eq!(v, w);
+
+    v.extend(3..10);
+    for i in 3..10 {
+        w.push(i)
+    }
+
+    assert_eq!(v, w);
+
+    v.extend(w.clone()); // specializes to `append`
+    assert!(v.iter().eq(w.iter().chain(w.iter())));
+
+    // Zero sized types
+    #[derive(PartialEq, Debug)]
+    struct Foo;
+
+    let mut a = V128::new();
+    let b = v128![Foo, Foo];
+
+    a.extend(b);
+    assert_eq!(a, &[Foo, Foo]);
+
+    // Double drop
+    let mut count_x = 0;
+    {
+        let mut x = V128::new();
+        let y = v128![DropCounter { count: &mut count_x }];
+        x.extend(y);
+    }
+    assert_eq!(count_x, 1);
+}
+
+#[test]
+fn test_extend_ref() {
+    let mut v = v128![1, 2];
+    v.extend(&[3, 4, 5]);
+
+    assert_eq!(v.len(), 5);
+    assert_eq!(v, [1, 2, 3, 4, 5]);
+
+    let w = v128![6, 7];
+    v.extend(&w);
+
+    assert_eq!(v.len(), 7);
+    assert_eq!(v, [1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_slice_from_mut() {
+    let mut values = v128![1, 2, 3, 4, 5];
+    {
+        let slice = &mut values[2..];
+        assert_eq!(slice, [3, 4, 5]);
+        for p in slice {
+            *p += 2;
+        }
+    }
+
+    assert_eq!(values, [1, 2, 5, 6, 7]);
+}
+
+#[test]
+fn test_slice_to_mut() {
+    let mut values = v128![1, 2, 3, 4, 5];
+    {
+        let slice = &mut values[..2];
+        assert_eq!(slice, [1, 2]);
+        for p in slice {
+            *p += 1;
+        }
+    }
+
+    assert_eq!(values, [2, 3, 3, 4, 5]);
+}
+
+#[test]
+fn test_split_at_mut() {
+    let mut values = v128![1, 2, 3, 4, 5];
+    {
+        let (left, right) = values.split_at_mut(2);
+        {
+            let left: &[_] = left;
+            assert_eq!(&left[..left.len()], &[1, 2]);
+        }
+        for p in left {
+            *p += 1;
+        }
+
+        {
+            let right: &[_] = right;
+            assert_eq!(&right[..right.len()], &[3, 4, 5]);
+        }
+        for p in right {
+            *p += 2;
+        }
+    }
+
+    assert_eq!(values, [2, 3, 5, 6, 7]);
+}
+
+#[test]
+fn test_clone() {
+    let v: V128<i32> = v128![];
+    let w = v128![1, 2, 3];
+
+    assert_eq!(v, v.clone());
+
+    let z = w.clone();
+    assert_eq!(w, z);
+    // they should be disjoint in memory.
+    assert_ne!(w.as_ptr(), z.as_ptr())
+}
+
+#[test]
+fn test_clone_from() {
+    let mut v = v128![];
+    let three: V128<Box<_>> = v128![Box::new(1), Box::new(2), Box::new(3)];
+    let two: V128<Box<_>> = v128![Box::new(4), Box::new(5)];
+    // zero, long
+    v.clone_from(&three);
+    assert_eq!(v, three);
+
+    // equal
+    v.clone_from(&three);
+    assert_eq!(v, three);
+
+    // long, short
+    v.clone_from(&two);
+    assert_eq!(v, two);
+
+    // short, long
+    v.clone_from(&three);
+    assert_eq!(v, three)
+}
+
+#[test]
+fn test_retain() {
+    let mut v128 = v128![1, 2, 3, 4];
+    v128.retain(|&x| x % 2 == 0);
+    assert_eq!(v128, [2, 4]);
+}
+
+#[test]
+fn test_dedup() {
+    fn case(a: V128<i32>, b: V128<i32>) {
+        let mut v = a;
+        v.dedup();
+        assert_eq!(v, b);
+    }
+    case(v128![], v128![]);
+    case(v128![1], v128![1]);
+    case(v128![1, 1], v128![1]);
+    case(v128![1, 2, 3], v128![1, 2, 3]);
+    case(v128![1, 1, 2, 3], v128![1, 2, 3]);
+    case(v128![1, 2, 2, 3], v128![1, 2, 3]);
+    case(v128![1, 2, 3, 3], v128![1, 2, 3]);
+    case(v128![1, 1, 2, 2, 2, 3, 3], v128![1, 2, 3]);
+}
+
+#[test]
+fn test_dedup_by_key() {
+    fn case(a: V128<i32>, b: V128<i32>) {
+        let mut v = a;
+        v.dedup_by_key(|i| *i / 10);
+        assert_eq!(v, b);
+    }
+    case(v128![], v128![]);
+    case(v128![10], v128![10]);
+    case(v128![10, 11], v128![10]);
+    case(v128![10, 20, 30], v128![10, 20, 30]);
+    case(v128![10, 11, 20, 30], v128![10, 20, 30]);
+    case(v128![10, 20, 21, 30], v128![10, 20, 30]);
+    case(v128![10, 20, 30, 31], v128![10, 20, 30]);
+    case(v128![10, 11, 20, 21, 22, 30, 31], v128![10, 20, 30]);
+}
+
+#[test]
+fn test_dedup_by() {
+    let mut v128 = v128!["foo", "bar", "Bar", "baz", "bar"];
+    v128.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    assert_eq!(v128, ["foo", "bar", "baz", "bar"]);
+
+    let mut v128 = v128![("foo", 1), ("foo", 2), ("bar", 3), ("bar", 4), ("bar", 5)];
+    v128.dedup_by(|a, b| a.0 == b.0 && {
+        b.1 += a.1;
+        true
+    });
+
+    assert_eq!(v128, [("foo", 3), ("bar", 12)]);
+}
+
+#[test]
+fn test_dedup_unique() {
+    let mut v0: V128<Box<_>> = v128![Box::new(1), Box::new(1), Box::new(2), Box::new(3)];
+    v0.dedup();
+    let mut v1: V128<Box<_>> = v128![Box::new(1), Box::new(2), Box::new(2), Box::new(3)];
+    v1.dedup();
+    let mut v2: V128<Box<_>> = v128![Box::new(1), Box::new(2), Box::new(3), Box::new(3)];
+    v2.dedup();
+    // If the boxed pointers were leaked or otherwise misused, valgrind
+    // and/or rt should raise errors.
+}
+
+#[test]
+fn zero_sized_values() {
+    let mut v = V128::new();
+    assert_eq!(v.len(), 0);
+    v.push(());
+    assert_eq!(v.len(), 1);
+    v.push(());
+    assert_eq!(v.len(), 2);
+    assert_eq!(v.pop(), Some(()));
+    assert_eq!(v.pop(), Some(()));
+    assert_eq!(v.pop(), None);
+
+
+    assert_eq!(v.iter().count(), 0);
+    v.push(());
+    assert_eq!(v.iter().count(), 1);
+    v.push(());
+    assert_eq!(v.iter().count(), 2);
+
+    for &() in &v {}
+
+    assert_eq!(v.iter_mut().count(), 2);
+    v.push(());
+    assert_eq!(v.iter_mut().count(), 3);
+    v.push(());
+    assert_eq!(v.iter_mut().count(), 4);
+
+    for &mut () in &mut v {}
+    v.clear();
+    assert_eq!(v.iter_mut().count(), 0);
+}
+
+#[test]
+fn test_partition() {
+    assert_eq!(v128![].into_iter().partition(|x: &i32| *x < 3),
+               (v128![], v128![]));
+    assert_eq!(v128![1, 2, 3].into_iter().partition(|x| *x < 4),
+               (v128![1, 2, 3], v128![]));
+    assert_eq!(v128![1, 2, 3].into_iter().partition(|x| *x < 2),
+               (v128![1], v128![2, 3]));
+    assert_eq!(v128![1, 2, 3].into_iter().partition(|x| *x < 0),
+               (v128![], v128![1, 2, 3]));
+}
+
+#[test]
+fn test_zip_unzip() {
+    let z1 = v128![(1, 4), (2, 5), (3, 6)];
+
+    let (left, right): (V128<_>, V128<_>) = z1.iter().cloned().unzip();
+
+    assert_eq!((1, 4), (left[0], right[0]));
+    assert_eq!((2, 5), (left[1], right[1]));
+    assert_eq!((3, 6), (left[2], right[2]));
+}
+
+#[test]
+fn test_v128_truncate_drop() {
+    static mut DROPS: u32 = 0;
+    struct Elem(i32);
+    impl Drop for Elem {
+        fn drop(&mut self) {
+            unsafe {
+                DROPS += 1;
+            }
+        }
+    }
+
+    let mut v = v128![Elem(1), Elem(2), Elem(3), Elem(4), Elem(5)];
+    assert_eq!(unsafe { DROPS }, 0);
+    v.truncate(3);
+    assert_eq!(unsafe { DROPS }, 2);
+    v.truncate(0);
+    assert_eq!(unsafe { DROPS }, 5);
+}
+
+#[test]
+#[should_panic]
+fn test_v128_truncate_fail() {
+    struct BadElem(i32);
+    impl Drop for BadElem {
+        fn drop(&mut self) {
+            let BadElem(ref mut x) = *self;
+            if *x == 0xbadbeef {
+                panic!("BadElem panic: 0xbadbeef")
+            }
+        }
+    }
+
+    let mut v = v128![BadElem(1), BadElem(2), BadElem(0xbadbeef), BadElem(4)];
+    v.truncate(0);
+}
+
+#[test]
+fn test_index() {
+    let v128 = v128![1, 2, 3];
+    assert_eq!(v128[1], 2);
+}
+
+#[test]
+#[should_panic]
+fn test_index_out_of_bounds() {
+    let v128 = v128![1, 2, 3];
+    let _ = v128[3];
+}
+
+#[test]
+#[should_panic]
+fn test_slice_out_of_bounds_1() {
+    let x = v128![1, 2, 3, 4, 5];
+    let _ = &x[!0..];
+}
+
+#[test]
+#[should_panic]
+fn test_slice_out_of_bounds_2() {
+    let x = v128![1, 2, 3, 4, 5];
+    let _ = &x[..6];
+}
+
+#[test]
+#[should_panic]
+fn test_slice_out_of_bounds_3() {
+    let x = v128![1, 2, 3, 4, 5];
+    let _ = &x[!0..4];
+}
+
+#[test]
+#[should_panic]
+fn test_slice_out_of_bounds_4() {
+    let x = v128![1, 2, 3, 4, 5];
+    let _ = &x[1..6];
+}
+
+#[test]
+#[should_panic]
+fn test_slice_out_of_bounds_5() {
+    let x = v128![1, 2, 3, 4, 5];
+    let _ = &x[3..2];
+}
+
+#[test]
+#[should_panic]
+fn test_swap_remove_empty() {
+    let mut v128 = V128::<i32>::new();
+    v128.swap_remove(0);
+}
+
+#[test]
+fn test_move_items() {
+    let v128 = v128![1, 2, 3];
+    let mut v1282 = v128![];
+    for i in v128 {
+        v1282.push(i);
+    }
+    assert_eq!(v1282, [1, 2, 3]);
+}
+
+#[test]
+fn test_move_items_reverse() {
+    let v128 = v128![1, 2, 3];
+    let mut v1282 = v128![];
+    for i in v128.into_iter().rev() {
+        v1282.push(i);
+    }
+    assert_eq!(v1282, [3, 2, 1]);
+}
+
+#[test]
+fn test_move_items_zero_sized() {
+    let v128 = v128![(), (), ()];
+    let mut v1282 = v128![];
+    for i in v128 {
+        v1282.push(i);
+    }
+    assert_eq!(v1282, [(), (), ()]);
+}
+
+#[test]
+fn test_drain_items() {
+    let mut v128 = v128![1, 2, 3];
+    let mut v1282 = v128![];
+    for i in v128.drain(..) {
+        v1282.push(i);
+    }
+    assert_eq!(v128, []);
+    assert_eq!(v1282, [1, 2, 3]);
+}
+
+#[test]
+fn test_drain_items_reverse() {
+    let mut v128 = v128![1, 2, 3];
+    let mut v1282 = v128![];
+    for i in v128.drain(..).rev() {
+        v1282.push(i);
+    }
+    assert_eq!(v128, []);
+    assert_eq!(v1282, [3, 2, 1]);
+}
+
+#[test]
+fn test_drain_items_zero_sized() {
+    let mut v128 = v128![(), (), ()];
+    let mut v1282 = v128![];
+    for i in v128.drain(..) {
+        v1282.push(i);
+    }
+    assert_eq!(v128, []);
+    assert_eq!(v1282, [(), (), ()]);
+}
+
+#[test]
+fn test_drain_items_zero_sized32() {
+    let mut v128 = v128![(); 32];
+    let mut v1282 = v128![];
+    for i in v128.drain(..) {
+        v1282.push(i);
+    }
+    assert_eq!(v128, []);
+    assert_eq!(v1282, [(); 32]);
+}
+
+#[test]
+#[should_panic]
+fn test_drain_out_of_bounds() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    v.drain(5..6);
+}
+
+#[test]
+fn test_drain_range() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    for _ in v.drain(4..) {}
+    assert_eq!(v, &[1, 2, 3, 4]);
+
+    let mut v: V128<_> = (1..6).map(|x| x.to_string()).collect();
+    for _ in v.drain(1..4) {}
+    assert_eq!(v, &[1.to_string(), 5.to_string()]);
+
+    let mut v: V128<_> = (1..6).map(|x| x.to_string()).collect();
+    for _ in v.drain(1..4).rev() {}
+    assert_eq!(v, &[1.to_string(), 5.to_string()]);
+
+    let mut v: V128<_> = v128![(); 5];
+    for _ in v.drain(1..4).rev() {}
+    assert_eq!(v, &[(), ()]);
+}
+
+#[test]
+fn test_drain_inclusive_range() {
+    let mut v = v128!['a', 'b', 'c', 'd', 'e'];
+    for _ in v.drain(1..=3) {}
+    assert_eq!(v, &['a', 'e']);
+
+    let mut v: V128<_> = (0..=5).map(|x| x.to_string()).collect();
+    for _ in v.drain(1..=5) {}
+    assert_eq!(v, &["0".to_string()]);
+
+    let mut v: V128<String> = (0..=5).map(|x| x.to_string()).collect();
+    for _ in v.drain(0..=5) {}
+    assert_eq!(v, V128::<String>::new());
+
+    let mut v: V128<_> = (0..=5).map(|x| x.to_string()).collect();
+    for _ in v.drain(0..=3) {}
+    assert_eq!(v, &["4".to_string(), "5".to_string()]);
+
+    let mut v: V128<_> = (0..=1).map(|x| x.to_string()).collect();
+    for _ in v.drain(..=0) {}
+    assert_eq!(v, &["1".to_string()]);
+}
+
+#[test]
+#[should_panic]
+fn test_drain_inclusive_out_of_bounds() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    v.drain(5..=5);
+}
+
+#[test]
+fn test_splice() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    let a = [10, 11, 12];
+    let _t1: V128<_> = v.splice(2..4, a.iter().cloned()).collect();
+    assert_eq!(v, &[1, 2, 10, 11, 12, 5]);
+    let _t2: V128<_> = v.splice(1..3, Some(20)).collect();
+    assert_eq!(v, &[1, 20, 11, 12, 5]);
+}
+
+#[test]
+fn test_splice_grows_past_tail() {
+    let mut v: V128<u64> = V128::with_capacity(5);
+    v.extend(0..5);
+    let t: V128<_> = v.splice(1..2, 10..20).collect();
+    assert_eq!(t, &[1]);
+    assert_eq!(v, &[0, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 2, 3, 4]);
+}
+
+#[test]
+fn test_splice_inline() {
+    let mut v: V128<u16> = v128![1, 2, 3, 4, 5];
+    let t: V128<_> = v.splice(1..3, Some(20)).collect();
+    assert_eq!(t, &[2, 3]);
+    assert_eq!(v, &[1, 20, 4, 5]);
+    let t: V128<_> = v.splice(1..2, 30..33).collect();
+    assert_eq!(t, &[20]);
+    assert_eq!(v, &[1, 30, 31, 32, 4, 5]);
+}
+
+#[test]
+fn test_splice_zst() {
+    let mut v = v128![(); 5];
+    let a = [(); 3];
+    let _t1: V128<_> = v.splice(2..4, a.iter().cloned()).collect();
+    assert_eq!(v, &[(); 6]);
+    let _t2: V128<_> = v.splice(1..3, Some(())).collect();
+    assert_eq!(v, &[(); 5]);
+}
+
+#[test]
+fn test_splice_zst32() {
+    let mut v = v128![(); 24];
+    let a = [(); 3];
+    let _t1: V128<_> = v.splice(2..4, a.iter().cloned()).collect();
+    assert_eq!(v, &[(); 25]);
+    let _t2: V128<_> = v.splice(1..3, Some(())).collect();
+    assert_eq!(v, &[(); 24]);
+}
+
+#[test]
+fn test_splice_inclusive_range() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    let a = [10, 11, 12];
+    let t1: V128<_> = v.splice(2..=3, a.iter().cloned()).collect();
+    assert_eq!(v, &[1, 2, 10, 11, 12, 5]);
+    assert_eq!(t1, &[3, 4]);
+    let t2: V128<_> = v.splice(1..=2, Some(20)).collect();
+    assert_eq!(v, &[1, 20, 11, 12, 5]);
+    assert_eq!(t2, &[2, 10]);
+}
+
+#[test]
+#[should_panic]
+fn test_splice_out_of_bounds() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    let a = [10, 11, 12];
+    v.splice(5..6, a.iter().cloned());
+}
+
+#[test]
+#[should_panic]
+fn test_splice_inclusive_out_of_bounds() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    let a = [10, 11, 12];
+    v.splice(5..=5, a.iter().cloned());
+}
+
+#[test]
+fn test_splice_items_zero_sized() {
+    let mut v128 = v128![(), (), ()];
+    let v1282 = v128![];
+    let t: V128<_> = v128.splice(1..2, v1282.iter().cloned()).collect();
+    assert_eq!(v128, &[(), ()]);
+    assert_eq!(t, &[()]);
+}
+
+#[test]
+fn test_splice_unbounded() {
+    let mut v128 = v128![1, 2, 3, 4, 5];
+    let t: V128<_> = v128.splice(.., None).collect();
+    assert_eq!(v128, &[]);
+    assert_eq!(t, &[1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_splice_forget() {
+    let mut v = v128![1, 2, 3, 4, 5];
+    let a = [10, 11, 12];
+    std::mem::forget(v.splice(2..4, a.iter().cloned()));
+    assert_eq!(v, &[1, 2]);
+}
+
+#[test]
+fn test_into_boxed_slice() {
+    let xs = v128![1, 2, 3];
+    let ys = xs.into_boxed_slice();
+    assert_eq!(&*ys, [1, 2, 3]);
+
+    let z = v128![(), (), ()];
+    let zs = z.into_boxed_slice();
+    assert_eq!(&*zs, [(), (), ()]);
+
+    let z = v128![(); 32];
+    let zs = z.into_boxed_slice();
+    assert_eq!(&*zs, [(); 32]);
+}
+
+#[test]
+fn test_append() {
+    let mut v128 = v128![1, 2, 3];
+    let mut v1282 = v128![4, 5, 6];
+    v128.append(&mut v1282);
+    assert_eq!(v128, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(v1282, []);
+}
+
+#[test]
+fn test_split_off() {
+    let mut v128 = v128![1, 2, 3, 4, 5, 6];
+    let v1282 = v128.split_off(4);
+    assert_eq!(v128, [1, 2, 3, 4]);
+    assert_eq!(v1282, [5, 6]);
+}
+
+#[test]
+fn test_into_iter_as_slice() {
+    let v128 = v128!['a', 'b', 'c'];
+    let mut into_iter = v128.into_iter();
+    assert_eq!(into_iter.as_slice(), &['a', 'b', 'c']);
+    let _ = into_iter.next().unwrap();
+    assert_eq!(into_iter.as_slice(), &['b', 'c']);
+    let _ = into_iter.next().unwrap();
+    let _ = into_iter.next().unwrap();
+    assert_eq!(into_iter.as_slice(), &[]);
+}
+
+#[test]
+fn test_into_iter_as_mut_slice() {
+    let v128 = v128!['a', 'b', 'c'];
+    let mut into_iter = v128.into_iter();
+    assert_eq!(into_iter.as_slice(), &['a', 'b', 'c']);
+    into_iter.as_mut_slice()[0] = 'x';
+    into_iter.as_mut_slice()[1] = 'y';
+    assert_eq!(into_iter.next().unwrap(), 'x');
+    assert_eq!(into_iter.as_slice(), &['y', 'c']);
+}
+
+#[test]
+fn test_into_iter_debug() {
+    let v128 = v128!['a', 'b', 'c'];
+    let into_iter = v128.into_iter();
+    let debug = format!("{:?}", into_iter);
+    assert_eq!(debug, "IntoIter(['a', 'b', 'c'])");
+}
+
+#[test]
+fn test_into_iter_count() {
+    assert_eq!(v128![1, 2, 3].into_iter().count(), 3);
+}
+
+#[test]
+fn test_into_iter_clone() {
+    fn iter_equal<I: Iterator<Item=i32>>(it: I, slice: &[i32]) {
+        let v: V128<i32> = it.collect();
+        assert_eq!(&v[..], slice);
+    }
+    let mut it = v128![1, 2, 3].into_iter();
+    iter_equal(it.clone(), &[1, 2, 3]);
+    assert_eq!(it.next(), Some(1));
+    let mut it = it.rev();
+    iter_equal(it.clone(), &[3, 2]);
+    assert_eq!(it.next(), Some(3));
+    iter_equal(it.clone(), &[2]);
+    assert_eq!(it.next(), Some(2));
+    iter_equal(it.clone(), &[]);
+    assert_eq!(it.next(), None);
+}
+
+#[allow(dead_code)]
+fn assert_covariance() {
+    fn drain<'new>(d: Drain<'static, &'static str>) -> Drain<'new, &'new str> {
+        d
+    }
+    fn into_iter<'new>(i: IntoIter<&'static str>) -> IntoIter<&'new str> {
+        i
+    }
+}
+
+//#[test]
+//fn from_into_inner() {
+//    let v128 = v128![1, 2, 3];
+//    let ptr = v128.as_ptr();
+//    let v128 = v128.into_iter().collect::<V128<_>>();
+//    assert_eq!(v128, [1, 2, 3]);
+//    assert_eq!(v128.as_ptr(), ptr);
+//
+//    let ptr = &v128[1] as *const _;
+//    let mut it = v128.into_iter();
+//    it.next().unwrap();
+//    let v128 = it.collect::<V128<_>>();
+//    assert_eq!(v128, [2, 3]);
+//    assert!(ptr != v128.as_ptr());
+//}
+
+#[test]
+fn overaligned_allocations() {
+    #[repr(align(256))]
+    struct Foo(usize);
+    let mut v = v128![Foo(273)];
+    for i in 0..0x1000 {
+        v.reserve_exact(i);
+        assert_eq!(v[0].0, 273);
+        assert_eq!(v.as_ptr() as usize & 0xff, 0);
+        v.shrink_to_fit();
+        assert_eq!(v[0].0, 273);
+        assert_eq!(v.as_ptr() as usize & 0xff, 0);
+    }
+}
+
+#[test]
+fn drain_filter_empty() {
+    let mut v128: V128<i32> = v128![];
+
+    {
+        let mut iter = v128.drain_filter(|_| true);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+    assert_eq!(v128.len(), 0);
+    assert_eq!(v128, v128![]);
+}
+
+#[test]
+fn drain_filter_zst() {
+    let mut v128 = v128![(), (), (), (), ()];
+    let initial_len = v128.len();
+    let mut count = 0;
+    {
+        let mut iter = v128.drain_filter(|_| true);
+        assert_eq!(iter.size_hint(), (0, Some(initial_len)));
+        while let Some(_) = iter.next() {
+            count += 1;
+            assert_eq!(iter.size_hint(), (0, Some(initial_len - count)));
+        }
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    assert_eq!(count, initial_len);
+    assert_eq!(v128.len(), 0);
+    assert_eq!(v128, v128![]);
+}
+
+#[test]
+fn drain_filter_zst32() {
+    let mut v128 = v128![(); 32];
+    let initial_len = v128.len();
+    let mut count = 0;
+    {
+        let mut iter = v128.drain_filter(|_| true);
+        assert_eq!(iter.size_hint(), (0, Some(initial_len)));
+        while let Some(_) = iter.next() {
+            count += 1;
+            assert_eq!(iter.size_hint(), (0, Some(initial_len - count)));
+        }
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    assert_eq!(count, initial_len);
+    assert_eq!(v128.len(), 0);
+    assert_eq!(v128, v128![]);
+}
+
+#[test]
+fn drain_filter_false() {
+    let mut v128 = v128![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
+    let initial_len = v128.len();
+    let mut count = 0;
+    {
+        let mut iter = v128.drain_filter(|_| false);
+        assert_eq!(iter.size_hint(), (0, Some(initial_len)));
+        for _ in iter.by_ref() {
+            count += 1;
+        }
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    assert_eq!(count, 0);
+    assert_eq!(v128.len(), initial_len);
+    assert_eq!(v128, v128![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+}
+
+#[test]
+fn drain_filter_true() {
+    let mut v128 = v128![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
+    let initial_len = v128.len();
+    let mut count = 0;
+    {
+        let mut iter = v128.drain_filter(|_| true);
+        assert_eq!(iter.size_hint(), (0, Some(initial_len)));
+        while let Some(_) = iter.next() {
+            count += 1;
+            assert_eq!(iter.size_hint(), (0, Some(initial_len - count)));
+        }
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+    }
+
+    assert_eq!(count, initial_len);
+    assert_eq!(v128.len(), 0);
+    assert_eq!(v128, v128![]);
+}
+
+#[test]
+fn drain_filter_complex() {
+    {   //                [+xxx++++++xxxxx++++x+x++]
+        let mut v128 = v128![1,
+                           2, 4, 6,
+                           7, 9, 11, 13, 15, 17,
+                           18, 20, 22, 24, 26,
+                           27, 29, 31, 33,
+                           34,
+                           35,
+                           36,
+                           37, 39];
+
+        let removed = v128.drain_filter(|x| *x % 2 == 0).collect::<V128<_>>();
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed, v128![2, 4, 6, 18, 20, 22, 24, 26, 34, 36]);
+
+        assert_eq!(v128.len(), 14);
+        assert_eq!(v128, v128![1, 7, 9, 11, 13, 15, 17, 27, 29, 31, 33, 35, 37, 39]);
+    }
+
+    {   //                [xxx++++++xxxxx++++x+x++]
+        let mut v128 = v128![2, 4, 6,
+                           7, 9, 11, 13, 15, 17,
+                           18, 20, 22, 24, 26,
+                           27, 29, 31, 33,
+                           34,
+                           35,
+                           36,
+                           37, 39];
+
+        let removed = v128.drain_filter(|x| *x % 2 == 0).collect::<V128<_>>();
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed, v128![2, 4, 6, 18, 20, 22, 24, 26, 34, 36]);
+
+        assert_eq!(v128.len(), 13);
+        assert_eq!(v128, v128![7, 9, 11, 13, 15, 17, 27, 29, 31, 33, 35, 37, 39]);
+    }
+
+    {   //                [xxx++++++xxxxx++++x+x]
+        let mut v128 = v128![2, 4, 6,
+                           7, 9, 11, 13, 15, 17,
+                           18, 20, 22, 24, 26,
+                           27, 29, 31, 33,
+                           34,
+                           35,
+                           36];
+
+        let removed = v128.drain_filter(|x| *x % 2 == 0).collect::<V128<_>>();
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed, v128![2, 4, 6, 18, 20, 22, 24, 26, 34, 36]);
+
+        assert_eq!(v128.len(), 11);
+        assert_eq!(v128, v128![7, 9, 11, 13, 15, 17, 27, 29, 31, 33, 35]);
+    }
+
+    {   //                [xxxxxxxxxx+++++++++++]
+        let mut v128 = v128![2, 4, 6, 8, 10, 12, 14, 16, 18, 20,
+                           1, 3, 5, 7, 9, 11, 13, 15, 17, 19];
+
+        let removed = v128.drain_filter(|x| *x % 2 == 0).collect::<V128<_>>();
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed, v128![2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
+
+        assert_eq!(v128.len(), 10);
+        assert_eq!(v128, v128![1, 3, 5, 7, 9, 11, 13, 15, 17, 19]);
+    }
+
+    {   //                [+++++++++++xxxxxxxxxx]
+        let mut v128 = v128![1, 3, 5, 7, 9, 11, 13, 15, 17, 19,
+                           2, 4, 6, 8, 10, 12, 14, 16, 18, 20];
+
+        let removed = v128.drain_filter(|x| *x % 2 == 0).collect::<V128<_>>();
+        assert_eq!(removed.len(), 10);
+        assert_eq!(removed, v128![2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
+
+        assert_eq!(v128.len(), 10);
+        assert_eq!(v128, v128![1, 3, 5, 7, 9, 11, 13, 15, 17, 19]);
+    }
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+#[should_panic(expected = "capacity overflow")]
+fn test_capacity_bytes_overflow() {
+    // u32::MAX elements of 8G each don't fit in a usize
+    let _v: V128<[u8; 1 << 33]> = V128::with_capacity(u32::MAX as usize);
+}
+
+#[test]
+fn test_large_growth_and_shrink() {
+    let mut v: V128<u32> = V128::new();
+    for i in 0..1_000_000 {
+        v.push(i);
+    }
+    v.extend(1_000_000..1_500_000);
+    assert_eq!(1_500_000, v.len());
+    assert!(v.iter().enumerate().all(|(i, x)| i as u32 == *x));
+    v.truncate(100_000);
+    v.shrink_to_fit();
+    assert_eq!(100_000, v.capacity());
+    v.reserve_exact(1_000_000);
+    assert_eq!(1_100_000, v.capacity());
+    assert!(v.iter().enumerate().all(|(i, x)| i as u32 == *x));
+}
+
+#[test]
+fn test_reserve_is_amortized() {
+    let mut v: V128<u64> = V128::new();
+    let mut capacities = vec![];
+    for i in 0..10_000 {
+        v.reserve(1);
+        if capacities.last() != Some(&v.capacity()) {
+            capacities.push(v.capacity());
+        }
+        v.push(i);
+    }
+    assert!(capacities.len() < 16, "{:?}", capacities);
+    let cap = v.capacity();
+    let ptr = v.as_ptr();
+    v.reserve(cap - v.len());
+    assert_eq!(cap, v.capacity());
+    assert_eq!(ptr, v.as_ptr());
+}
+
+#[test]
+fn test_reserve_exact() {
+    // This is all the same as test_reserve
+
+    let mut v = V128::new();
+    assert_eq!(v.capacity(), 3);
+
+    v.reserve_exact(2);
+    assert!(v.capacity() >= 2);
+
+    for i in 0..16 {
+        v.push(i);
+    }
+
+    assert!(v.capacity() >= 16);
+    v.reserve_exact(16);
+    assert!(v.capacity() >= 32);
+
+    v.push(16);
+
+    v.reserve_exact(16);
+    assert!(v.capacity() >= 33)
+}
+
+#[test]
+fn test_append_empty() {
+    let mut a: V128<u64> = v128![1];
+    let mut b = v128![];
+    a.append(&mut b);
+    assert_eq!(1, a.len());
+}
+
+
+#[test]
+fn test_stack_capacity() {
+    assert_eq!(15, V128::<u8>::new().capacity());
+    assert_eq!(7, V128::<u16>::new().capacity());
+    assert_eq!(3, V128::<u32>::new().capacity());
+    assert_eq!(1, V128::<u64>::new().capacity());
+    assert_eq!(0, V128::<u128>::new().capacity());
+    assert_eq!(5, V128::<[u8; 3]>::new().capacity());
+}
+
+#[test]
+fn test_stack_to_heap_and_back() {
+    let mut v: V128<u16> = V128::new();
+    for i in 0..7 {
+        v.push(i);
+        assert_eq!(0, v.bytes_on_heap());
+    }
+    v.push(7);
+    assert!(v.bytes_on_heap() > 0);
+    assert_eq!(v, [0, 1, 2, 3, 4, 5, 6, 7]);
+    for i in 8..1000 {
+        v.push(i);
+    }
+    assert_eq!(1000, v.len());
+    assert_eq!(999, v[999]);
+    v.truncate(5);
+    v.shrink_to_fit();
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(7, v.capacity());
+    assert_eq!(v, [0, 1, 2, 3, 4]);
+    v.push(42);
+    assert_eq!(v, [0, 1, 2, 3, 4, 42]);
+}
+
+#[test]
+fn test_shrink_to_fit_stays_on_heap() {
+    let mut v: V128<u32> = V128::with_capacity(100);
+    v.extend(0..10);
+    assert_eq!(400, v.bytes_on_heap());
+    v.shrink_to_fit();
+    assert_eq!(10, v.capacity());
+    assert_eq!(40, v.bytes_on_heap());
+    assert_eq!(v, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn test_heap_strings() {
+    let mut v: V128<String> = V128::new();
+    for i in 0..100 {
+        v.push(i.to_string());
+    }
+    v.retain(|s| s.len() == 1);
+    assert_eq!(10, v.len());
+    assert_eq!("9", v.pop().unwrap());
+    v.insert(0, "x".to_string());
+    assert_eq!("x", v.remove(0));
+    let collected: Vec<String> = v.into_iter().collect();
+    assert_eq!(9, collected.len());
+}
+
+#[test]
+fn test_into_boxed_slice_stack_and_empty() {
+    let v: V128<u8> = v128![1, 2, 3];
+    assert_eq!([1, 2, 3], *v.into_boxed_slice());
+    let v: V128<u32> = V128::with_capacity(10);
+    assert_eq!(0, v.into_boxed_slice().len());
+}
+
+#[test]
+fn test_from_conversions() {
+    let v: V128<u8> = V128::from("hello, world");
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(b"hello, world", &v[..]);
+    let v: V128<i32> = V128::from(&[1, 2, 3, 4][..]);
+    assert_eq!(v, [1, 2, 3, 4]);
+    let v: V128<i32> = V128::from(vec![5, 6].into_boxed_slice());
+    assert_eq!(v, [5, 6]);
+}
+
+#[test]
+fn test_splice_stack_to_heap() {
+    let mut v: V128<u8> = v128![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let removed: V128<u8> = v.splice(2..4, (20..30).into_iter()).collect();
+    assert_eq!(removed, [3, 4]);
+    assert_eq!(v, [1, 2, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 5, 6, 7, 8, 9, 10]);
+}
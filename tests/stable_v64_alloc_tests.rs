@@ -0,0 +1,124 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use thincollections::thin_v64::V64;
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    // growing or shrinking a block in place isn't a new allocation
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// counts the allocations made on this thread by f
+fn allocations<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let before = ALLOCATIONS.with(|a| a.get());
+    let r = f();
+    (ALLOCATIONS.with(|a| a.get()) - before, r)
+}
+
+#[test]
+fn test_into_iter_stack_does_not_allocate() {
+    let v: V64<u8> = v64![1, 2, 3, 4, 5, 6, 7];
+    let (count, sum) = allocations(|| v.into_iter().map(|x| x as u32).sum::<u32>());
+    assert_eq!(0, count);
+    assert_eq!(28, sum);
+
+    let v: V64<u16> = v64![1, 2, 3];
+    let (count, rev) = allocations(|| {
+        let mut it = v.into_iter();
+        [it.next_back(), it.next_back(), it.next_back(), it.next_back()]
+    });
+    assert_eq!(0, count);
+    assert_eq!([Some(3), Some(2), Some(1), None], rev);
+
+    let v: V64<u64> = V64::new();
+    let (count, n) = allocations(|| v.into_iter().count());
+    assert_eq!(0, count);
+    assert_eq!(0, n);
+}
+
+#[test]
+fn test_into_iter_stack_slices_and_clone() {
+    let v: V64<u8> = v64![1, 2, 3, 4];
+    let (count, _) = allocations(|| {
+        let mut it = v.into_iter();
+        assert_eq!(4, it.len());
+        assert_eq!(&[1, 2, 3, 4], it.as_slice());
+        it.as_mut_slice()[3] = 9;
+        assert_eq!(Some(1), it.next());
+        assert_eq!(Some(9), it.next_back());
+        let mut copy = it.clone();
+        assert_eq!(&[2, 3], copy.as_slice());
+        assert_eq!(Some(2), copy.next());
+        assert_eq!(&[2, 3], it.as_slice());
+        assert_eq!(2, it.len());
+    });
+    assert_eq!(0, count);
+}
+
+#[test]
+fn test_into_iter_heap_and_partial_drop() {
+    let v: V64<String> = v64!["a".to_string(), "b".to_string(), "c".to_string()];
+    let mut it = v.into_iter();
+    assert_eq!(Some("a".to_string()), it.next());
+    assert_eq!(&["b".to_string(), "c".to_string()], it.as_slice());
+    let copy = it.clone();
+    drop(it);
+    assert_eq!(vec!["b".to_string(), "c".to_string()], copy.collect::<Vec<_>>());
+
+    let v: V64<u8> = (0..20).collect();
+    let (count, n) = allocations(|| v.into_iter().rev().take(5).count());
+    assert_eq!(0, count);
+    assert_eq!(5, n);
+}
+
+#[repr(align(16))]
+#[derive(Debug, PartialEq)]
+struct Wide(u64);
+
+#[test]
+fn test_vec_conversions_reuse_the_allocation() {
+    let v: Vec<Wide> = (0..100).map(Wide).collect();
+    let (count, v64) = allocations(|| V64::from(v));
+    assert_eq!(0, count);
+    assert_eq!(Wide(99), v64[99]);
+    let (count, v) = allocations(|| Vec::from(v64));
+    assert_eq!(0, count);
+    assert_eq!(100, v.len());
+
+    let v: Vec<u8> = vec![1, 2, 3];
+    let (count, v64) = allocations(|| V64::from(v));
+    assert_eq!(0, count);
+    assert_eq!(v64, [1, 2, 3]);
+}
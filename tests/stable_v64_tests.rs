@@ -18,6 +18,7 @@ use std::usize;
 use thincollections::thin_v64::Drain;
 use thincollections::thin_v64::IntoIter;
 use thincollections::thin_v64::V64;
+use thincollections::thin_vec::ThinVec;
 
 struct DropCounter<'a> {
     count: &'a mut u32,
@@ -601,6 +602,15 @@ fn test_splice() {
     assert_eq!(v, &[1, 20, 11, 12, 5]);
 }
 
+#[test]
+fn test_splice_grows_past_tail() {
+    let mut v: V64<u64> = V64::with_capacity(5);
+    v.extend(0..5);
+    let t: V64<_> = v.splice(1..2, 10..20).collect();
+    assert_eq!(t, &[1]);
+    assert_eq!(v, &[0, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 2, 3, 4]);
+}
+
 #[test]
 fn test_splice_zst() {
     let mut v = v64![(); 5];
@@ -689,6 +699,50 @@ fn test_into_boxed_slice() {
     assert_eq!(&*zs, [(); 32]);
 }
 
+#[test]
+fn test_vec_conversions() {
+    let mut v: Vec<u64> = Vec::with_capacity(10);
+    v.extend_from_slice(&[1, 2, 3]);
+    let t = V64::from(v);
+    assert_eq!(t, [1, 2, 3]);
+    let v: Vec<u64> = Vec::from(t);
+    assert_eq!(v, [1, 2, 3]);
+
+    let v: Vec<String> = vec!["a".to_string(), "b".to_string()];
+    let t: V64<String> = v.into();
+    assert_eq!(t, ["a".to_string(), "b".to_string()]);
+    let v: Vec<String> = t.into();
+    assert_eq!(v, ["a".to_string(), "b".to_string()]);
+
+    let t: V64<u8> = V64::from(vec![1u8, 2, 3]);
+    assert_eq!(t, [1, 2, 3]);
+    assert_eq!(0, t.bytes_on_heap());
+    let v: Vec<u8> = t.into();
+    assert_eq!(v, [1, 2, 3]);
+
+    let t: V64<()> = V64::from(vec![(); 5]);
+    assert_eq!(5, t.len());
+    assert_eq!(5, Vec::from(t).len());
+
+    let t: V64<u32> = V64::from(Vec::with_capacity(4));
+    assert!(t.is_empty());
+    assert_eq!(0, Vec::from(t).capacity());
+}
+
+#[test]
+fn test_into_shared_slices() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    let b: Box<[String]> = v64!["x".to_string()].into();
+    assert_eq!(&*b, ["x".to_string()]);
+    let r: Rc<[String]> = v64!["x".to_string(), "y".to_string()].into();
+    assert_eq!(&*r, ["x".to_string(), "y".to_string()]);
+    let a: Arc<[u16]> = v64![1u16, 2].into();
+    assert_eq!(&*a, [1, 2]);
+    let a: Arc<[u16]> = V64::new().into();
+    assert!(a.is_empty());
+}
+
 #[test]
 fn test_append() {
     let mut v64 = v64![1, 2, 3];
@@ -987,6 +1041,53 @@ fn drain_filter_complex() {
     }
 }
 
+#[test]
+fn test_push_after_shrinking_to_nothing() {
+    let mut v: V64<u64> = V64::with_capacity(20);
+    v.shrink_to_fit();
+    for i in 0..100 {
+        v.push(i);
+    }
+    assert_eq!(100, v.len());
+    assert_eq!(99, v[99]);
+}
+
+#[test]
+fn test_reserve_is_amortized() {
+    let mut v: V64<u64> = V64::new();
+    let mut capacities = vec![];
+    for i in 0..10_000 {
+        v.reserve(1);
+        if capacities.last() != Some(&v.capacity()) {
+            capacities.push(v.capacity());
+        }
+        v.push(i);
+    }
+    assert!(capacities.len() < 16, "{:?}", capacities);
+    let cap = v.capacity();
+    let ptr = v.as_ptr();
+    v.reserve(cap - v.len());
+    assert_eq!(cap, v.capacity());
+    assert_eq!(ptr, v.as_ptr());
+}
+
+#[test]
+fn test_large_growth_and_shrink() {
+    let mut v: V64<u32> = V64::new();
+    for i in 0..1_000_000 {
+        v.push(i);
+    }
+    v.extend(1_000_000..1_500_000);
+    assert_eq!(1_500_000, v.len());
+    assert!(v.iter().enumerate().all(|(i, x)| i as u32 == *x));
+    v.truncate(100_000);
+    v.shrink_to_fit();
+    assert_eq!(100_000, v.capacity());
+    v.reserve_exact(1_000_000);
+    assert_eq!(1_100_000, v.capacity());
+    assert!(v.iter().enumerate().all(|(i, x)| i as u32 == *x));
+}
+
 #[test]
 fn test_reserve_exact() {
     // This is all the same as test_reserve
@@ -1019,3 +1120,98 @@ fn test_append_empty() {
     assert_eq!(1, a.len());
 }
 
+
+#[test]
+fn test_shrink_to_fit_back_to_stack() {
+    let mut v: V64<u8> = (0..20).collect();
+    assert!(v.bytes_on_heap() > 0);
+    v.truncate(7);
+    v.shrink_to_fit();
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [0, 1, 2, 3, 4, 5, 6]);
+    v.push(7);
+    assert!(v.bytes_on_heap() > 0);
+    assert_eq!(v, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+    let mut v: V64<String> = V64::with_capacity(4);
+    v.push("a".to_string());
+    v.clear();
+    v.shrink_to_fit();
+    assert_eq!(0, v.bytes_on_heap());
+    assert!(v.is_empty());
+
+    let mut v: V64<u32> = V64::with_capacity(4);
+    v.push(1);
+    v.push(2);
+    v.shrink_to_fit();
+    assert_eq!(2, v.capacity());
+    v.pop();
+    v.shrink_to_fit();
+    assert_eq!(0, v.bytes_on_heap());
+    assert_eq!(v, [1]);
+}
+
+#[test]
+fn test_extend_from_within() {
+    let mut v = v64![String::from("a"), String::from("b"), String::from("c")];
+    v.extend_from_within(1..);
+    assert_eq!(v, ["a", "b", "c", "b", "c"]);
+    v.extend_from_within(..2);
+    assert_eq!(v, ["a", "b", "c", "b", "c", "a", "b"]);
+    v.extend_from_within(3..3);
+    assert_eq!(7, v.len());
+
+    let mut e: V64<u32> = V64::new();
+    e.extend_from_within(..);
+    assert!(e.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn test_extend_from_within_out_of_bounds() {
+    let mut v = v64![1, 2, 3];
+    v.extend_from_within(2..4);
+}
+
+#[test]
+fn test_extend_with_vectors() {
+    let mut v: V64<String> = V64::new();
+    v.extend(vec![v64![String::from("a")], V64::new(), v64![String::from("b"), String::from("c")]]);
+    v.extend(vec![thinvec![String::from("d")]]);
+    v.extend(vec![vec![String::from("e"), String::from("f")], vec![]]);
+    assert_eq!(v, ["a", "b", "c", "d", "e", "f"]);
+}
+
+#[test]
+fn test_extend_reserves_up_front() {
+    let mut v: V64<u64> = V64::new();
+    v.extend(0..100);
+    assert_eq!(100, v.capacity());
+    assert_eq!(4950u64, v.iter().sum());
+
+    // a lying size hint still works
+    let mut w: V64<u64> = V64::new();
+    w.extend((0..100).filter(|x| x % 2 == 0));
+    assert_eq!(50, w.len());
+}
+
+#[test]
+fn test_extend_from_slice_panicking_clone() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[derive(Debug, PartialEq)]
+    struct Bomb(u32);
+    impl Clone for Bomb {
+        fn clone(&self) -> Bomb {
+            if self.0 == 3 {
+                panic!("boom");
+            }
+            Bomb(self.0)
+        }
+    }
+
+    let mut v = v64![Bomb(0)];
+    let source = [Bomb(1), Bomb(2), Bomb(3), Bomb(4)];
+    assert!(catch_unwind(AssertUnwindSafe(|| v.extend_from_slice(&source))).is_err());
+    assert_eq!(v, [Bomb(0), Bomb(1), Bomb(2)]);
+}
@@ -0,0 +1,160 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+extern crate thincollections;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use thincollections::thin_map::ThinMap;
+use thincollections::thin_sentinel::ThinSentinel;
+use thincollections::thin_set::ThinSet;
+use thincollections::thin_v64str::V64Str;
+
+fn hash_of<T: Hash + ?Sized>(t: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    t.hash(&mut h);
+    h.finish()
+}
+
+#[test]
+fn test_size() {
+    assert_eq!(8, mem::size_of::<V64Str>());
+    assert_eq!(8, mem::size_of::<Option<V64Str>>());
+}
+
+#[test]
+fn test_inline_then_heap() {
+    let mut s = V64Str::new();
+    for (i, c) in "ABCDEFG".chars().enumerate() {
+        s.push(c);
+        assert_eq!(i + 1, s.len());
+        assert_eq!(0, s.bytes_on_heap());
+    }
+    assert_eq!("ABCDEFG", s);
+    s.push('H');
+    assert!(s.bytes_on_heap() > 0);
+    assert_eq!("ABCDEFGH", s);
+    s.push_str(" and some more");
+    assert_eq!("ABCDEFGH and some more", s.as_str());
+}
+
+#[test]
+fn test_multi_byte() {
+    let mut s = V64Str::from("añ€");
+    assert_eq!(6, s.len());
+    assert_eq!(0, s.bytes_on_heap());
+    s.push('😀');
+    assert_eq!("añ€😀", s);
+    assert_eq!(Some('😀'), s.pop());
+    assert_eq!(Some('€'), s.pop());
+    s.truncate(1);
+    assert_eq!("a", s);
+}
+
+#[test]
+#[should_panic]
+fn test_truncate_not_on_boundary() {
+    let mut s = V64Str::from("ñ");
+    s.truncate(1);
+}
+
+#[test]
+fn test_eq_ord_hash() {
+    let a = V64Str::from("USD");
+    let b = V64Str::from(String::from("USD"));
+    let c = V64Str::from("a longer string on the heap");
+    assert_eq!(a, b);
+    assert_eq!(a, "USD");
+    assert_eq!("USD", a);
+    assert_eq!(a, String::from("USD"));
+    assert!(a < c);
+    assert!(V64Str::from("EUR") < a);
+    assert_eq!(hash_of(&a), hash_of(&b));
+    assert_eq!(hash_of(&a), hash_of("USD"));
+    assert_eq!(hash_of(&c), hash_of("a longer string on the heap"));
+}
+
+#[test]
+fn test_display_debug() {
+    let s = V64Str::from("JPY");
+    assert_eq!("JPY", format!("{}", s));
+    assert_eq!("\"JPY\"", format!("{:?}", s));
+    let mut w = V64Str::new();
+    write!(w, "{}-{}", 12, "ab").unwrap();
+    assert_eq!("12-ab", w);
+}
+
+#[test]
+fn test_from_utf8() {
+    let s = V64Str::from("tag");
+    let bytes = s.into_bytes();
+    assert_eq!(b"tag", &bytes[..]);
+    let s = V64Str::from_utf8(bytes).unwrap();
+    assert_eq!("tag", s);
+    let mut bad = s.into_bytes();
+    bad.push(0xFF);
+    assert!(V64Str::from_utf8(bad).is_err());
+}
+
+#[test]
+fn test_collect_and_clone() {
+    let s: V64Str = "hello world".chars().filter(|c| *c != 'o').collect();
+    assert_eq!("hell wrld", s);
+    let t = s.clone();
+    assert_eq!(s, t);
+    let u: V64Str = vec!["ab", "cd"].into_iter().collect();
+    assert_eq!("abcd", u);
+    assert_eq!(V64Str::new(), V64Str::default());
+}
+
+#[test]
+fn test_sentinels_differ_from_strings() {
+    assert!(V64Str::SENTINEL_ZERO != V64Str::SENTINEL_ONE);
+    assert_eq!(1, V64Str::SENTINEL_ZERO.as_bytes().len());
+    assert_eq!(0, V64Str::SENTINEL_ZERO.bytes_on_heap());
+    assert!(V64Str::SENTINEL_ZERO != V64Str::new());
+    assert!(V64Str::SENTINEL_ONE != V64Str::from("\u{ff}"));
+}
+
+#[test]
+fn test_map_key() {
+    let mut map = ThinMap::new();
+    for i in 0..1000 {
+        let key = V64Str::from(format!("k{}", i));
+        assert_eq!(None, map.insert(key, i));
+    }
+    assert_eq!(1000, map.len());
+    for i in 0..1000 {
+        assert_eq!(Some(&i), map.get(&V64Str::from(format!("k{}", i))));
+    }
+    for i in 0..500 {
+        assert_eq!(Some(i), map.remove(&V64Str::from(format!("k{}", i))));
+    }
+    assert_eq!(500, map.len());
+    assert_eq!(None, map.get(&V64Str::from("k1")));
+    assert_eq!(Some(&999), map.get(&V64Str::from("k999")));
+    map.insert(V64Str::from(""), -1);
+    assert_eq!(Some(&-1), map.get(&V64Str::new()));
+}
+
+#[test]
+fn test_set_element() {
+    let mut set = ThinSet::new();
+    set.insert(V64Str::from("CA"));
+    set.insert(V64Str::from("US"));
+    set.insert(V64Str::from("a country with a long name"));
+    assert!(!set.insert(V64Str::from("US")));
+    assert_eq!(3, set.len());
+    assert!(set.contains(&V64Str::from("a country with a long name")));
+    assert!(set.remove(&V64Str::from("a country with a long name")));
+    assert_eq!(2, set.len());
+}
@@ -0,0 +1,303 @@
+// Copyright 2018 Mohammad Rezaei.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+
+#[macro_use]
+extern crate thincollections;
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use thincollections::thin_vec::ThinVec;
+use thincollections::thin_vec_deque::ThinVecDeque;
+
+thread_local! {
+    static DROPS: Cell<usize> = Cell::new(0);
+}
+
+struct Tracked(u32);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        DROPS.with(|d| d.set(d.get() + 1));
+    }
+}
+
+fn drops() -> usize {
+    DROPS.with(|d| d.get())
+}
+
+fn contents<T: Clone>(q: &ThinVecDeque<T>) -> Vec<T> {
+    q.iter().cloned().collect()
+}
+
+#[test]
+fn test_size_and_empty() {
+    assert_eq!(size_of::<usize>(), size_of::<ThinVecDeque<u8>>());
+    assert_eq!(size_of::<usize>(), size_of::<Option<ThinVecDeque<u8>>>());
+    let q: ThinVecDeque<u64> = ThinVecDeque::new();
+    assert_eq!(0, q.capacity());
+    assert_eq!((&[][..], &[][..]), q.as_slices());
+    assert_eq!(None, q.front());
+}
+
+#[test]
+fn test_push_pop_wraps() {
+    let mut q = ThinVecDeque::with_capacity(4);
+    q.push_back(1);
+    q.push_back(2);
+    q.push_front(0);
+    q.push_front(-1);
+    assert_eq!(4, q.capacity());
+    let (a, b) = q.as_slices();
+    assert_eq!(a, [-1, 0]);
+    assert_eq!(b, [1, 2]);
+    assert_eq!(contents(&q), [-1, 0, 1, 2]);
+
+    // grows while wrapped
+    q.push_back(3);
+    assert!(q.capacity() > 4);
+    assert_eq!(contents(&q), [-1, 0, 1, 2, 3]);
+    assert_eq!(Some(-1), q.pop_front());
+    assert_eq!(Some(3), q.pop_back());
+    assert_eq!(Some(&0), q.front());
+    assert_eq!(Some(&2), q.back());
+    *q.front_mut().unwrap() = 10;
+    q[2] = 20;
+    assert_eq!(contents(&q), [10, 1, 20]);
+    assert_eq!(3, q.iter().rev().count());
+    assert!(q.contains(&20));
+}
+
+#[test]
+fn test_make_contiguous() {
+    let mut q = ThinVecDeque::with_capacity(8);
+    q.extend(vec![3, 4, 5, 6, 7]);
+    q.push_front(2);
+    q.push_front(1);
+    q.push_front(0);
+    assert!(!q.as_slices().1.is_empty());
+    assert_eq!(q.make_contiguous(), [0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(q.as_slices().0, [0, 1, 2, 3, 4, 5, 6, 7]);
+    q.push_front(-1);
+    assert_eq!(contents(&q), [-1, 0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_rotate() {
+    // full buffer: only the head moves
+    let mut q: ThinVecDeque<u32> = ThinVecDeque::with_capacity(5);
+    q.extend(0..5);
+    q.rotate_left(2);
+    assert_eq!(contents(&q), [2, 3, 4, 0, 1]);
+    q.rotate_right(4);
+    assert_eq!(contents(&q), [3, 4, 0, 1, 2]);
+
+    let mut q: ThinVecDeque<String> = ThinVecDeque::with_capacity(16);
+    q.extend((0..10).map(|x| x.to_string()));
+    q.rotate_left(3);
+    q.rotate_left(8);
+    q.rotate_right(0);
+    q.rotate_left(10);
+    let v: Vec<_> = q.iter().map(|s| s.parse::<u32>().unwrap()).collect();
+    assert_eq!(v, [1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+}
+
+#[test]
+#[should_panic]
+fn test_rotate_too_far() {
+    let mut q: ThinVecDeque<u32> = (0..3).collect();
+    q.rotate_left(4);
+}
+
+#[test]
+fn test_drain() {
+    // front shorter than the tail: the front moves
+    let mut q: ThinVecDeque<u32> = (0..10).collect();
+    assert_eq!(q.drain(1..3).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(contents(&q), [0, 3, 4, 5, 6, 7, 8, 9]);
+
+    // tail shorter than the front: the tail moves
+    assert_eq!(q.drain(5..7).rev().collect::<Vec<_>>(), [8, 7]);
+    assert_eq!(contents(&q), [0, 3, 4, 5, 6, 9]);
+
+    assert_eq!(q.drain(..).count(), 6);
+    assert!(q.is_empty());
+
+    // wrapped
+    let mut q = ThinVecDeque::with_capacity(8);
+    q.extend(vec![4, 5, 6, 7]);
+    for x in (0..4).rev() {
+        q.push_front(x);
+    }
+    q.drain(3..6);
+    assert_eq!(contents(&q), [0, 1, 2, 6, 7]);
+    q.drain(1..=2);
+    assert_eq!(contents(&q), [0, 6, 7]);
+}
+
+#[test]
+fn test_drain_drops_unconsumed() {
+    let before = drops();
+    let mut q: ThinVecDeque<Tracked> = (0..8).map(Tracked).collect();
+    {
+        let mut d = q.drain(2..6);
+        assert_eq!(2, d.next().unwrap().0);
+    }
+    assert_eq!(before + 4, drops());
+    assert_eq!(q.iter().map(|t| t.0).collect::<Vec<_>>(), [0, 1, 6, 7]);
+    drop(q);
+    assert_eq!(before + 8, drops());
+}
+
+#[test]
+fn test_from_thinvec_is_free() {
+    let v: ThinVec<u64> = thinvec![1, 2, 3];
+    let ptr = v.as_ptr();
+    let mut q = ThinVecDeque::from(v);
+    assert_eq!(ptr, q.as_slices().0.as_ptr());
+    q.push_back(4);
+    assert_eq!(contents(&q), [1, 2, 3, 4]);
+    let v = ThinVec::from(q);
+    assert_eq!(v, [1, 2, 3, 4]);
+
+    // moving the head copies the block once, after that the deque is a normal ring buffer
+    let mut q = ThinVecDeque::from(v);
+    q.push_front(0);
+    assert_eq!(Some(4), q.pop_back());
+    assert_eq!(Some(0), q.pop_front());
+    assert_eq!(ThinVec::from(q), [1, 2, 3]);
+
+    let q: ThinVecDeque<u8> = ThinVecDeque::from(ThinVec::new());
+    assert_eq!(0, q.capacity());
+}
+
+#[test]
+fn test_drain_front_keeps_adopted_block() {
+    let v: ThinVec<u64> = (0..10).collect();
+    let ptr = v.as_ptr();
+    let mut q = ThinVecDeque::from(v);
+    assert!(q.drain(1..3).eq(vec![1, 2]));
+    assert_eq!(ptr, q.as_slices().0.as_ptr());
+    assert!(q.drain(..2).eq(vec![0, 3]));
+    assert_eq!(contents(&q), [4, 5, 6, 7, 8, 9]);
+    let v = ThinVec::from(q);
+    assert_eq!(ptr, v.as_ptr());
+    assert_eq!(v, [4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn test_zero_sized() {
+    let mut q = ThinVecDeque::new();
+    q.push_back(());
+    q.push_front(());
+    q.extend(vec![(); 5]);
+    assert_eq!(7, q.len());
+    assert_eq!(Some(()), q.pop_front());
+    q.rotate_left(2);
+    assert_eq!(2, q.drain(1..3).count());
+    assert_eq!(4, q.len());
+    assert_eq!(4, ThinVec::from(q).len());
+}
+
+#[test]
+fn test_shrink_clear_and_drop() {
+    let before = drops();
+    let mut q = ThinVecDeque::with_capacity(32);
+    q.push_back(Tracked(1));
+    q.push_front(Tracked(0));
+    q.shrink_to_fit();
+    assert_eq!(2, q.capacity());
+    assert_eq!(q.iter().map(|t| t.0).collect::<Vec<_>>(), [0, 1]);
+    q.clear();
+    assert_eq!(before + 2, drops());
+    q.shrink_to_fit();
+    assert_eq!(0, q.capacity());
+    q.push_back(Tracked(2));
+    q.push_front(Tracked(3));
+    drop(q);
+    assert_eq!(before + 4, drops());
+}
+
+#[test]
+fn test_traits() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash<T: Hash>(t: &T) -> u64 {
+        let mut h = DefaultHasher::new();
+        t.hash(&mut h);
+        h.finish()
+    }
+
+    let mut a = ThinVecDeque::with_capacity(4);
+    a.push_back(3);
+    a.push_back(4);
+    a.push_front(2);
+    a.push_front(1);
+    let b: ThinVecDeque<i32> = (1..5).collect();
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(a.clone(), b);
+    assert!(a < (2..3).collect());
+    assert_eq!("[1, 2, 3, 4]", format!("{:?}", a));
+    for x in &mut a {
+        *x *= 2;
+    }
+    assert_eq!(a.into_iter().rev().collect::<Vec<_>>(), [8, 6, 4, 2]);
+}
+
+#[test]
+fn test_matches_vec_deque() {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let mut q: ThinVecDeque<u64> = ThinVecDeque::from(thinvec![7, 8, 9]);
+    let mut expected: VecDeque<u64> = vec![7, 8, 9].into_iter().collect();
+    for _ in 0..20_000 {
+        let r = next();
+        match r % 9 {
+            0 | 1 => {
+                q.push_back(r);
+                expected.push_back(r);
+            }
+            2 | 3 => {
+                q.push_front(r);
+                expected.push_front(r);
+            }
+            4 => assert_eq!(expected.pop_back(), q.pop_back()),
+            5 => assert_eq!(expected.pop_front(), q.pop_front()),
+            6 if !expected.is_empty() => {
+                let n = (r >> 8) as usize % expected.len();
+                q.rotate_left(n);
+                expected.rotate_left(n);
+            }
+            7 if !expected.is_empty() => {
+                let a = (r >> 8) as usize % expected.len();
+                let b = a + (r >> 32) as usize % (expected.len() - a).min(3);
+                assert!(q.drain(a..b).eq(expected.drain(a..b)));
+            }
+            _ => {
+                if r & 0x100 == 0 {
+                    q.make_contiguous();
+                } else {
+                    q.shrink_to_fit();
+                }
+            }
+        }
+        assert_eq!(expected.len(), q.len());
+        assert_eq!(expected.front(), q.front());
+        assert_eq!(expected.back(), q.back());
+    }
+    assert!(q.iter().eq(expected.iter()));
+}